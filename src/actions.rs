@@ -0,0 +1,164 @@
+//! Scripted page actions run before capture
+//!
+//! Requests can carry an ordered list of actions such as dismissing a cookie
+//! banner, opening a menu or logging in. Actions run after the wait conditions
+//! have been met and before the screenshot is taken. A failing action aborts
+//! the capture with `ScreenshotError::ActionFailed`, naming the step.
+
+use crate::ScreenshotError;
+use chromiumoxide::element::Element;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// A single scripted interaction with the page
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::PageAction;
+///
+/// let actions: Vec<PageAction> = serde_json::from_str(r##"[
+///     {"type": "click", "selector": "#accept-cookies"},
+///     {"type": "type", "selector": "input[name=q]", "text": "rust"},
+///     {"type": "press_key", "key": "Enter"},
+///     {"type": "wait", "ms": 500}
+/// ]"##).unwrap();
+/// assert_eq!(actions.len(), 4);
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PageAction {
+    /// Click the first element matching the selector
+    Click { selector: String },
+    /// Click the element to focus it, then type the text key by key
+    Type { selector: String, text: String },
+    /// Press a key such as `Enter` or `Escape`, optionally focusing an element first
+    PressKey {
+        key: String,
+        #[serde(default)]
+        selector: Option<String>,
+    },
+    /// Move the mouse over the element
+    Hover { selector: String },
+    /// Scroll the element into view
+    ScrollTo { selector: String },
+    /// Select the option with the given value in a `<select>` element
+    SelectOption { selector: String, value: String },
+    /// Pause for a fixed number of milliseconds
+    Wait { ms: u64 },
+    /// Evaluate a JavaScript expression (promises are awaited)
+    Evaluate { expression: String },
+}
+
+impl PageAction {
+    /// Short human readable description used in logs and error messages
+    pub fn describe(&self) -> String {
+        match self {
+            PageAction::Click { selector } => format!("click '{selector}'"),
+            PageAction::Type { selector, .. } => format!("type into '{selector}'"),
+            PageAction::PressKey { key, .. } => format!("press key '{key}'"),
+            PageAction::Hover { selector } => format!("hover '{selector}'"),
+            PageAction::ScrollTo { selector } => format!("scroll to '{selector}'"),
+            PageAction::SelectOption { selector, value } => {
+                format!("select '{value}' in '{selector}'")
+            }
+            PageAction::Wait { ms } => format!("wait {ms}ms"),
+            PageAction::Evaluate { .. } => "evaluate script".to_string(),
+        }
+    }
+
+    /// Perform the action on the page
+    pub async fn run(&self, page: &Page) -> Result<(), ScreenshotError> {
+        match self {
+            PageAction::Click { selector } => {
+                find(page, selector).await?.click().await.map_err(failed)?;
+            }
+            PageAction::Type { selector, text } => {
+                find(page, selector)
+                    .await?
+                    .click()
+                    .await
+                    .map_err(failed)?
+                    .type_str(text)
+                    .await
+                    .map_err(failed)?;
+            }
+            PageAction::PressKey { key, selector } => {
+                let element = match selector {
+                    Some(selector) => {
+                        let element = find(page, selector).await?;
+                        element.focus().await.map_err(failed)?;
+                        element
+                    }
+                    None => find(page, "body").await?,
+                };
+                element.press_key(key).await.map_err(failed)?;
+            }
+            PageAction::Hover { selector } => {
+                find(page, selector).await?.hover().await.map_err(failed)?;
+            }
+            PageAction::ScrollTo { selector } => {
+                find(page, selector)
+                    .await?
+                    .scroll_into_view()
+                    .await
+                    .map_err(failed)?;
+            }
+            PageAction::SelectOption { selector, value } => {
+                let element = find(page, selector).await?;
+                let value = serde_json::to_string(value)?;
+                let selected = element
+                    .call_js_fn(
+                        format!(
+                            "function() {{ \
+                             if (!Array.from(this.options || []).some(o => o.value === {value})) return false; \
+                             this.value = {value}; \
+                             this.dispatchEvent(new Event('input', {{ bubbles: true }})); \
+                             this.dispatchEvent(new Event('change', {{ bubbles: true }})); \
+                             return true; }}"
+                        ),
+                        false,
+                    )
+                    .await
+                    .map_err(failed)?;
+
+                if selected.result.value != Some(serde_json::Value::Bool(true)) {
+                    return Err(ScreenshotError::ElementNotFound(format!(
+                        "option {value} in '{selector}'"
+                    )));
+                }
+            }
+            PageAction::Wait { ms } => sleep(Duration::from_millis(*ms)).await,
+            PageAction::Evaluate { expression } => {
+                page.evaluate_expression(expression.as_str())
+                    .await
+                    .map_err(failed)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run actions in order, reporting the 1-based step that failed
+pub async fn run_actions(page: &Page, actions: &[PageAction]) -> Result<(), ScreenshotError> {
+    for (index, action) in actions.iter().enumerate() {
+        action.run(page).await.map_err(|e| {
+            ScreenshotError::ActionFailed(index + 1, action.describe(), e.to_string())
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn find(page: &Page, selector: &str) -> Result<Element, ScreenshotError> {
+    page.find_element(selector)
+        .await
+        .map_err(|_| ScreenshotError::ElementNotFound(selector.to_string()))
+}
+
+fn failed(e: chromiumoxide::error::CdpError) -> ScreenshotError {
+    ScreenshotError::PageError(e.to_string())
+}
@@ -0,0 +1,436 @@
+//! Baseline store for visual regression
+//!
+//! A baseline store is a directory of approved screenshots with a
+//! `baselines.json` manifest mapping each `BaselineKey` (URL, device,
+//! viewport and result label) to its image file. `baseline update` accepts
+//! new captures into the store; `baseline check` compares fresh captures
+//! against it and produces a `BaselineReport` of changed, new and missing
+//! entries.
+
+use crate::{
+    diff_result, load_image, sanitize_filename, DiffOptions, ScreenshotError, ScreenshotResult,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// File name of the store manifest
+pub const BASELINE_MANIFEST: &str = "baselines.json";
+
+/// Identity of a baseline: the page and how it was rendered
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct BaselineKey {
+    pub url: String,
+    /// Device preset the page was captured with, if any
+    pub device: Option<String>,
+    /// Viewport as `WIDTHxHEIGHT`, suffixed with `@2x` and so on for
+    /// device scale factors other than 1
+    pub viewport: String,
+    /// Result label of multi-viewport and per-element captures
+    pub label: Option<String>,
+}
+
+impl BaselineKey {
+    /// Key of a successful capture made with the given device preset
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use screenshot_tool::{BaselineKey, ScreenshotResult};
+    /// # fn key(result: &ScreenshotResult) {
+    /// let key = BaselineKey::for_result(result, Some("iPhone 15"));
+    /// // e.g. "example.com_about__iphone-15__393x852@3x"
+    /// println!("{}", key.file_stem());
+    /// # }
+    /// ```
+    pub fn for_result(result: &ScreenshotResult, device: Option<&str>) -> Self {
+        let viewport = &result.metadata.viewport;
+        let mut size = format!("{}x{}", viewport.width, viewport.height);
+        if viewport.device_scale_factor != 1.0 {
+            size.push_str(&format!("@{}x", viewport.device_scale_factor));
+        }
+
+        Self {
+            url: result.url.clone(),
+            device: device.map(str::to_string),
+            viewport: size,
+            label: result.metadata.label(),
+        }
+    }
+
+    /// Readable file name stem, without extension
+    pub fn file_stem(&self) -> String {
+        let url = self
+            .url
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_end_matches('/');
+        let slug = |value: &str| {
+            sanitize_filename(
+                &value
+                    .to_lowercase()
+                    .replace([' ', '?', '&', '=', '#', '%'], "-"),
+            )
+            .replace('.', "-")
+        };
+
+        let mut stem = sanitize_filename(&url.replace(['/', '?', '&', '=', '#', '%'], "_"));
+        stem.push_str("__");
+        stem.push_str(
+            &self
+                .device
+                .as_deref()
+                .map(slug)
+                .unwrap_or_else(|| "default".to_string()),
+        );
+        stem.push_str("__");
+        stem.push_str(&self.viewport);
+        if let Some(label) = &self.label {
+            stem.push_str("__");
+            stem.push_str(&slug(label));
+        }
+
+        stem
+    }
+}
+
+/// An approved image in the store
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BaselineEntry {
+    #[serde(flatten)]
+    pub key: BaselineKey,
+    /// Image file, relative to the store directory
+    pub file: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Manifest {
+    entries: Vec<BaselineEntry>,
+}
+
+/// A directory of approved screenshots
+///
+/// Changes made with `accept` and `remove` are written to the image files
+/// immediately and to the manifest by `save`.
+#[derive(Debug)]
+pub struct BaselineStore {
+    root: PathBuf,
+    entries: BTreeMap<BaselineKey, BaselineEntry>,
+}
+
+impl BaselineStore {
+    /// Open the store in `root`, which need not exist yet
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, ScreenshotError> {
+        let root = root.into();
+        let manifest_path = root.join(BASELINE_MANIFEST);
+
+        let manifest: Manifest = if manifest_path.exists() {
+            let content = std::fs::read_to_string(&manifest_path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                ScreenshotError::ConfigurationError(format!(
+                    "Invalid baseline manifest {}: {e}",
+                    manifest_path.display()
+                ))
+            })?
+        } else {
+            Manifest::default()
+        };
+
+        let entries = manifest
+            .entries
+            .into_iter()
+            .map(|entry| (entry.key.clone(), entry))
+            .collect();
+
+        Ok(Self { root, entries })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &BaselineKey) -> Option<&BaselineEntry> {
+        self.entries.get(key)
+    }
+
+    /// Entries ordered by key
+    pub fn entries(&self) -> impl Iterator<Item = &BaselineEntry> {
+        self.entries.values()
+    }
+
+    /// Absolute path of an entry's image
+    pub fn image_path(&self, entry: &BaselineEntry) -> PathBuf {
+        self.root.join(&entry.file)
+    }
+
+    /// Store a capture as the baseline for `key`, replacing any previous one
+    pub fn accept(
+        &mut self,
+        key: BaselineKey,
+        result: &ScreenshotResult,
+    ) -> Result<&BaselineEntry, ScreenshotError> {
+        if !result.success {
+            return Err(ScreenshotError::CaptureFailed(format!(
+                "Cannot accept a failed capture of {}",
+                result.url
+            )));
+        }
+
+        std::fs::create_dir_all(&self.root)?;
+        let file = match self.entries.get(&key) {
+            // Keep the file name, but not a stale extension
+            Some(entry)
+                if entry
+                    .file
+                    .ends_with(&format!(".{}", result.format.extension())) =>
+            {
+                entry.file.clone()
+            }
+            Some(entry) => {
+                std::fs::remove_file(self.root.join(&entry.file)).ok();
+                self.unique_file(&key, result)
+            }
+            None => self.unique_file(&key, result),
+        };
+        std::fs::write(self.root.join(&file), &result.data)?;
+
+        let entry = BaselineEntry {
+            key: key.clone(),
+            file,
+            updated_at: Utc::now(),
+        };
+        self.entries.insert(key.clone(), entry);
+
+        Ok(&self.entries[&key])
+    }
+
+    /// Delete an entry and its image, returning whether it existed
+    pub fn remove(&mut self, key: &BaselineKey) -> Result<bool, ScreenshotError> {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                let path = self.image_path(&entry);
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Write the manifest
+    pub fn save(&self) -> Result<(), ScreenshotError> {
+        std::fs::create_dir_all(&self.root)?;
+        let manifest = Manifest {
+            entries: self.entries.values().cloned().collect(),
+        };
+        std::fs::write(
+            self.root.join(BASELINE_MANIFEST),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Compare the results of a capture run against their baselines
+    ///
+    /// `device` is the preset the results were captured with. Stored entries
+    /// the run did not produce are reported as missing, except those of pages
+    /// that failed to capture; `scope` limits this to the captured pages when
+    /// the run covers only part of the store. Entries whose image has been
+    /// deleted are missing too, and those whose image cannot be read are
+    /// failed. Diff images of changed entries are written to `diff_dir` when
+    /// given. This decodes and compares images synchronously; run it on a
+    /// blocking thread from async code.
+    pub fn check(
+        &self,
+        results: &[ScreenshotResult],
+        device: Option<&str>,
+        scope: MissingScope,
+        options: &DiffOptions,
+        diff_dir: Option<&Path>,
+    ) -> Result<BaselineReport, ScreenshotError> {
+        let mut report = BaselineReport::new();
+        let mut seen = BTreeSet::new();
+
+        for result in results {
+            if !result.success {
+                report.failed.push(FailedCapture {
+                    url: result.url.clone(),
+                    error: result
+                        .error
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_else(|| "Unknown error".to_string()),
+                });
+                continue;
+            }
+
+            let key = BaselineKey::for_result(result, device);
+            seen.insert(key.clone());
+
+            let Some(entry) = self.get(&key) else {
+                report.new.push(key);
+                continue;
+            };
+
+            // The manifest can outlive its images; report those baselines
+            // rather than aborting the whole check
+            let baseline = self.image_path(entry);
+            if !baseline.exists() {
+                report.missing.push(key);
+                continue;
+            }
+            let diff = match load_image(&baseline)
+                .and_then(|image| diff_result(result, &image, options))
+            {
+                Ok(diff) => diff,
+                Err(e) => {
+                    report.failed.push(FailedCapture {
+                        url: result.url.clone(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if !diff.exceeds(options.fail_threshold) {
+                report.unchanged.push(key);
+                continue;
+            }
+
+            let diff_image = match diff_dir {
+                Some(dir) => {
+                    std::fs::create_dir_all(dir)?;
+                    let path = dir.join(format!("{}.diff.png", key.file_stem()));
+                    std::fs::write(&path, diff.diff_png()?)?;
+                    Some(path)
+                }
+                None => None,
+            };
+
+            report.changed.push(ChangedBaseline {
+                key,
+                baseline,
+                mismatch_percentage: diff.mismatch_percentage,
+                size_changed: diff.size_changed,
+                diff_image,
+            });
+        }
+
+        // A page that failed to load says nothing about its baselines
+        let failed: BTreeSet<&str> = results
+            .iter()
+            .filter(|result| !result.success)
+            .map(|result| result.url.as_str())
+            .collect();
+        let captured: BTreeSet<&str> = results
+            .iter()
+            .filter(|result| result.success)
+            .map(|result| result.url.as_str())
+            .collect();
+        report.missing.extend(
+            self.entries
+                .keys()
+                .filter(|key| !seen.contains(*key) && !failed.contains(key.url.as_str()))
+                .filter(|key| match scope {
+                    MissingScope::All => true,
+                    MissingScope::CapturedPages => captured.contains(key.url.as_str()),
+                })
+                .cloned(),
+        );
+        report.passed =
+            report.changed.is_empty() && report.missing.is_empty() && report.failed.is_empty();
+
+        Ok(report)
+    }
+
+    /// File name for a new entry, numbered if the readable name is taken
+    fn unique_file(&self, key: &BaselineKey, result: &ScreenshotResult) -> String {
+        let stem = key.file_stem();
+        let extension = result.format.extension();
+        let taken: BTreeSet<&str> = self
+            .entries
+            .values()
+            .map(|entry| entry.file.as_str())
+            .collect();
+
+        let mut file = format!("{stem}.{extension}");
+        let mut number = 2;
+        while taken.contains(file.as_str()) {
+            file = format!("{stem}-{number}.{extension}");
+            number += 1;
+        }
+
+        file
+    }
+}
+
+/// Which stored baselines a check may report as missing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingScope {
+    /// Every baseline the run did not produce
+    #[default]
+    All,
+    /// Only baselines of pages captured in the run, for checking part of the store
+    CapturedPages,
+}
+
+/// A capture that differs from its baseline beyond the failure threshold
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedBaseline {
+    #[serde(flatten)]
+    pub key: BaselineKey,
+    /// Approved image the capture was compared with
+    pub baseline: PathBuf,
+    pub mismatch_percentage: f64,
+    pub size_changed: bool,
+    /// Highlighted diff image, if diff images were written
+    pub diff_image: Option<PathBuf>,
+}
+
+/// A capture that could not be made
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedCapture {
+    pub url: String,
+    pub error: String,
+}
+
+/// Outcome of a baseline check, written as JSON for CI
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineReport {
+    pub generated_at: DateTime<Utc>,
+    /// No entry changed, went missing or failed to capture; new entries
+    /// don't fail a check
+    pub passed: bool,
+    pub changed: Vec<ChangedBaseline>,
+    /// Captures without a baseline
+    pub new: Vec<BaselineKey>,
+    /// Baselines that the run did not produce
+    pub missing: Vec<BaselineKey>,
+    pub unchanged: Vec<BaselineKey>,
+    pub failed: Vec<FailedCapture>,
+}
+
+impl BaselineReport {
+    fn new() -> Self {
+        Self {
+            generated_at: Utc::now(),
+            passed: true,
+            changed: Vec::new(),
+            new: Vec::new(),
+            missing: Vec::new(),
+            unchanged: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+}
@@ -4,15 +4,20 @@
 //! shared across multiple screenshot operations for optimal performance and
 //! resource utilization.
 
-use crate::{create_browser_config_with_instance_id, Config, ScreenshotError};
+use crate::{create_browser_config_with_instance_id, Config, Metrics, ScreenshotError};
 use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use chromiumoxide::cdp::browser_protocol::target::{
+    CreateBrowserContextParams, CreateTargetParams,
+};
+use chromiumoxide::Page;
 use futures::StreamExt;
-use std::collections::VecDeque;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{Mutex, Notify, OwnedSemaphorePermit, Semaphore};
 use tokio::time::sleep;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// Current status of a browser instance in the pool
 ///
@@ -20,9 +25,9 @@ use tracing::{error, info, warn};
 /// for load balancing and error recovery.
 #[derive(Debug, Clone, Copy)]
 pub enum InstanceStatus {
-    /// Instance is ready and available for use
+    /// Instance is ready and has free tab slots
     Healthy,
-    /// Instance is currently processing a request
+    /// Instance has reached its tab limit
     Busy,
     /// Instance is not responding to commands
     Unresponsive,
@@ -54,6 +59,15 @@ pub struct BrowserInstance {
     pub created_at: Instant,
     /// Number of failures encountered by this instance
     pub failure_count: usize,
+    /// Number of tabs currently leased out from this instance
+    pub active_tabs: usize,
+    /// Maximum number of tabs this instance may have open at once
+    pub max_tabs: usize,
+    /// Signalled when a tab slot is returned or the instance fails
+    pub tab_released: Arc<Notify>,
+    /// Incremented on every restart, so that tabs leased from the previous
+    /// browser process are not returned to the new one
+    pub generation: u64,
 }
 
 impl BrowserInstance {
@@ -61,6 +75,7 @@ impl BrowserInstance {
         id: usize,
         browser: Browser,
         handler: tokio::task::JoinHandle<Result<(), chromiumoxide::error::CdpError>>,
+        max_tabs: usize,
     ) -> Self {
         Self {
             id,
@@ -71,22 +86,41 @@ impl BrowserInstance {
             status: InstanceStatus::Healthy,
             created_at: Instant::now(),
             failure_count: 0,
+            active_tabs: 0,
+            max_tabs,
+            tab_released: Arc::new(Notify::new()),
+            generation: 0,
         }
     }
 
-    pub fn mark_used(&mut self) {
+    /// Reserve a tab slot on this instance
+    pub fn open_tab(&mut self) {
         self.last_used = Instant::now();
         self.screenshot_count += 1;
-        self.status = InstanceStatus::Busy;
+        self.active_tabs += 1;
+        if self.active_tabs >= self.max_tabs {
+            self.status = InstanceStatus::Busy;
+        }
     }
 
-    pub fn mark_available(&mut self) {
-        self.status = InstanceStatus::Healthy;
+    /// Release a tab slot previously reserved with `open_tab`
+    pub fn close_tab(&mut self) {
+        self.active_tabs = self.active_tabs.saturating_sub(1);
+        if matches!(self.status, InstanceStatus::Busy) && self.active_tabs < self.max_tabs {
+            self.status = InstanceStatus::Healthy;
+        }
+        self.tab_released.notify_waiters();
+    }
+
+    /// Whether this instance can take another tab
+    pub fn has_capacity(&self) -> bool {
+        self.is_healthy() && !self.handler.is_finished() && self.active_tabs < self.max_tabs
     }
 
     pub fn mark_failed(&mut self) {
         self.failure_count += 1;
         self.status = InstanceStatus::Failed;
+        self.tab_released.notify_waiters();
     }
 
     pub fn is_healthy(&self) -> bool {
@@ -107,49 +141,174 @@ impl BrowserInstance {
     }
 }
 
-pub struct BrowserHandle {
-    pub browser: Arc<Mutex<Browser>>,
+/// Browser context a leased tab is opened in
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserContextMode {
+    /// The instance's default context, sharing cookies and cache with other tabs
+    Shared,
+    /// A fresh incognito context that is disposed when the lease is closed
+    Incognito,
+    /// A named incognito context reused by every lease with the same name
+    ///
+    /// The context lives until `BrowserPool::close_session` is called, it is
+    /// idle for `Config::session_idle_timeout`, or its browser instance is
+    /// restarted.
+    Session(String),
+}
+
+/// Named browser context pinned to the instance that created it
+#[derive(Debug, Clone)]
+struct SessionContext {
+    instance_id: usize,
+    context_id: BrowserContextId,
+    last_used: Instant,
+}
+
+/// A tab leased from the pool
+///
+/// The page is closed and its tab slot returned to the pool when the lease is
+/// closed or dropped. Incognito contexts created for the lease are disposed
+/// along with it.
+pub struct PageLease {
+    page: Option<Page>,
     pub instance_id: usize,
+    generation: u64,
+    browser: Arc<Mutex<Browser>>,
+    context: Option<BrowserContextId>,
     pool: Arc<BrowserPool>,
+    permit: Option<OwnedSemaphorePermit>,
 }
 
-impl BrowserHandle {
-    pub fn new(browser: Arc<Mutex<Browser>>, instance_id: usize, pool: Arc<BrowserPool>) -> Self {
-        Self {
-            browser,
-            instance_id,
-            pool,
+impl PageLease {
+    pub fn page(&self) -> &Page {
+        self.page.as_ref().expect("page lease already closed")
+    }
+
+    /// Close the page and release the tab slot immediately
+    pub async fn close(mut self) {
+        if let Some(page) = self.page.take() {
+            let _ = page.close().await;
+        }
+        if let Some(context) = self.context.take() {
+            dispose_context(&self.browser, context).await;
         }
+        self.pool
+            .release_tab(self.instance_id, self.generation)
+            .await;
+        self.permit.take();
     }
 }
 
-impl Drop for BrowserHandle {
+impl Drop for PageLease {
     fn drop(&mut self) {
+        let Some(page) = self.page.take() else {
+            return;
+        };
         let pool = self.pool.clone();
+        let browser = self.browser.clone();
+        let context = self.context.take();
         let instance_id = self.instance_id;
+        let generation = self.generation;
+        let permit = self.permit.take();
 
         tokio::spawn(async move {
-            pool.return_browser(instance_id).await;
+            let _ = page.close().await;
+            if let Some(context) = context {
+                dispose_context(&browser, context).await;
+            }
+            pool.release_tab(instance_id, generation).await;
+            drop(permit);
         });
     }
 }
 
+/// Browser context a new tab is opened in
+enum TabContext {
+    /// The instance's default context
+    Default,
+    /// A new incognito context
+    New,
+    Existing(BrowserContextId),
+}
+
+/// Open a blank tab, returning the id of its context unless it is the default
+///
+/// A context created for the tab is disposed again if the tab fails to open.
+async fn open_tab(
+    browser: &Mutex<Browser>,
+    context: TabContext,
+) -> Result<(Page, Option<BrowserContextId>), chromiumoxide::error::CdpError> {
+    // Only hold the browser lock while the tab is created
+    let browser = browser.lock().await;
+    let (context, created) = match context {
+        TabContext::Default => (None, false),
+        TabContext::Existing(context) => (Some(context), false),
+        TabContext::New => (
+            Some(
+                browser
+                    .create_browser_context(CreateBrowserContextParams::default())
+                    .await?,
+            ),
+            true,
+        ),
+    };
+
+    let mut params = CreateTargetParams::new("about:blank");
+    params.browser_context_id = context.clone();
+    match browser.new_page(params).await {
+        Ok(page) => Ok((page, context)),
+        Err(e) => {
+            // Don't leak a context created for this attempt
+            if let (Some(context), true) = (context, created) {
+                let _ = browser.dispose_browser_context(context).await;
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn dispose_context(browser: &Mutex<Browser>, context: BrowserContextId) {
+    if let Err(e) = browser.lock().await.dispose_browser_context(context).await {
+        warn!("Failed to dispose browser context: {}", e);
+    }
+}
+
 pub struct BrowserPool {
     instances: Arc<Mutex<Vec<BrowserInstance>>>,
-    available: Arc<Mutex<VecDeque<usize>>>,
+    /// One permit per tab slot across the whole pool
     semaphore: Arc<Semaphore>,
+    /// Named browser contexts created for `BrowserContextMode::Session`
+    sessions: Arc<Mutex<HashMap<String, SessionContext>>>,
     config: Config,
     is_shutting_down: Arc<std::sync::atomic::AtomicBool>,
+    metrics: Arc<Metrics>,
 }
 
 impl BrowserPool {
     pub async fn new(config: Config) -> Result<Self, ScreenshotError> {
+        Self::with_metrics(config, Arc::new(Metrics::new())).await
+    }
+
+    /// Create a pool that reports utilization and restarts to the given metrics
+    pub async fn with_metrics(
+        config: Config,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, ScreenshotError> {
+        if config.max_tabs_per_browser == 0 {
+            return Err(ScreenshotError::ConfigurationError(
+                "max_tabs_per_browser must be at least 1".to_string(),
+            ));
+        }
+
         let pool = Self {
             instances: Arc::new(Mutex::new(Vec::new())),
-            available: Arc::new(Mutex::new(VecDeque::new())),
-            semaphore: Arc::new(Semaphore::new(config.browser_pool_size)),
+            semaphore: Arc::new(Semaphore::new(
+                config.browser_pool_size * config.max_tabs_per_browser,
+            )),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
             config: config.clone(),
             is_shutting_down: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            metrics,
         };
 
         // Initialize browser instances
@@ -163,7 +322,6 @@ impl BrowserPool {
 
     async fn initialize_instances(&self) -> Result<(), ScreenshotError> {
         let mut instances = self.instances.lock().await;
-        let mut available = self.available.lock().await;
 
         for i in 0..self.config.browser_pool_size {
             // Add a small delay between browser launches to avoid race conditions
@@ -174,7 +332,6 @@ impl BrowserPool {
             match self.create_browser_instance(i).await {
                 Ok(instance) => {
                     instances.push(instance);
-                    available.push_back(i);
                     info!("Browser instance {} created successfully", i);
                 }
                 Err(e) => {
@@ -245,10 +402,23 @@ impl BrowserPool {
             Ok(())
         });
 
-        Ok(BrowserInstance::new(id, browser, handler_task))
+        Ok(BrowserInstance::new(
+            id,
+            browser,
+            handler_task,
+            self.config.max_tabs_per_browser,
+        ))
     }
 
-    pub async fn get_browser(&self) -> Result<BrowserHandle, ScreenshotError> {
+    /// Lease a fresh tab from the least loaded browser instance
+    ///
+    /// Waits for a free tab slot when every instance is at its tab limit. Tabs for
+    /// a named session are always opened on the instance owning that session, and
+    /// wait for a slot on that instance.
+    pub async fn acquire_page(
+        &self,
+        mode: &BrowserContextMode,
+    ) -> Result<PageLease, ScreenshotError> {
         if self
             .is_shutting_down
             .load(std::sync::atomic::Ordering::Relaxed)
@@ -256,87 +426,370 @@ impl BrowserPool {
             return Err(ScreenshotError::BrowserUnavailable);
         }
 
-        // Acquire semaphore permit
-        let _permit = self
+        let permit = self
             .semaphore
-            .acquire()
+            .clone()
+            .acquire_owned()
             .await
             .map_err(|_| ScreenshotError::BrowserUnavailable)?;
 
+        if let BrowserContextMode::Session(name) = mode {
+            return self.acquire_session_page(name, permit).await;
+        }
+
         // Retry logic for finding a healthy instance
+        let mut last_error = ScreenshotError::BrowserUnavailable;
         for attempt in 0..3 {
-            let instance_id = {
-                let mut available = self.available.lock().await;
-                available
-                    .pop_front()
-                    .ok_or(ScreenshotError::BrowserUnavailable)?
+            let Some((instance_id, generation, browser)) = self.reserve_tab().await else {
+                self.restart_idle_failed_instance(attempt).await?;
+                continue;
             };
 
-            let browser_result = {
-                let mut instances = self.instances.lock().await;
-                let instance = instances
-                    .get_mut(instance_id)
-                    .ok_or(ScreenshotError::BrowserUnavailable)?;
+            let context = match mode {
+                BrowserContextMode::Incognito => TabContext::New,
+                _ => TabContext::Default,
+            };
+            match open_tab(&browser, context).await {
+                Ok((page, context)) => {
+                    return Ok(PageLease {
+                        page: Some(page),
+                        instance_id,
+                        generation,
+                        browser,
+                        context,
+                        pool: Arc::new(self.clone()),
+                        permit: Some(permit),
+                    });
+                }
+                Err(e) => last_error = self.tab_failed(instance_id, generation, attempt, e).await,
+            }
+        }
 
-                // Check instance health and handler status
-                let is_healthy = instance.is_healthy() && !instance.handler.is_finished();
+        Err(last_error)
+    }
 
-                if !is_healthy {
-                    warn!(
-                        "Browser instance {} unhealthy (attempt {}), attempting restart",
-                        instance_id,
-                        attempt + 1
-                    );
+    /// Lease a tab in a named session's context, creating the session if needed
+    async fn acquire_session_page(
+        &self,
+        name: &str,
+        permit: OwnedSemaphorePermit,
+    ) -> Result<PageLease, ScreenshotError> {
+        let mut last_error = ScreenshotError::BrowserUnavailable;
+        for attempt in 0..3 {
+            let session = self.sessions.lock().await.get_mut(name).map(|session| {
+                session.last_used = Instant::now();
+                session.clone()
+            });
+
+            let Some(session) = session else {
+                self.expire_idle_sessions().await;
+                if self.sessions.lock().await.len() >= self.config.max_sessions {
+                    return Err(ScreenshotError::TooManySessions(self.config.max_sessions));
+                }
 
-                    // Try to restart the instance
-                    match self.restart_instance_internal(instance_id).await {
-                        Ok(()) => {
-                            info!("Successfully restarted browser instance {}", instance_id);
-                            instance.mark_used();
-                            Ok(instance.browser.clone())
-                        }
-                        Err(e) => {
-                            error!("Failed to restart browser instance {}: {}", instance_id, e);
-                            // Return instance to available pool and try another one
-                            self.available.lock().await.push_back(instance_id);
-                            Err(e)
-                        }
+                let Some((instance_id, generation, browser)) = self.reserve_tab().await else {
+                    self.restart_idle_failed_instance(attempt).await?;
+                    continue;
+                };
+                let (page, context) = match open_tab(&browser, TabContext::New).await {
+                    Ok((page, context)) => (page, context.expect("new tabs get a new context")),
+                    Err(e) => {
+                        last_error = self.tab_failed(instance_id, generation, attempt, e).await;
+                        continue;
+                    }
+                };
+
+                // Another request may have created the session, or reached the
+                // session limit, meanwhile
+                let inserted = {
+                    let mut sessions = self.sessions.lock().await;
+                    if sessions.contains_key(name) {
+                        Ok(false)
+                    } else if sessions.len() >= self.config.max_sessions {
+                        Err(ScreenshotError::TooManySessions(self.config.max_sessions))
+                    } else {
+                        sessions.insert(
+                            name.to_string(),
+                            SessionContext {
+                                instance_id,
+                                context_id: context.clone(),
+                                last_used: Instant::now(),
+                            },
+                        );
+                        Ok(true)
                     }
-                } else {
-                    instance.mark_used();
-                    Ok(instance.browser.clone())
+                };
+                if !matches!(inserted, Ok(true)) {
+                    let _ = page.close().await;
+                    dispose_context(&browser, context).await;
+                    self.release_tab(instance_id, generation).await;
+                    inserted?;
+                    continue;
                 }
+
+                return Ok(PageLease {
+                    page: Some(page),
+                    instance_id,
+                    generation,
+                    browser,
+                    context: None,
+                    pool: Arc::new(self.clone()),
+                    permit: Some(permit),
+                });
             };
 
-            match browser_result {
-                Ok(browser) => {
-                    return Ok(BrowserHandle::new(
-                        browser,
-                        instance_id,
-                        Arc::new(self.clone()),
-                    ));
+            let Some((instance_id, generation, browser)) =
+                self.wait_for_tab_on(session.instance_id).await
+            else {
+                // The session died with its browser; start a new one
+                let mut sessions = self.sessions.lock().await;
+                if sessions
+                    .get(name)
+                    .is_some_and(|current| current.context_id == session.context_id)
+                {
+                    sessions.remove(name);
                 }
-                Err(_) if attempt < 2 => {
-                    // Try next instance
-                    continue;
+                continue;
+            };
+
+            match open_tab(&browser, TabContext::Existing(session.context_id.clone())).await {
+                Ok((page, _)) => {
+                    return Ok(PageLease {
+                        page: Some(page),
+                        instance_id,
+                        generation,
+                        browser,
+                        context: None,
+                        pool: Arc::new(self.clone()),
+                        permit: Some(permit),
+                    });
                 }
                 Err(e) => {
-                    return Err(e);
+                    // A broken session context says nothing about the browser;
+                    // drop the session and retry in a fresh one. Should the
+                    // browser itself be at fault, opening that tab fails too.
+                    warn!(
+                        "Failed to open tab in session '{}' on browser instance {}: {}",
+                        name, instance_id, e
+                    );
+                    self.release_tab(instance_id, generation).await;
+                    let removed = {
+                        let mut sessions = self.sessions.lock().await;
+                        let current = sessions.get(name);
+                        if current.is_some_and(|current| current.context_id == session.context_id) {
+                            sessions.remove(name)
+                        } else {
+                            None
+                        }
+                    };
+                    if let Some(removed) = removed {
+                        dispose_context(&browser, removed.context_id).await;
+                    }
+                    last_error = ScreenshotError::ChromeError(e.to_string());
                 }
             }
         }
 
-        Err(ScreenshotError::BrowserUnavailable)
+        Err(last_error)
+    }
+
+    /// Return the slot of a tab that failed to open and mark its instance failed
+    async fn tab_failed(
+        &self,
+        instance_id: usize,
+        generation: u64,
+        attempt: usize,
+        error: chromiumoxide::error::CdpError,
+    ) -> ScreenshotError {
+        warn!(
+            "Failed to open tab on browser instance {} (attempt {}): {}",
+            instance_id,
+            attempt + 1,
+            error
+        );
+        let mut instances = self.instances.lock().await;
+        if let Some(instance) = instances.get_mut(instance_id) {
+            // A restart in the meantime already replaced the failed browser
+            if instance.generation == generation {
+                instance.close_tab();
+                instance.mark_failed();
+            }
+        }
+        self.report_utilization(&instances);
+
+        ScreenshotError::ChromeError(error.to_string())
+    }
+
+    /// Dispose a named session's browser context
+    ///
+    /// Returns `false` if no session with that name exists.
+    pub async fn close_session(&self, name: &str) -> bool {
+        let Some(session) = self.sessions.lock().await.remove(name) else {
+            return false;
+        };
+
+        self.dispose_session(session).await;
+        true
+    }
+
+    /// Close named sessions that have been idle for `Config::session_idle_timeout`
+    async fn expire_idle_sessions(&self) {
+        let expired: Vec<(String, SessionContext)> = {
+            let mut sessions = self.sessions.lock().await;
+            let names: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| {
+                    session.last_used.elapsed() >= self.config.session_idle_timeout
+                })
+                .map(|(name, _)| name.clone())
+                .collect();
+            names
+                .into_iter()
+                .filter_map(|name| sessions.remove(&name).map(|session| (name, session)))
+                .collect()
+        };
+
+        for (name, session) in expired {
+            info!("Closing idle session '{}'", name);
+            self.dispose_session(session).await;
+        }
+    }
+
+    async fn dispose_session(&self, session: SessionContext) {
+        let browser = {
+            let instances = self.instances.lock().await;
+            instances
+                .get(session.instance_id)
+                .map(|instance| instance.browser.clone())
+        };
+        if let Some(browser) = browser {
+            dispose_context(&browser, session.context_id).await;
+        }
+    }
+
+    /// Names of the currently open sessions
+    pub async fn session_names(&self) -> Vec<String> {
+        self.sessions.lock().await.keys().cloned().collect()
+    }
+
+    /// Whether an instance is running and not marked as failed
+    async fn instance_alive(&self, instance_id: usize) -> bool {
+        let instances = self.instances.lock().await;
+        instances.get(instance_id).is_some_and(|instance| {
+            matches!(
+                instance.status,
+                InstanceStatus::Healthy | InstanceStatus::Busy
+            ) && !instance.handler.is_finished()
+        })
+    }
+
+    /// Reserve a tab slot on a specific instance, waiting while it is at its tab
+    /// limit
+    ///
+    /// Returns `None` once the instance is no longer running.
+    async fn wait_for_tab_on(
+        &self,
+        instance_id: usize,
+    ) -> Option<(usize, u64, Arc<Mutex<Browser>>)> {
+        loop {
+            let tab_released = {
+                let instances = self.instances.lock().await;
+                instances.get(instance_id)?.tab_released.clone()
+            };
+            // Registered before checking, so a release in between isn't missed
+            let released = tab_released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if let Some(reserved) = self.reserve_tab_on(instance_id).await {
+                return Some(reserved);
+            }
+            if !self.instance_alive(instance_id).await {
+                return None;
+            }
+            released.await;
+        }
+    }
+
+    /// Reserve a tab slot on a specific instance if it has room
+    async fn reserve_tab_on(
+        &self,
+        instance_id: usize,
+    ) -> Option<(usize, u64, Arc<Mutex<Browser>>)> {
+        let mut instances = self.instances.lock().await;
+
+        let instance = instances.get_mut(instance_id)?;
+        if !instance.has_capacity() {
+            return None;
+        }
+
+        instance.open_tab();
+        let reserved = (instance.id, instance.generation, instance.browser.clone());
+        self.report_utilization(&instances);
+        Some(reserved)
     }
 
-    pub async fn return_browser(&self, instance_id: usize) {
+    /// Reserve a tab slot on the healthy instance with the fewest open tabs
+    async fn reserve_tab(&self) -> Option<(usize, u64, Arc<Mutex<Browser>>)> {
+        let mut instances = self.instances.lock().await;
+
+        let instance = instances
+            .iter_mut()
+            .filter(|instance| instance.has_capacity())
+            .min_by_key(|instance| (instance.active_tabs, instance.last_used))?;
+
+        instance.open_tab();
+        let reserved = (instance.id, instance.generation, instance.browser.clone());
+        self.report_utilization(&instances);
+        Some(reserved)
+    }
+
+    /// Restart one unhealthy instance that has no open tabs
+    async fn restart_idle_failed_instance(&self, attempt: usize) -> Result<(), ScreenshotError> {
+        let candidate = {
+            let instances = self.instances.lock().await;
+            instances
+                .iter()
+                .find(|instance| {
+                    instance.active_tabs == 0
+                        && (!instance.is_healthy() || instance.handler.is_finished())
+                        && !matches!(instance.status, InstanceStatus::Restarting)
+                })
+                .map(|instance| instance.id)
+        };
+
+        let Some(instance_id) = candidate else {
+            return Err(ScreenshotError::BrowserUnavailable);
+        };
+
+        warn!(
+            "Browser instance {} unhealthy (attempt {}), attempting restart",
+            instance_id,
+            attempt + 1
+        );
+        self.restart_instance_internal(instance_id).await
+    }
+
+    /// Return a tab slot to its browser instance
+    ///
+    /// Slots leased before the instance was restarted are ignored; the new
+    /// browser started with none in use.
+    pub async fn release_tab(&self, instance_id: usize, generation: u64) {
         let mut instances = self.instances.lock().await;
-        let mut available = self.available.lock().await;
 
         if let Some(instance) = instances.get_mut(instance_id) {
-            instance.mark_available();
-            available.push_back(instance_id);
+            if instance.generation == generation {
+                instance.close_tab();
+            }
         }
+
+        self.report_utilization(&instances);
+    }
+
+    fn report_utilization(&self, instances: &[BrowserInstance]) {
+        let active_tabs = instances.iter().map(|instance| instance.active_tabs).sum();
+        let capacity = instances.iter().map(|instance| instance.max_tabs).sum();
+        self.metrics.record_browser_usage(active_tabs, capacity);
     }
 
     pub async fn health_check(&self) -> Vec<InstanceHealth> {
@@ -351,6 +804,8 @@ impl BrowserPool {
                 age: instance.age(),
                 idle_time: instance.idle_time(),
                 failure_count: instance.failure_count,
+                active_tabs: instance.active_tabs,
+                max_tabs: instance.max_tabs,
             };
             healths.push(health);
         }
@@ -363,30 +818,55 @@ impl BrowserPool {
     }
 
     async fn restart_instance_internal(&self, instance_id: usize) -> Result<(), ScreenshotError> {
-        let mut instances = self.instances.lock().await;
+        // Contexts die with the browser process
+        self.sessions
+            .lock()
+            .await
+            .retain(|_, session| session.instance_id != instance_id);
+
+        // Relaunching takes a while; don't hold the pool lock meanwhile
+        let (old_browser, generation) = {
+            let mut instances = self.instances.lock().await;
+            let Some(instance) = instances.get_mut(instance_id) else {
+                return Err(ScreenshotError::BrowserUnavailable);
+            };
+            if matches!(instance.status, InstanceStatus::Restarting) {
+                debug!("Browser instance {} is already restarting", instance_id);
+                return Ok(());
+            }
 
-        if let Some(instance) = instances.get_mut(instance_id) {
             instance.status = InstanceStatus::Restarting;
+            // Session waiters give up on the old browser
+            instance.tab_released.notify_waiters();
+            (instance.browser.clone(), instance.generation)
+        };
 
-            // Shutdown old browser
-            let _ = instance.browser.lock().await.close().await;
-            instance.handler.abort();
+        // Shutdown old browser
+        let _ = old_browser.lock().await.close().await;
 
-            // Create new browser instance
-            match self.create_browser_instance(instance_id).await {
-                Ok(new_instance) => {
-                    *instance = new_instance;
-                    info!("Browser instance {} restarted successfully", instance_id);
-                    Ok(())
-                }
-                Err(e) => {
-                    instance.status = InstanceStatus::Failed;
-                    error!("Failed to restart browser instance {}: {}", instance_id, e);
-                    Err(e)
-                }
+        // Create new browser instance
+        let result = self.create_browser_instance(instance_id).await;
+
+        let mut instances = self.instances.lock().await;
+        let Some(instance) = instances.get_mut(instance_id) else {
+            return Err(ScreenshotError::BrowserUnavailable);
+        };
+        instance.handler.abort();
+
+        match result {
+            Ok(mut new_instance) => {
+                new_instance.generation = generation + 1;
+                *instance = new_instance;
+                self.report_utilization(&instances);
+                self.metrics.record_browser_restart();
+                info!("Browser instance {} restarted successfully", instance_id);
+                Ok(())
+            }
+            Err(e) => {
+                instance.status = InstanceStatus::Failed;
+                error!("Failed to restart browser instance {}: {}", instance_id, e);
+                Err(e)
             }
-        } else {
-            Err(ScreenshotError::BrowserUnavailable)
         }
     }
 
@@ -403,6 +883,7 @@ impl BrowserPool {
                 tokio::select! {
                     _ = quick_interval.tick() => {
                         pool.quick_health_check().await;
+                        pool.expire_idle_sessions().await;
                     }
                     _ = deep_interval.tick() => {
                         pool.deep_health_check().await;
@@ -444,8 +925,8 @@ impl BrowserPool {
 
             for instance in instances.iter() {
                 let needs_restart =
-                    // Too old (1 hour)
-                    instance.age() > Duration::from_secs(3600) ||
+                    // Too old (1 hour), once its tabs have finished
+                    (instance.age() > Duration::from_secs(3600) && instance.active_tabs == 0) ||
                     // Too many failures
                     instance.failure_count > 10 ||
                     // Handler crashed
@@ -479,11 +960,17 @@ impl BrowserPool {
         self.is_shutting_down
             .store(true, std::sync::atomic::Ordering::Relaxed);
 
-        // Wait for all instances to become available
+        // Wait for leased tabs to be returned
         let mut retries = 0;
         while retries < 10 {
-            let available_count = self.available.lock().await.len();
-            if available_count == self.config.browser_pool_size {
+            let active_tabs: usize = self
+                .instances
+                .lock()
+                .await
+                .iter()
+                .map(|instance| instance.active_tabs)
+                .sum();
+            if active_tabs == 0 {
                 break;
             }
 
@@ -492,6 +979,7 @@ impl BrowserPool {
         }
 
         // Shutdown all browser instances
+        self.sessions.lock().await.clear();
         let mut instances = self.instances.lock().await;
         for instance in instances.drain(..) {
             instance.shutdown().await;
@@ -502,15 +990,22 @@ impl BrowserPool {
 
     pub async fn get_stats(&self) -> BrowserPoolStats {
         let instances = self.instances.lock().await;
-        let available = self.available.lock().await;
 
         let mut healthy_count = 0;
         let mut busy_count = 0;
         let mut failed_count = 0;
+        let mut available_count = 0;
         let mut total_screenshots = 0;
+        let mut active_tabs = 0;
+        let mut tab_capacity = 0;
 
         for instance in instances.iter() {
             total_screenshots += instance.screenshot_count;
+            active_tabs += instance.active_tabs;
+            tab_capacity += instance.max_tabs;
+            if instance.has_capacity() {
+                available_count += 1;
+            }
             match instance.status {
                 InstanceStatus::Healthy => healthy_count += 1,
                 InstanceStatus::Busy => busy_count += 1,
@@ -524,8 +1019,10 @@ impl BrowserPool {
             healthy_instances: healthy_count,
             busy_instances: busy_count,
             failed_instances: failed_count,
-            available_instances: available.len(),
+            available_instances: available_count,
             total_screenshots,
+            active_tabs,
+            tab_capacity,
         }
     }
 }
@@ -534,10 +1031,11 @@ impl Clone for BrowserPool {
     fn clone(&self) -> Self {
         Self {
             instances: self.instances.clone(),
-            available: self.available.clone(),
             semaphore: self.semaphore.clone(),
+            sessions: self.sessions.clone(),
             config: self.config.clone(),
             is_shutting_down: self.is_shutting_down.clone(),
+            metrics: self.metrics.clone(),
         }
     }
 }
@@ -550,6 +1048,8 @@ pub struct InstanceHealth {
     pub age: Duration,
     pub idle_time: Duration,
     pub failure_count: usize,
+    pub active_tabs: usize,
+    pub max_tabs: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -560,4 +1060,8 @@ pub struct BrowserPoolStats {
     pub failed_instances: usize,
     pub available_instances: usize,
     pub total_screenshots: usize,
+    /// Tabs currently leased out across all instances
+    pub active_tabs: usize,
+    /// Total tab slots across all instances
+    pub tab_capacity: usize,
 }
@@ -1,8 +1,19 @@
 use crate::{
-    BatchProcessor, Config, Priority, ProgressTracker, ScreenshotRequest, ScreenshotService,
+    diff_directories, diff_files, diff_result, load_image, sanitize_filename, AcceptStatuses,
+    AlertSink, ApiServer, BaselineKey, BaselineStore, BasicAuth, BatchProcessor, BrowserIsolation,
+    ChangeDetection, Clip, ColorScheme, Config, Cookie, DeviceCatalog, DiffOptions, DiffStatus,
+    ElementCapture, ElementMatch, EncoderSettings, FullPageOptions, Geolocation, ImageDiff,
+    MediaType, Metrics, MissingScope, NotFoundBehavior, OutputFormat, PageAction, PaperSize,
+    PdfMargins, PdfOptions, PngCompression, PngFilter, Priority, ProgressTracker,
+    PrometheusExporter, RedactionStyle, Schedule, ScreenshotError, ScreenshotRequest,
+    ScreenshotResult, ScreenshotService, SelectorType, ServerOptions, ViewportTarget,
+    WaitCondition, WatchOptions, WatchStore,
 };
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs;
 // use tokio::io::AsyncWriteExt;
@@ -25,6 +36,18 @@ pub struct Cli {
     #[arg(long, help = "Maximum concurrent screenshots")]
     pub max_concurrent: Option<usize>,
 
+    #[arg(long, help = "Maximum concurrent tabs per browser instance")]
+    pub max_tabs: Option<usize>,
+
+    #[arg(long, help = "Open every request in a fresh incognito browser context")]
+    pub incognito: bool,
+
+    #[arg(
+        long,
+        help = "Render deterministically: frozen animations, fixed clock and random seed, stable fonts"
+    )]
+    pub deterministic: bool,
+
     #[arg(long, help = "Screenshot timeout in seconds")]
     pub timeout: Option<u64>,
 
@@ -33,6 +56,19 @@ pub struct Cli {
 
     #[arg(long, help = "Chrome executable path")]
     pub chrome_path: Option<String>,
+
+    #[arg(long, help = "JSON file of extra device presets")]
+    pub devices: Option<PathBuf>,
+
+    #[arg(long, help = "Serve Prometheus metrics on this port")]
+    pub metrics_port: Option<u16>,
+
+    #[arg(
+        long,
+        requires = "metrics_port",
+        help = "Bind address for --metrics-port (default: 127.0.0.1)"
+    )]
+    pub metrics_bind: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -48,7 +84,7 @@ pub enum Commands {
         #[arg(short, long, default_value = "10", help = "Concurrency level")]
         concurrency: usize,
 
-        #[arg(long, help = "Output format (png, jpeg, webp)")]
+        #[arg(long, help = "Output format (png, jpeg, webp, pdf)")]
         format: Option<String>,
 
         #[arg(long, help = "Viewport width")]
@@ -57,6 +93,21 @@ pub enum Commands {
         #[arg(long, help = "Viewport height")]
         height: Option<u32>,
 
+        #[arg(
+            long,
+            conflicts_with_all = ["width", "height"],
+            help = "Emulate a named device (e.g. \"iPhone 15\", \"Pixel 7 landscape\")"
+        )]
+        device: Option<String>,
+
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with_all = ["width", "height", "device"],
+            help = "Capture each of these devices or WIDTHxHEIGHT sizes from one page load"
+        )]
+        viewports: Vec<ViewportTarget>,
+
         #[arg(long, help = "Take full page screenshots")]
         full_page: bool,
 
@@ -65,6 +116,27 @@ pub enum Commands {
 
         #[arg(long, help = "Progress reporting interval in seconds")]
         progress_interval: Option<u64>,
+
+        #[command(flatten)]
+        waits: WaitArgs,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        pdf: PdfArgs,
+
+        #[command(flatten)]
+        tiling: TilingArgs,
+
+        #[command(flatten)]
+        injection: InjectionArgs,
+
+        #[command(flatten)]
+        emulation: EmulationArgs,
+
+        #[command(flatten)]
+        http: HttpArgs,
     },
 
     /// Take a single screenshot
@@ -75,7 +147,7 @@ pub enum Commands {
         #[arg(short, long, help = "Output file path")]
         output: PathBuf,
 
-        #[arg(long, help = "Output format (png, jpeg, webp)")]
+        #[arg(long, help = "Output format (png, jpeg, webp, pdf)")]
         format: Option<String>,
 
         #[arg(long, help = "Viewport width")]
@@ -84,6 +156,21 @@ pub enum Commands {
         #[arg(long, help = "Viewport height")]
         height: Option<u32>,
 
+        #[arg(
+            long,
+            conflicts_with_all = ["width", "height"],
+            help = "Emulate a named device (e.g. \"iPhone 15\", \"Pixel 7 landscape\")"
+        )]
+        device: Option<String>,
+
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with_all = ["width", "height", "device"],
+            help = "Capture each of these devices or WIDTHxHEIGHT sizes from one page load"
+        )]
+        viewports: Vec<ViewportTarget>,
+
         #[arg(long, help = "Take full page screenshot")]
         full_page: bool,
 
@@ -93,16 +180,40 @@ pub enum Commands {
         #[arg(long, help = "CSS selector for element screenshot")]
         selector: Option<String>,
 
+        #[command(flatten)]
+        elements: ElementArgs,
+
         #[arg(long, help = "Request priority (low, normal, high, critical)")]
         priority: Option<String>,
+
+        #[command(flatten)]
+        waits: WaitArgs,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        pdf: PdfArgs,
+
+        #[command(flatten)]
+        tiling: TilingArgs,
+
+        #[command(flatten)]
+        injection: InjectionArgs,
+
+        #[command(flatten)]
+        emulation: EmulationArgs,
+
+        #[command(flatten)]
+        http: HttpArgs,
     },
 
-    /// Start monitoring server
+    /// Start the HTTP API server
     Server {
         #[arg(short, long, default_value = "8080", help = "Server port")]
         port: u16,
 
-        #[arg(long, help = "Bind address")]
+        #[arg(long, help = "Bind address (default: 127.0.0.1)")]
         bind: Option<String>,
 
         #[arg(long, help = "Enable metrics endpoint")]
@@ -110,6 +221,68 @@ pub enum Commands {
 
         #[arg(long, help = "Enable health check endpoint")]
         health: bool,
+
+        #[arg(
+            long,
+            default_value = "100",
+            help = "Maximum number of requests per batch call"
+        )]
+        max_batch_size: usize,
+    },
+
+    /// Compare screenshots with baseline images
+    Diff {
+        #[arg(long, help = "Baseline image, or directory of baseline images")]
+        baseline: PathBuf,
+
+        #[arg(
+            long,
+            required_unless_present = "url",
+            conflicts_with = "url",
+            help = "Image or directory of images to compare with the baseline"
+        )]
+        current: Option<PathBuf>,
+
+        #[arg(long, help = "Capture this URL and compare it with the baseline image")]
+        url: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Highlighted diff image, or directory of them for directory diffs"
+        )]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+
+    /// Manage the baseline store for visual regression
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineCommand,
+    },
+
+    /// Recapture pages on a schedule and alert when they change
+    Watch {
+        #[command(flatten)]
+        capture: CaptureArgs,
+
+        #[arg(
+            long,
+            default_value = "watch",
+            help = "Directory for the stored versions of watched pages"
+        )]
+        store: PathBuf,
+
+        #[arg(long, help = "Capture and compare once, then exit")]
+        once: bool,
+
+        #[command(flatten)]
+        watch: WatchArgs,
+
+        #[command(flatten)]
+        diff: DiffArgs,
     },
 
     /// Validate configuration
@@ -125,6 +298,733 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum BaselineCommand {
+    /// Capture pages and accept the screenshots as their baselines
+    Update {
+        #[command(flatten)]
+        capture: CaptureArgs,
+
+        #[arg(long, default_value = "baselines", help = "Baseline store directory")]
+        store: PathBuf,
+
+        #[arg(long, help = "Only add baselines for pages that have none")]
+        new_only: bool,
+
+        #[arg(long, help = "Delete baselines of pages that were not captured")]
+        prune: bool,
+    },
+
+    /// Capture pages and compare them with their baselines
+    Check {
+        #[command(flatten)]
+        capture: CaptureArgs,
+
+        #[arg(long, default_value = "baselines", help = "Baseline store directory")]
+        store: PathBuf,
+
+        #[arg(
+            long,
+            default_value = "baseline-report.json",
+            help = "JSON report of changed, new and missing baselines"
+        )]
+        report: PathBuf,
+
+        #[arg(long, help = "Directory for highlighted diff images of changed pages")]
+        diff_output: Option<PathBuf>,
+
+        #[arg(long, help = "Fail when a page has no baseline yet")]
+        fail_on_new: bool,
+
+        #[arg(
+            long,
+            help = "Only report missing baselines of the captured pages, to check part of the store"
+        )]
+        subset: bool,
+
+        #[command(flatten)]
+        diff: DiffArgs,
+    },
+}
+
+/// Pages and capture settings of the baseline and watch commands
+///
+/// Pages are always captured as PNG.
+#[derive(Args, Debug, Clone)]
+pub struct CaptureArgs {
+    #[arg(short, long, help = "Input file containing URLs (one per line)")]
+    pub input: PathBuf,
+
+    #[arg(long, help = "Viewport width")]
+    pub width: Option<u32>,
+
+    #[arg(long, help = "Viewport height")]
+    pub height: Option<u32>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["width", "height"],
+        help = "Emulate a named device (e.g. \"iPhone 15\", \"Pixel 7 landscape\")"
+    )]
+    pub device: Option<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["width", "height", "device"],
+        help = "Capture each of these devices or WIDTHxHEIGHT sizes from one page load"
+    )]
+    pub viewports: Vec<ViewportTarget>,
+
+    #[arg(long, help = "Take full page screenshots")]
+    pub full_page: bool,
+
+    #[arg(long, help = "Wait time in milliseconds before taking screenshot")]
+    pub wait: Option<u64>,
+
+    #[command(flatten)]
+    pub waits: WaitArgs,
+
+    #[command(flatten)]
+    pub injection: InjectionArgs,
+
+    #[command(flatten)]
+    pub emulation: EmulationArgs,
+
+    #[command(flatten)]
+    pub http: HttpArgs,
+}
+
+impl CaptureArgs {
+    pub fn request_options(&self) -> RequestOptions {
+        RequestOptions {
+            format: Some("png".to_string()),
+            width: self.width,
+            height: self.height,
+            device: self.device.clone(),
+            viewports: self.viewports.clone(),
+            full_page: self.full_page,
+            wait: self.wait,
+            selector: None,
+            elements: ElementArgs::default(),
+            waits: self.waits.clone(),
+            encoder: EncoderArgs::default(),
+            pdf: PdfArgs::default(),
+            tiling: TilingArgs::default(),
+            injection: self.injection.clone(),
+            emulation: self.emulation.clone(),
+            http: self.http.clone(),
+        }
+    }
+}
+
+fn print_image_diff(image_diff: &ImageDiff) {
+    println!("  Mismatch: {:.3}%", image_diff.mismatch_percentage);
+    println!("  Changed pixels: {}", image_diff.different_pixels);
+    println!("  Anti-aliased pixels: {}", image_diff.anti_aliased_pixels);
+    println!("  Ignored pixels: {}", image_diff.ignored_pixels);
+    if image_diff.size_changed {
+        println!(
+            "  Size changed: now {}x{}",
+            image_diff.width, image_diff.height
+        );
+    }
+}
+
+async fn write_diff_image(path: &Path, image_diff: &ImageDiff) -> Result<(), ScreenshotError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, image_diff.diff_png()?).await?;
+
+    Ok(())
+}
+
+/// Wait conditions checked after navigation
+///
+/// Network idle is checked first, then the selector, function and fonts.
+#[derive(Args, Debug, Clone, Default)]
+pub struct WaitArgs {
+    #[arg(
+        long,
+        help = "Wait until an element matching this CSS selector is visible"
+    )]
+    pub wait_for_selector: Option<String>,
+
+    #[arg(long, help = "Wait until this JavaScript expression is truthy")]
+    pub wait_for_function: Option<String>,
+
+    #[arg(
+        long,
+        help = "Wait until no requests have been in flight for this many milliseconds"
+    )]
+    pub network_idle: Option<u64>,
+
+    #[arg(long, help = "Wait until web fonts have loaded")]
+    pub wait_for_fonts: bool,
+
+    #[arg(long, help = "Timeout in milliseconds for each wait condition")]
+    pub wait_timeout: Option<u64>,
+}
+
+impl WaitArgs {
+    pub fn conditions(&self) -> Vec<WaitCondition> {
+        let timeout_ms = self.wait_timeout;
+        let mut conditions = Vec::new();
+
+        if let Some(idle_ms) = self.network_idle {
+            conditions.push(WaitCondition::NetworkIdle {
+                idle_ms: Some(idle_ms),
+                timeout_ms,
+            });
+        }
+        if let Some(selector) = &self.wait_for_selector {
+            conditions.push(WaitCondition::Selector {
+                selector: selector.clone(),
+                timeout_ms,
+            });
+        }
+        if let Some(expression) = &self.wait_for_function {
+            conditions.push(WaitCondition::Function {
+                expression: expression.clone(),
+                timeout_ms,
+            });
+        }
+        if self.wait_for_fonts {
+            conditions.push(WaitCondition::FontsLoaded { timeout_ms });
+        }
+
+        conditions
+    }
+}
+
+/// Image encoder settings, layered over the `encoder` config section
+#[derive(Args, Debug, Clone, Default)]
+pub struct EncoderArgs {
+    #[arg(long, help = "JPEG quality (1-100)")]
+    pub jpeg_quality: Option<u8>,
+
+    #[arg(long, help = "Encode WebP lossy with this quality (1-100)")]
+    pub webp_quality: Option<u8>,
+
+    #[arg(long, conflicts_with = "webp_quality", help = "Encode WebP losslessly")]
+    pub webp_lossless: bool,
+
+    #[arg(long, help = "PNG compression level (fast, default, best)")]
+    pub png_compression: Option<PngCompression>,
+
+    #[arg(long, help = "PNG filter (none, sub, up, avg, paeth, adaptive)")]
+    pub png_filter: Option<PngFilter>,
+}
+
+impl EncoderArgs {
+    pub fn is_empty(&self) -> bool {
+        self.jpeg_quality.is_none()
+            && self.webp_quality.is_none()
+            && !self.webp_lossless
+            && self.png_compression.is_none()
+            && self.png_filter.is_none()
+    }
+
+    /// Apply the command-line overrides on top of the configured encoder settings
+    pub fn apply(&self, base: &EncoderSettings) -> EncoderSettings {
+        let mut settings = base.clone();
+
+        if let Some(quality) = self.jpeg_quality {
+            settings.jpeg_quality = quality;
+        }
+        if let Some(quality) = self.webp_quality {
+            settings.webp_lossless = false;
+            settings.webp_quality = quality;
+        }
+        if self.webp_lossless {
+            settings.webp_lossless = true;
+        }
+        if let Some(compression) = self.png_compression {
+            settings.png_compression = Some(compression);
+        }
+        if let Some(filter) = self.png_filter {
+            settings.png_filter = Some(filter);
+        }
+
+        settings
+    }
+}
+
+/// Print settings for `--format pdf`, layered over the `pdf` config section
+#[derive(Args, Debug, Clone, Default)]
+pub struct PdfArgs {
+    #[arg(
+        long,
+        help = "PDF paper size (letter, legal, tabloid, ledger, a0-a6, or WIDTHxHEIGHT in inches)"
+    )]
+    pub paper_size: Option<PaperSize>,
+
+    #[arg(long, help = "Print PDF in landscape orientation")]
+    pub landscape: bool,
+
+    #[arg(long, help = "Omit background colors and images from PDF output")]
+    pub no_background: bool,
+
+    #[arg(long, help = "PDF page margin in inches, applied to every side")]
+    pub margin: Option<f64>,
+
+    #[arg(long, help = "HTML template for the PDF page header")]
+    pub header_template: Option<String>,
+
+    #[arg(long, help = "HTML template for the PDF page footer")]
+    pub footer_template: Option<String>,
+}
+
+impl PdfArgs {
+    /// Apply the command-line overrides on top of the configured PDF settings
+    pub fn apply(&self, base: &PdfOptions) -> PdfOptions {
+        let mut options = base.clone();
+
+        if let Some(paper_size) = &self.paper_size {
+            options.paper_size = paper_size.clone();
+        }
+        if self.landscape {
+            options.landscape = true;
+        }
+        if self.no_background {
+            options.print_background = false;
+        }
+        if let Some(margin) = self.margin {
+            options.margins = PdfMargins::uniform(margin);
+        }
+        if let Some(header) = &self.header_template {
+            options.header_template = Some(header.clone());
+        }
+        if let Some(footer) = &self.footer_template {
+            options.footer_template = Some(footer.clone());
+        }
+
+        options
+    }
+}
+
+/// Clip rectangle and element capture settings for `single`
+#[derive(Args, Debug, Clone, Default)]
+pub struct ElementArgs {
+    #[arg(
+        long,
+        value_name = "X,Y,WIDTH,HEIGHT",
+        conflicts_with = "selector",
+        help = "Capture this rectangle of the page, in CSS pixels"
+    )]
+    pub clip: Option<Clip>,
+
+    #[arg(
+        long,
+        requires = "selector",
+        help = "Treat --selector as an XPath expression"
+    )]
+    pub xpath: bool,
+
+    #[arg(
+        long,
+        requires = "selector",
+        help = "Capture every matching element to its own file"
+    )]
+    pub all_matches: bool,
+
+    #[arg(
+        long,
+        requires = "selector",
+        conflicts_with = "all_matches",
+        help = "Capture every matching element onto one contact sheet"
+    )]
+    pub contact_sheet: bool,
+
+    #[arg(
+        long,
+        requires = "selector",
+        help = "Space around captured elements in CSS pixels"
+    )]
+    pub padding: Option<f64>,
+
+    #[arg(
+        long,
+        requires = "selector",
+        value_name = "BEHAVIOR",
+        help = "When no element matches: error, skip or full-page"
+    )]
+    pub not_found: Option<NotFoundBehavior>,
+}
+
+impl ElementArgs {
+    /// Set the clip, and element settings for the request's `element_selector`
+    pub fn apply(&self, request: &mut ScreenshotRequest) {
+        request.clip = self.clip;
+
+        let Some(selector) = &request.element_selector else {
+            return;
+        };
+        let mut element = ElementCapture::new(selector.clone());
+        if self.xpath {
+            element.selector_type = SelectorType::Xpath;
+        }
+        if self.all_matches {
+            element.matches = ElementMatch::All;
+        }
+        if self.contact_sheet {
+            element.matches = ElementMatch::ContactSheet;
+        }
+        if let Some(padding) = self.padding {
+            element.padding = padding;
+        }
+        if let Some(not_found) = self.not_found {
+            element.not_found = not_found;
+        }
+        request.element = Some(element);
+    }
+}
+
+/// Styles, scripts and selectors to hide or redact, added to the configured ones
+#[derive(Args, Debug, Clone, Default)]
+pub struct InjectionArgs {
+    #[arg(
+        long = "inject-css",
+        value_name = "CSS",
+        help = "Stylesheet added to the page (repeatable)"
+    )]
+    pub styles: Vec<String>,
+
+    #[arg(
+        long = "inject-js",
+        value_name = "SCRIPT",
+        help = "JavaScript run in the page before capture (repeatable)"
+    )]
+    pub scripts: Vec<String>,
+
+    #[arg(
+        long = "hide",
+        value_name = "SELECTOR",
+        help = "Hide elements matching this CSS selector (repeatable)"
+    )]
+    pub hide_selectors: Vec<String>,
+
+    #[arg(
+        long = "redact",
+        value_name = "SELECTOR",
+        help = "Obscure elements matching this CSS selector in the image (repeatable)"
+    )]
+    pub redact_selectors: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "STYLE",
+        help = "How redacted elements are obscured: blackout or blur"
+    )]
+    pub redaction: Option<RedactionStyle>,
+}
+
+impl InjectionArgs {
+    pub fn apply(&self, request: &mut ScreenshotRequest) {
+        let injection = &mut request.injection;
+        injection.inject_styles.extend(self.styles.iter().cloned());
+        injection
+            .inject_scripts
+            .extend(self.scripts.iter().cloned());
+        injection
+            .hide_selectors
+            .extend(self.hide_selectors.iter().cloned());
+        injection
+            .redact_selectors
+            .extend(self.redact_selectors.iter().cloned());
+        if self.redaction.is_some() {
+            injection.redaction = self.redaction;
+        }
+    }
+}
+
+/// Emulated environment, overriding the configured emulation options
+#[derive(Args, Debug, Clone, Default)]
+pub struct EmulationArgs {
+    #[arg(
+        long,
+        value_name = "SCHEME",
+        help = "Emulate prefers-color-scheme: light or dark"
+    )]
+    pub color_scheme: Option<ColorScheme>,
+
+    #[arg(
+        long,
+        value_name = "TYPE",
+        help = "Emulate CSS media type: screen or print"
+    )]
+    pub media: Option<MediaType>,
+
+    #[arg(long, help = "Emulate prefers-reduced-motion: reduce")]
+    pub reduced_motion: bool,
+
+    #[arg(long, help = "Locale and Accept-Language, e.g. de-DE")]
+    pub locale: Option<String>,
+
+    #[arg(long, help = "Timezone ID, e.g. America/New_York")]
+    pub timezone: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "LAT,LON[,ACCURACY]",
+        help = "Emulated position, granting the geolocation permission"
+    )]
+    pub geolocation: Option<Geolocation>,
+}
+
+impl EmulationArgs {
+    pub fn apply(&self, request: &mut ScreenshotRequest) {
+        let emulation = &mut request.emulation;
+        if self.color_scheme.is_some() {
+            emulation.color_scheme = self.color_scheme;
+        }
+        if self.media.is_some() {
+            emulation.media = self.media;
+        }
+        if self.reduced_motion {
+            emulation.reduced_motion = Some(true);
+        }
+        if self.locale.is_some() {
+            emulation.locale = self.locale.clone();
+        }
+        if self.timezone.is_some() {
+            emulation.timezone = self.timezone.clone();
+        }
+        if self.geolocation.is_some() {
+            emulation.geolocation = self.geolocation;
+        }
+    }
+}
+
+/// Visual diff settings, layered over the `diff` config section
+#[derive(Args, Debug, Clone, Default)]
+pub struct DiffArgs {
+    #[arg(
+        long,
+        help = "Per-pixel color difference tolerated, from 0 to 1 (default: 0.1)"
+    )]
+    pub threshold: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Fail when more than this percentage of pixels changed (default: 0)"
+    )]
+    pub fail_threshold: Option<f64>,
+
+    #[arg(long, help = "Count anti-aliased pixels as changed")]
+    pub include_anti_aliasing: bool,
+
+    #[arg(
+        long = "ignore",
+        value_name = "X,Y,W,H",
+        help = "Rectangle to skip, in image pixels (repeatable)"
+    )]
+    pub ignore_regions: Vec<Clip>,
+
+    #[arg(
+        long = "ignore-selector",
+        value_name = "SELECTOR",
+        help = "Skip elements matching this CSS selector; needs a fresh capture (repeatable)"
+    )]
+    pub ignore_selectors: Vec<String>,
+}
+
+impl DiffArgs {
+    /// Apply the command-line overrides on top of the configured diff settings
+    pub fn apply(&self, base: &DiffOptions) -> DiffOptions {
+        let mut options = base.clone();
+
+        if let Some(threshold) = self.threshold {
+            options.threshold = threshold;
+        }
+        if let Some(fail_threshold) = self.fail_threshold {
+            options.fail_threshold = fail_threshold;
+        }
+        if self.include_anti_aliasing {
+            options.include_anti_aliasing = true;
+        }
+        options
+            .ignore_regions
+            .extend(self.ignore_regions.iter().copied());
+        options
+            .ignore_selectors
+            .extend(self.ignore_selectors.iter().cloned());
+
+        options
+    }
+}
+
+/// Change monitoring settings, layered over the `watch` config section
+#[derive(Args, Debug, Clone, Default)]
+pub struct WatchArgs {
+    #[arg(
+        long,
+        help = "Cron expression (UTC) or interval like \"@every 15m\" to recapture on"
+    )]
+    pub schedule: Option<Schedule>,
+
+    #[arg(
+        long,
+        value_name = "METHOD",
+        help = "Compare captures by pixel diff or perceptual hash: pixel or hash"
+    )]
+    pub detection: Option<ChangeDetection>,
+
+    #[arg(
+        long,
+        help = "Perceptual hash distance (0-64) above which a page changed (default: 5)"
+    )]
+    pub hash_distance: Option<u32>,
+
+    #[arg(
+        long = "alert-webhook",
+        value_name = "URL",
+        help = "POST change alerts as JSON to this URL (repeatable)"
+    )]
+    pub webhooks: Vec<String>,
+
+    #[arg(
+        long = "alert-file",
+        value_name = "PATH",
+        help = "Append change alerts as JSON lines to this file (repeatable)"
+    )]
+    pub files: Vec<PathBuf>,
+}
+
+impl WatchArgs {
+    /// Apply the command-line overrides on top of the configured watch settings
+    pub fn apply(&self, base: &WatchOptions) -> WatchOptions {
+        let mut options = base.clone();
+
+        if let Some(schedule) = &self.schedule {
+            options.schedule = Some(schedule.clone());
+        }
+        if let Some(detection) = self.detection {
+            options.detection = detection;
+        }
+        if let Some(distance) = self.hash_distance {
+            options.hash_distance = distance;
+        }
+        options.alerts.extend(
+            self.webhooks
+                .iter()
+                .map(|url| AlertSink::Webhook { url: url.clone() }),
+        );
+        options.alerts.extend(
+            self.files
+                .iter()
+                .map(|path| AlertSink::File { path: path.clone() }),
+        );
+
+        options
+    }
+}
+
+/// Tiled full-page capture settings, layered over the `full_page` config section
+#[derive(Args, Debug, Clone, Default)]
+pub struct TilingArgs {
+    #[arg(
+        long,
+        requires = "full_page",
+        help = "Scroll the page to load lazy content and capture it in stitched tiles"
+    )]
+    pub tiled: bool,
+
+    #[arg(long, help = "Maximum full page height in CSS pixels when tiling")]
+    pub max_height: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Delay in milliseconds after each scroll step when tiling"
+    )]
+    pub scroll_delay: Option<u64>,
+
+    #[arg(long, help = "Keep fixed and sticky elements visible in every tile")]
+    pub keep_sticky: bool,
+}
+
+impl TilingArgs {
+    pub fn is_empty(&self) -> bool {
+        !self.tiled && self.max_height.is_none() && self.scroll_delay.is_none() && !self.keep_sticky
+    }
+
+    /// Apply the command-line overrides on top of the configured full-page settings
+    pub fn apply(&self, base: &FullPageOptions) -> FullPageOptions {
+        let mut options = base.clone();
+
+        if self.tiled {
+            options.tiled = true;
+        }
+        if let Some(max_height) = self.max_height {
+            options.max_height = max_height;
+        }
+        if let Some(delay) = self.scroll_delay {
+            options.scroll_delay_ms = delay;
+        }
+        if self.keep_sticky {
+            options.hide_sticky = false;
+        }
+
+        options
+    }
+}
+
+/// Extra headers, cookies and credentials sent with the page load
+#[derive(Args, Debug, Clone, Default)]
+pub struct HttpArgs {
+    #[arg(
+        long = "header",
+        value_name = "NAME: VALUE",
+        value_parser = parse_header,
+        help = "Extra HTTP header sent to the captured origin (repeatable)"
+    )]
+    pub headers: Vec<(String, String)>,
+
+    #[arg(
+        long = "cookie",
+        value_name = "NAME=VALUE",
+        help = "Cookie scoped to the URL (repeatable)"
+    )]
+    pub cookies: Vec<Cookie>,
+
+    #[arg(
+        long,
+        value_name = "USER:PASSWORD",
+        help = "HTTP basic auth credentials"
+    )]
+    pub basic_auth: Option<BasicAuth>,
+
+    #[arg(long, help = "User agent for the page load")]
+    pub user_agent: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "STATUSES",
+        help = "Fail unless the page responds with one of these statuses (e.g. 2xx,304)"
+    )]
+    pub accept_statuses: Option<AcceptStatuses>,
+}
+
+impl HttpArgs {
+    pub fn apply(&self, request: &mut ScreenshotRequest) {
+        request.headers.extend(self.headers.iter().cloned());
+        request.cookies.extend(self.cookies.iter().cloned());
+        request.basic_auth = self.basic_auth.clone();
+        request.user_agent = self.user_agent.clone();
+        request.accept_statuses = self.accept_statuses.clone();
+    }
+}
+
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("Header must be given as 'Name: value', got '{s}'"))?;
+
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
 #[derive(Debug, Clone)]
 pub struct BatchOptions {
     pub input: PathBuf,
@@ -133,9 +1033,18 @@ pub struct BatchOptions {
     pub format: Option<String>,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    pub device: Option<String>,
+    pub viewports: Vec<ViewportTarget>,
     pub full_page: bool,
     pub wait: Option<u64>,
     pub progress_interval: Option<u64>,
+    pub waits: WaitArgs,
+    pub encoder: EncoderArgs,
+    pub pdf: PdfArgs,
+    pub tiling: TilingArgs,
+    pub injection: InjectionArgs,
+    pub emulation: EmulationArgs,
+    pub http: HttpArgs,
 }
 
 #[derive(Debug, Clone)]
@@ -145,10 +1054,20 @@ pub struct SingleOptions {
     pub format: Option<String>,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    pub device: Option<String>,
+    pub viewports: Vec<ViewportTarget>,
     pub full_page: bool,
     pub wait: Option<u64>,
     pub selector: Option<String>,
+    pub elements: ElementArgs,
     pub priority: Option<String>,
+    pub waits: WaitArgs,
+    pub encoder: EncoderArgs,
+    pub pdf: PdfArgs,
+    pub tiling: TilingArgs,
+    pub injection: InjectionArgs,
+    pub emulation: EmulationArgs,
+    pub http: HttpArgs,
 }
 
 #[derive(Debug, Clone)]
@@ -156,14 +1075,26 @@ pub struct RequestOptions {
     pub format: Option<String>,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    pub device: Option<String>,
+    pub viewports: Vec<ViewportTarget>,
     pub full_page: bool,
     pub wait: Option<u64>,
     pub selector: Option<String>,
+    pub elements: ElementArgs,
+    pub waits: WaitArgs,
+    pub encoder: EncoderArgs,
+    pub pdf: PdfArgs,
+    pub tiling: TilingArgs,
+    pub injection: InjectionArgs,
+    pub emulation: EmulationArgs,
+    pub http: HttpArgs,
 }
 
 pub struct CliRunner {
     pub config: Config,
     pub service: Arc<ScreenshotService>,
+    pub metrics: Arc<Metrics>,
+    metrics_handle: Option<PrometheusHandle>,
 }
 
 impl CliRunner {
@@ -175,16 +1106,50 @@ impl CliRunner {
         if let Some(max_concurrent) = args.max_concurrent {
             config.max_concurrent_screenshots = max_concurrent;
         }
+        if let Some(max_tabs) = args.max_tabs {
+            config.max_tabs_per_browser = max_tabs;
+        }
+        if args.incognito {
+            config.isolation = BrowserIsolation::Incognito;
+        }
+        if args.deterministic {
+            config.deterministic.enabled = true;
+        }
         if let Some(timeout) = args.timeout {
             config.screenshot_timeout = std::time::Duration::from_secs(timeout);
         }
         if let Some(chrome_path) = &args.chrome_path {
             config.chrome_path = Some(chrome_path.clone());
         }
+        if let Some(devices) = &args.devices {
+            config.devices_file = Some(devices.clone());
+        }
 
-        let service = Arc::new(ScreenshotService::new(config.clone()).await?);
+        // The recorder has to be installed before any metric handles are registered
+        let serve_metrics = matches!(args.command, Commands::Server { metrics: true, .. });
+        let metrics_handle = if let Some(port) = args.metrics_port {
+            let bind = args.metrics_bind.as_deref().unwrap_or("127.0.0.1");
+            let ip: std::net::IpAddr = bind
+                .parse()
+                .map_err(|e| format!("Invalid metrics bind address '{bind}': {e}"))?;
+            let exporter = PrometheusExporter::new(std::net::SocketAddr::new(ip, port));
+            Some(exporter.start().await?)
+        } else if serve_metrics {
+            Some(PrometheusExporter::install_recorder()?)
+        } else {
+            None
+        };
+
+        let metrics = Arc::new(Metrics::new());
+        let service =
+            Arc::new(ScreenshotService::with_metrics(config.clone(), metrics.clone()).await?);
 
-        Ok(Self { config, service })
+        Ok(Self {
+            config,
+            service,
+            metrics,
+            metrics_handle,
+        })
     }
 
     pub async fn run(&self, command: Commands) -> Result<(), Box<dyn std::error::Error>> {
@@ -196,9 +1161,18 @@ impl CliRunner {
                 format,
                 width,
                 height,
+                device,
+                viewports,
                 full_page,
                 wait,
                 progress_interval,
+                waits,
+                encoder,
+                pdf,
+                tiling,
+                injection,
+                emulation,
+                http,
             } => {
                 self.run_batch(BatchOptions {
                     input,
@@ -207,9 +1181,18 @@ impl CliRunner {
                     format,
                     width,
                     height,
+                    device,
+                    viewports,
                     full_page,
                     wait,
                     progress_interval,
+                    waits,
+                    encoder,
+                    pdf,
+                    tiling,
+                    injection,
+                    emulation,
+                    http,
                 })
                 .await
             }
@@ -219,10 +1202,20 @@ impl CliRunner {
                 format,
                 width,
                 height,
+                device,
+                viewports,
                 full_page,
                 wait,
                 selector,
+                elements,
                 priority,
+                waits,
+                encoder,
+                pdf,
+                tiling,
+                injection,
+                emulation,
+                http,
             } => {
                 self.run_single(SingleOptions {
                     url,
@@ -230,10 +1223,20 @@ impl CliRunner {
                     format,
                     width,
                     height,
+                    device,
+                    viewports,
                     full_page,
                     wait,
                     selector,
+                    elements,
                     priority,
+                    waits,
+                    encoder,
+                    pdf,
+                    tiling,
+                    injection,
+                    emulation,
+                    http,
                 })
                 .await
             }
@@ -242,7 +1245,61 @@ impl CliRunner {
                 bind,
                 metrics,
                 health,
-            } => self.run_server(port, bind, metrics, health).await,
+                max_batch_size,
+            } => {
+                self.run_server(port, bind, metrics, health, max_batch_size)
+                    .await
+            }
+            Commands::Diff {
+                baseline,
+                current,
+                url,
+                output,
+                diff,
+            } => self.run_diff(baseline, current, url, output, diff).await,
+            Commands::Baseline { action } => match action {
+                BaselineCommand::Update {
+                    capture,
+                    store,
+                    new_only,
+                    prune,
+                } => {
+                    self.run_baseline_update(capture, store, new_only, prune)
+                        .await
+                }
+                BaselineCommand::Check {
+                    capture,
+                    store,
+                    report,
+                    diff_output,
+                    fail_on_new,
+                    subset,
+                    diff,
+                } => {
+                    let scope = if subset {
+                        MissingScope::CapturedPages
+                    } else {
+                        MissingScope::All
+                    };
+                    self.run_baseline_check(
+                        capture,
+                        store,
+                        report,
+                        diff_output,
+                        fail_on_new,
+                        scope,
+                        diff,
+                    )
+                    .await
+                }
+            },
+            Commands::Watch {
+                capture,
+                store,
+                once,
+                watch,
+                diff,
+            } => self.run_watch(capture, store, once, watch, diff).await,
             Commands::Validate { config } => self.validate_config(config).await,
             Commands::Health { detailed } => self.show_health(detailed).await,
         }
@@ -251,11 +1308,11 @@ impl CliRunner {
     pub async fn run_batch(&self, options: BatchOptions) -> Result<(), Box<dyn std::error::Error>> {
         info!("Starting batch screenshot processing");
 
-        // Read URLs from file
-        let urls = self.read_urls_from_file(&options.input).await?;
+        // Read URLs (and any per-URL actions) from file
+        let entries = self.read_entries_from_file(&options.input).await?;
         info!(
             "Loaded {} URLs from {}",
-            urls.len(),
+            entries.len(),
             options.input.display()
         );
 
@@ -263,17 +1320,31 @@ impl CliRunner {
         fs::create_dir_all(&options.output).await?;
 
         // Create requests
-        let requests = self.create_requests(
-            urls,
-            RequestOptions {
-                format: options.format,
-                width: options.width,
-                height: options.height,
-                full_page: options.full_page,
-                wait: options.wait,
-                selector: None,
-            },
-        )?;
+        let request_options = RequestOptions {
+            format: options.format,
+            width: options.width,
+            height: options.height,
+            device: options.device,
+            viewports: options.viewports,
+            full_page: options.full_page,
+            wait: options.wait,
+            selector: None,
+            elements: ElementArgs::default(),
+            waits: options.waits,
+            encoder: options.encoder,
+            pdf: options.pdf,
+            tiling: options.tiling,
+            injection: options.injection,
+            emulation: options.emulation,
+            http: options.http,
+        };
+        let mut requests = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let mut request =
+                self.create_single_request(entry.url, request_options.clone(), None)?;
+            request.actions = entry.actions;
+            requests.push(request);
+        }
 
         // Set up progress tracking
         let progress_tracker = Arc::new(ProgressTracker::new(requests.len()));
@@ -308,7 +1379,11 @@ impl CliRunner {
             progress_tracker.record_completion(result.success);
 
             if result.success {
-                let filename = self.generate_filename(&result.url, &result.format);
+                let filename = self.generate_filename(
+                    &result.url,
+                    &result.format,
+                    result.metadata.label().as_deref(),
+                );
                 let filepath = options.output.join(filename);
 
                 fs::write(&filepath, &result.data).await?;
@@ -340,27 +1415,74 @@ impl CliRunner {
                 format: options.format,
                 width: options.width,
                 height: options.height,
+                device: options.device,
+                viewports: options.viewports,
                 full_page: options.full_page,
                 wait: options.wait,
                 selector: options.selector,
+                elements: options.elements,
+                waits: options.waits,
+                encoder: options.encoder,
+                pdf: options.pdf,
+                tiling: options.tiling,
+                injection: options.injection,
+                emulation: options.emulation,
+                http: options.http,
             },
             options.priority,
         )?;
 
-        let result = self.service.screenshot_single(request).await?;
+        // Multi-viewport and multi-element requests produce several results
+        let results = self.service.process_requests(vec![request]).await?;
+        if results.is_empty() {
+            println!("No element matched the selector; nothing was captured");
+            return Ok(());
+        }
+        let mut failed = Vec::new();
+
+        for result in results {
+            if !result.success {
+                error!(
+                    "Failed to take screenshot{}: {:?}",
+                    result
+                        .metadata
+                        .label()
+                        .map(|label| format!(" at {label}"))
+                        .unwrap_or_default(),
+                    result.error
+                );
+                failed.push(format!("{:?}", result.error));
+                continue;
+            }
+
+            let output = match result.metadata.label() {
+                Some(label) => labelled_path(&options.output, &label),
+                None => options.output.clone(),
+            };
 
-        if result.success {
             // Create output directory if it doesn't exist
-            if let Some(parent) = options.output.parent() {
+            if let Some(parent) = output.parent() {
                 fs::create_dir_all(parent).await?;
             }
 
-            fs::write(&options.output, &result.data).await?;
-            info!("Screenshot saved to: {}", options.output.display());
+            fs::write(&output, &result.data).await?;
+            info!("Screenshot saved to: {}", output.display());
 
             println!("Screenshot captured successfully:");
             println!("  URL: {}", result.url);
-            println!("  Output: {}", options.output.display());
+            println!("  Output: {}", output.display());
+            if let Some(label) = &result.metadata.viewport_label {
+                println!(
+                    "  Viewport: {label} ({}x{})",
+                    result.metadata.viewport.width, result.metadata.viewport.height
+                );
+            }
+            if let Some(status) = result.metadata.response_status {
+                println!("  Status: {status}");
+            }
+            for redirect in &result.metadata.redirect_chain {
+                println!("  Redirect: {} ({})", redirect.url, redirect.status);
+            }
             println!("  Format: {:?}", result.format);
             println!("  Size: {} bytes", result.data.len());
             println!("  Duration: {:?}", result.duration);
@@ -368,26 +1490,445 @@ impl CliRunner {
             if let Some(title) = &result.metadata.page_title {
                 println!("  Title: {title}");
             }
-        } else {
-            error!("Failed to take screenshot: {:?}", result.error);
-            return Err(format!("Screenshot failed: {:?}", result.error).into());
+        }
+
+        if !failed.is_empty() {
+            return Err(format!("Screenshot failed: {}", failed.join(", ")).into());
         }
 
         Ok(())
     }
 
+    pub async fn run_diff(
+        &self,
+        baseline: PathBuf,
+        current: Option<PathBuf>,
+        url: Option<String>,
+        output: Option<PathBuf>,
+        args: DiffArgs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let options = args.apply(&self.config.diff);
+        options.validate()?;
+        let fail_threshold = options.fail_threshold;
+
+        let image_diff = match (current, url) {
+            (_, Some(url)) => {
+                info!("Capturing {} to compare with {}", url, baseline.display());
+                // Configured ignore selectors are added by the service
+                let request = ScreenshotRequest {
+                    url,
+                    ignore_selectors: args.ignore_selectors.clone(),
+                    ..Default::default()
+                };
+                let result = self.service.screenshot_single(request).await?;
+                if !result.success {
+                    return Err(format!("Screenshot failed: {:?}", result.error).into());
+                }
+
+                let baseline = baseline.clone();
+                tokio::task::spawn_blocking(move || {
+                    diff_result(&result, &load_image(&baseline)?, &options)
+                })
+                .await??
+            }
+            (Some(current), None) if current.is_dir() => {
+                return self
+                    .diff_directories(baseline, current, output, options)
+                    .await;
+            }
+            (Some(current), None) => {
+                if !options.ignore_selectors.is_empty() {
+                    warn!("Ignore selectors only apply to fresh captures made with --url");
+                }
+                let (baseline, current) = (baseline.clone(), current.clone());
+                tokio::task::spawn_blocking(move || diff_files(&baseline, &current, &options))
+                    .await??
+            }
+            (None, None) => return Err("Either --current or --url is required".into()),
+        };
+
+        println!("Visual diff against {}:", baseline.display());
+        print_image_diff(&image_diff);
+        if let Some(output) = &output {
+            write_diff_image(output, &image_diff).await?;
+            println!("  Diff image: {}", output.display());
+        }
+
+        if image_diff.exceeds(fail_threshold) {
+            return Err(format!(
+                "{:.3}% of pixels changed, above the {fail_threshold}% threshold",
+                image_diff.mismatch_percentage
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    async fn diff_directories(
+        &self,
+        baseline: PathBuf,
+        current: PathBuf,
+        output: Option<PathBuf>,
+        options: DiffOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !options.ignore_selectors.is_empty() {
+            warn!("Ignore selectors only apply to fresh captures made with --url");
+        }
+
+        let entries = {
+            let (baseline, current) = (baseline.clone(), current.clone());
+            tokio::task::spawn_blocking(move || diff_directories(&baseline, &current, &options))
+                .await??
+        };
+
+        println!(
+            "Visual diff of {} against {}:",
+            current.display(),
+            baseline.display()
+        );
+        let (mut changed, mut missing) = (0, 0);
+        for entry in &entries {
+            match (&entry.status, &entry.diff) {
+                (DiffStatus::Changed, Some(image_diff)) => {
+                    changed += 1;
+                    println!(
+                        "  changed    {} ({:.3}%)",
+                        entry.path.display(),
+                        image_diff.mismatch_percentage
+                    );
+                    if let Some(output) = &output {
+                        let path = output.join(&entry.path).with_extension("png");
+                        write_diff_image(&path, image_diff).await?;
+                    }
+                }
+                (DiffStatus::Unchanged, _) => {
+                    println!("  unchanged  {}", entry.path.display())
+                }
+                (DiffStatus::New, _) => println!("  new        {}", entry.path.display()),
+                (DiffStatus::Missing, _) => {
+                    missing += 1;
+                    println!("  missing    {}", entry.path.display())
+                }
+                (DiffStatus::Changed, None) => {}
+            }
+        }
+
+        println!(
+            "{} compared, {} changed, {} missing",
+            entries.iter().filter(|entry| entry.diff.is_some()).count(),
+            changed,
+            missing
+        );
+        // A baseline without a current image is a page that stopped rendering
+        if changed > 0 || missing > 0 {
+            return Err(format!(
+                "{changed} images changed beyond the threshold, {missing} baseline images missing"
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Capture every page of a baseline or watch command's input file
+    async fn capture_pages(
+        &self,
+        args: &CaptureArgs,
+        ignore_selectors: &[String],
+    ) -> Result<Vec<ScreenshotResult>, Box<dyn std::error::Error>> {
+        let entries = self.read_entries_from_file(&args.input).await?;
+        info!(
+            "Capturing {} URLs from {}",
+            entries.len(),
+            args.input.display()
+        );
+
+        let request_options = args.request_options();
+        let mut requests = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let mut request =
+                self.create_single_request(entry.url, request_options.clone(), None)?;
+            request.actions = entry.actions;
+            request.ignore_selectors = ignore_selectors.to_vec();
+            requests.push(request);
+        }
+
+        let mut processor = BatchProcessor::new(self.config.clone(), self.service.clone());
+        Ok(processor.process_batch(requests).await)
+    }
+
+    pub async fn run_baseline_update(
+        &self,
+        args: CaptureArgs,
+        store_path: PathBuf,
+        new_only: bool,
+        prune: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let results = self.capture_pages(&args, &[]).await?;
+        let mut store = BaselineStore::open(&store_path)?;
+
+        let mut captured = BTreeSet::new();
+        let mut failed_urls = BTreeSet::new();
+        let (mut accepted, mut kept, mut errors) = (0, 0, 0);
+        for result in &results {
+            if !result.success {
+                warn!("Failed to screenshot {}: {:?}", result.url, result.error);
+                failed_urls.insert(result.url.clone());
+                continue;
+            }
+
+            let key = BaselineKey::for_result(result, args.device.as_deref());
+            captured.insert(key.clone());
+            if new_only && store.get(&key).is_some() {
+                kept += 1;
+                continue;
+            }
+
+            // Keep going so the manifest still records what was written
+            match store.accept(key, result) {
+                Ok(entry) => {
+                    info!("Accepted baseline: {}", entry.file);
+                    accepted += 1;
+                }
+                Err(e) => {
+                    warn!("Failed to accept baseline of {}: {}", result.url, e);
+                    errors += 1;
+                }
+            }
+        }
+
+        let mut pruned = 0;
+        if prune {
+            let stale: Vec<BaselineKey> = store
+                .entries()
+                .map(|entry| &entry.key)
+                .filter(|key| !captured.contains(*key) && !failed_urls.contains(&key.url))
+                .cloned()
+                .collect();
+            for key in stale {
+                match store.remove(&key) {
+                    Ok(_) => {
+                        info!("Pruned baseline of {}", key.url);
+                        pruned += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to prune baseline of {}: {}", key.url, e);
+                        errors += 1;
+                    }
+                }
+            }
+        }
+        store.save()?;
+
+        println!(
+            "Baselines in {}: {} accepted, {} kept, {} pruned, {} total",
+            store_path.display(),
+            accepted,
+            kept,
+            pruned,
+            store.len()
+        );
+        if !failed_urls.is_empty() {
+            return Err(format!("{} pages failed to capture", failed_urls.len()).into());
+        }
+        if errors > 0 {
+            return Err(format!("{errors} baselines could not be written").into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn run_baseline_check(
+        &self,
+        args: CaptureArgs,
+        store_path: PathBuf,
+        report_path: PathBuf,
+        diff_output: Option<PathBuf>,
+        fail_on_new: bool,
+        scope: MissingScope,
+        diff: DiffArgs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let options = diff.apply(&self.config.diff);
+        options.validate()?;
+
+        let store = BaselineStore::open(&store_path)?;
+        if store.is_empty() {
+            warn!(
+                "Baseline store {} is empty; run `baseline update` first",
+                store_path.display()
+            );
+        }
+
+        // Configured ignore selectors are added by the service
+        let results = self.capture_pages(&args, &diff.ignore_selectors).await?;
+        let device = args.device.clone();
+        let report = tokio::task::spawn_blocking(move || {
+            store.check(
+                &results,
+                device.as_deref(),
+                scope,
+                &options,
+                diff_output.as_deref(),
+            )
+        })
+        .await??;
+
+        if let Some(parent) = report_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&report_path, serde_json::to_string_pretty(&report)?).await?;
+
+        println!("Baseline check against {}:", store_path.display());
+        for changed in &report.changed {
+            println!(
+                "  changed    {} ({:.3}%)",
+                changed.key.file_stem(),
+                changed.mismatch_percentage
+            );
+        }
+        for key in &report.new {
+            println!("  new        {}", key.file_stem());
+        }
+        for key in &report.missing {
+            println!("  missing    {}", key.file_stem());
+        }
+        for failed in &report.failed {
+            println!("  failed     {}: {}", failed.url, failed.error);
+        }
+        println!(
+            "{} unchanged, {} changed, {} new, {} missing, {} failed",
+            report.unchanged.len(),
+            report.changed.len(),
+            report.new.len(),
+            report.missing.len(),
+            report.failed.len()
+        );
+        println!("Report: {}", report_path.display());
+
+        if !report.passed {
+            return Err("Baseline check failed".into());
+        }
+        if fail_on_new && !report.new.is_empty() {
+            return Err(format!("{} pages have no baseline", report.new.len()).into());
+        }
+
+        Ok(())
+    }
+
+    pub async fn run_watch(
+        &self,
+        args: CaptureArgs,
+        store_path: PathBuf,
+        once: bool,
+        watch: WatchArgs,
+        diff: DiffArgs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let options = watch.apply(&self.config.watch);
+        options.validate()?;
+        let diff_options = diff.apply(&self.config.diff);
+        diff_options.validate()?;
+        let schedule = match (&options.schedule, once) {
+            (_, true) => None,
+            (Some(schedule), false) => Some(schedule.clone()),
+            (None, false) => return Err("A schedule is required unless --once is given".into()),
+        };
+
+        let mut store = WatchStore::open(&store_path)?;
+        let alert_client = AlertSink::client()?;
+        info!(
+            "Watching pages from {} into {}",
+            args.input.display(),
+            store_path.display()
+        );
+
+        loop {
+            // The input file is read every round, so pages can be added while watching
+            let round = match self.capture_pages(&args, &diff.ignore_selectors).await {
+                Ok(results) => {
+                    let (device, round_options, round_diff) =
+                        (args.device.clone(), options.clone(), diff_options.clone());
+                    let round;
+                    (store, round) = tokio::task::spawn_blocking(move || {
+                        let round = store.record_round(
+                            &results,
+                            device.as_deref(),
+                            &round_options,
+                            &round_diff,
+                        );
+                        (store, round)
+                    })
+                    .await?;
+                    round.map_err(Into::into)
+                }
+                Err(e) => Err(e),
+            };
+
+            match round {
+                Ok(round) => {
+                    for alert in &round.alerts {
+                        warn!("Change Alert: {}", alert.message);
+                        for sink in &options.alerts {
+                            if let Err(e) = sink.send(&alert_client, alert).await {
+                                error!("Failed to send change alert: {}", e);
+                            }
+                        }
+                    }
+                    println!(
+                        "{}: {} changed, {} unchanged, {} new, {} failed",
+                        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+                        round.alerts.len(),
+                        round.unchanged,
+                        round.first_seen,
+                        round.failed
+                    );
+                }
+                Err(e) if schedule.is_none() => return Err(e),
+                // A scheduled watch outlives a bad round, e.g. an unreadable input file
+                Err(e) => error!("Watch round failed: {}", e),
+            }
+
+            let Some(schedule) = &schedule else {
+                return Ok(());
+            };
+            let now = chrono::Utc::now();
+            let next = schedule
+                .next_after(now)
+                .ok_or_else(|| format!("Schedule '{schedule}' never runs again"))?;
+            info!("Next capture at {}", next);
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+        }
+    }
+
     pub async fn run_server(
         &self,
         port: u16,
-        _bind: Option<String>,
-        _metrics: bool,
-        _health: bool,
+        bind: Option<String>,
+        metrics: bool,
+        health: bool,
+        max_batch_size: usize,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Starting server on port {}", port);
-
-        // TODO: Implement HTTP server
-        // This would typically use a web framework like warp or axum
-        println!("Server mode not yet implemented");
+        let bind = bind.unwrap_or_else(|| "127.0.0.1".to_string());
+        let ip: std::net::IpAddr = bind
+            .parse()
+            .map_err(|e| format!("Invalid bind address '{bind}': {e}"))?;
+
+        info!("Starting server on {}:{}", ip, port);
+
+        let server = ApiServer::new(
+            self.service.clone(),
+            ServerOptions {
+                addr: std::net::SocketAddr::new(ip, port),
+                metrics: if metrics {
+                    self.metrics_handle.clone()
+                } else {
+                    None
+                },
+                health,
+                max_batch_size,
+            },
+        );
+        server.run().await?;
 
         Ok(())
     }
@@ -400,16 +1941,55 @@ impl CliRunner {
 
         let config_content = fs::read_to_string(&config_path).await?;
         let config: Config = serde_json::from_str(&config_content)?;
+        config.encoder.validate()?;
+        config.full_page.validate()?;
+        config.emulation.validate()?;
+        config.pdf.validate()?;
+        config.diff.validate()?;
+        config.watch.validate()?;
+        let devices = match &config.devices_file {
+            Some(path) => DeviceCatalog::with_file(path)?,
+            None => DeviceCatalog::builtin(),
+        };
 
         println!("Configuration is valid:");
         println!("  Browser pool size: {}", config.browser_pool_size);
         println!("  Max concurrent: {}", config.max_concurrent_screenshots);
+        println!("  Max tabs per browser: {}", config.max_tabs_per_browser);
+        println!("  Isolation: {:?}", config.isolation);
         println!("  Timeout: {:?}", config.screenshot_timeout);
         println!("  Output format: {:?}", config.output_format);
         println!(
             "  Viewport: {}x{}",
             config.viewport.width, config.viewport.height
         );
+        println!("  Device presets: {}", devices.len());
+        println!("  JPEG quality: {}", config.encoder.jpeg_quality);
+        if config.encoder.webp_lossless {
+            println!("  WebP: lossless");
+        } else {
+            println!("  WebP: lossy, quality {}", config.encoder.webp_quality);
+        }
+        if config.encoder.reencodes_png() {
+            println!(
+                "  PNG: compression {:?}, filter {:?}",
+                config.encoder.png_compression, config.encoder.png_filter
+            );
+        }
+        if config.full_page.tiled {
+            println!(
+                "  Full page: tiled, max height {}px",
+                config.full_page.max_height
+            );
+        }
+        if config.full_page.tiled
+            && config.output_format == OutputFormat::Webp
+            && !config.encoder.webp_lossless
+        {
+            println!(
+                "  Warning: tiled full-page WebP is encoded losslessly; webp_quality is ignored"
+            );
+        }
 
         Ok(())
     }
@@ -427,15 +2007,21 @@ impl CliRunner {
         println!("  Failed instances: {}", pool_stats.failed_instances);
         println!("  Available instances: {}", pool_stats.available_instances);
         println!("  Total screenshots: {}", pool_stats.total_screenshots);
+        println!(
+            "  Open tabs: {}/{}",
+            pool_stats.active_tabs, pool_stats.tab_capacity
+        );
 
         if detailed {
             let health_checks = self.service.browser_pool.health_check().await;
             println!("\nDetailed Instance Health:");
             for health in health_checks {
                 println!(
-                    "  Instance {}: {:?} - Screenshots: {}, Age: {:?}, Idle: {:?}, Failures: {}",
+                    "  Instance {}: {:?} - Tabs: {}/{}, Screenshots: {}, Age: {:?}, Idle: {:?}, Failures: {}",
                     health.id,
                     health.status,
+                    health.active_tabs,
+                    health.max_tabs,
                     health.screenshot_count,
                     health.age,
                     health.idle_time,
@@ -456,15 +2042,21 @@ impl CliRunner {
         &self,
         path: &PathBuf,
     ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(path).await?;
-        let urls: Vec<String> = content
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty() && !line.starts_with('#'))
-            .map(|line| line.to_string())
-            .collect();
+        let entries = self.read_entries_from_file(path).await?;
+        Ok(entries.into_iter().map(|entry| entry.url).collect())
+    }
 
-        Ok(urls)
+    /// Read batch entries, one per line
+    ///
+    /// A line is either a bare URL or a JSON object such as
+    /// `{"url": "https://example.com", "actions": [{"type": "click", "selector": "#accept"}]}`.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub async fn read_entries_from_file(
+        &self,
+        path: &PathBuf,
+    ) -> Result<Vec<BatchEntry>, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path).await?;
+        parse_batch_entries(&content)
     }
 
     pub fn create_requests(
@@ -499,6 +2091,20 @@ impl CliRunner {
             None
         };
 
+        let devices = options
+            .viewports
+            .iter()
+            .filter_map(|target| match target {
+                ViewportTarget::Device(name) => Some(name),
+                ViewportTarget::Custom { .. } => None,
+            })
+            .chain(options.device.as_ref());
+        for device in devices {
+            if self.service.devices().get(device).is_none() {
+                return Err(format!("Unknown device: {device}").into());
+            }
+        }
+
         let wait_time = options.wait.map(std::time::Duration::from_millis);
 
         let request_priority = match priority.as_deref() {
@@ -509,18 +2115,63 @@ impl CliRunner {
             _ => Priority::Normal,
         };
 
-        Ok(ScreenshotRequest {
+        let format = options
+            .format
+            .as_deref()
+            .map(str::parse::<OutputFormat>)
+            .transpose()?;
+        let wants_pdf = format.as_ref().unwrap_or(&self.config.output_format) == &OutputFormat::Pdf;
+        let pdf = wants_pdf.then(|| options.pdf.apply(&self.config.pdf));
+
+        let encoder = if options.encoder.is_empty() {
+            None
+        } else {
+            let settings = options.encoder.apply(&self.config.encoder);
+            settings.validate()?;
+            Some(settings)
+        };
+
+        let full_page_options = if options.tiling.is_empty() {
+            None
+        } else {
+            let settings = options.tiling.apply(&self.config.full_page);
+            settings.validate()?;
+            Some(settings)
+        };
+
+        let mut request = ScreenshotRequest {
             url,
             priority: request_priority,
             custom_viewport,
+            device: options.device,
+            viewports: options.viewports,
             wait_time,
+            wait_for: options.waits.conditions(),
             element_selector: options.selector,
             full_page: options.full_page,
+            full_page_options,
+            format,
+            encoder,
+            pdf,
             ..Default::default()
-        })
+        };
+        options.elements.apply(&mut request);
+        options.injection.apply(&mut request);
+        options.emulation.apply(&mut request);
+        options.http.apply(&mut request);
+
+        Ok(request)
     }
 
-    pub fn generate_filename(&self, url: &str, format: &crate::OutputFormat) -> String {
+    /// File name for a capture, suffixed with its result label if there is one
+    ///
+    /// See `ScreenshotMetadata::label`.
+    pub fn generate_filename(
+        &self,
+        url: &str,
+        format: &OutputFormat,
+        label: Option<&str>,
+    ) -> String {
         let sanitized = url
             .replace("https://", "")
             .replace("http://", "")
@@ -530,14 +2181,59 @@ impl CliRunner {
             .replace("=", "_")
             .replace(":", "_");
 
-        let extension = match format {
-            crate::OutputFormat::Png => "png",
-            crate::OutputFormat::Jpeg => "jpg",
-            crate::OutputFormat::Webp => "webp",
-        };
+        match label {
+            Some(label) => format!("{sanitized}_{}.{}", label_slug(label), format.extension()),
+            None => format!("{sanitized}.{}", format.extension()),
+        }
+    }
+}
+
+/// Lowercase, file-name safe form of a viewport label
+fn label_slug(label: &str) -> String {
+    sanitize_filename(&label.to_lowercase().replace(' ', "-"))
+}
 
-        format!("{sanitized}.{extension}")
+/// `shots/home.png` with label `iPhone 15` becomes `shots/home-iphone-15.png`
+pub fn labelled_path(path: &std::path::Path, label: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut name = format!("{stem}-{}", label_slug(label));
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
     }
+    path.with_file_name(name)
+}
+
+/// A URL from a batch input file together with the actions to run on it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BatchEntry {
+    pub url: String,
+    #[serde(default)]
+    pub actions: Vec<PageAction>,
+}
+
+/// Parse batch input where each line is a URL or a JSON `BatchEntry`
+pub fn parse_batch_entries(content: &str) -> Result<Vec<BatchEntry>, Box<dyn std::error::Error>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            if line.starts_with('{') {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Invalid batch entry on line {}: {e}", index + 1).into())
+            } else {
+                Ok(BatchEntry {
+                    url: line.to_string(),
+                    actions: Vec::new(),
+                })
+            }
+        })
+        .collect()
 }
 
 pub fn setup_logging(verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
@@ -3,7 +3,13 @@
 //! This module provides all configuration structures and utilities for the screenshot tool,
 //! including browser settings, optimization parameters, and output formats.
 
+use crate::{
+    Clip, DeterministicOptions, DiffOptions, ElementCapture, Emulation, PageAction, PageInjection,
+    ScreenshotError, WaitCondition, WatchOptions,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// Main configuration structure for the screenshot tool
@@ -40,6 +46,13 @@ pub struct Config {
     /// Should be higher than browser_pool_size for optimal utilization.
     pub max_concurrent_screenshots: usize,
 
+    /// Maximum number of tabs each browser instance renders at once (default: 4)
+    ///
+    /// Tabs share a Chrome process, so the pool can serve up to
+    /// `browser_pool_size * max_tabs_per_browser` captures concurrently.
+    #[serde(default = "default_max_tabs_per_browser")]
+    pub max_tabs_per_browser: usize,
+
     /// Timeout for individual screenshot operations (default: 30 seconds)
     ///
     /// Pages that take longer than this will be marked as failed and retried
@@ -77,6 +90,81 @@ pub struct Config {
     /// Helps prevent Chrome instances from consuming excessive memory during
     /// processing of complex pages.
     pub memory_limit: Option<usize>,
+
+    /// Print settings used when the output format is PDF
+    #[serde(default)]
+    pub pdf: PdfOptions,
+
+    /// Image encoder settings for JPEG, WebP and PNG output
+    #[serde(default)]
+    pub encoder: EncoderSettings,
+
+    /// Full-page capture settings used when a request sets `full_page`
+    #[serde(default)]
+    pub full_page: FullPageOptions,
+
+    /// Styles, scripts, hidden and redacted selectors applied to every page
+    #[serde(flatten)]
+    pub injection: PageInjection,
+
+    /// Color scheme, media, locale, timezone and geolocation emulated on every page
+    #[serde(flatten)]
+    pub emulation: Emulation,
+
+    /// Deterministic rendering, for byte-stable captures across runs
+    #[serde(default)]
+    pub deterministic: DeterministicOptions,
+
+    /// Visual diff settings used when comparing captures with baselines
+    #[serde(default)]
+    pub diff: DiffOptions,
+
+    /// Schedule, change detection and alert sinks of the `watch` command
+    #[serde(default)]
+    pub watch: WatchOptions,
+
+    /// Browser context isolation between requests (default: shared)
+    ///
+    /// `Incognito` opens every request in a fresh browser context so cookies,
+    /// storage and cache never leak between unrelated URLs.
+    #[serde(default)]
+    pub isolation: BrowserIsolation,
+
+    /// Maximum number of named sessions open at once (default: 32)
+    ///
+    /// Requests starting a new session beyond this limit fail with
+    /// `ScreenshotError::TooManySessions` until a session is closed or expires.
+    #[serde(default = "default_max_sessions")]
+    pub max_sessions: usize,
+
+    /// Named sessions unused for this long are closed (default: 10 minutes)
+    #[serde(default = "default_session_idle_timeout")]
+    pub session_idle_timeout: Duration,
+
+    /// JSON file of extra device presets added to the built-in catalogue
+    ///
+    /// See `DeviceCatalog::extend_from_file` for the format.
+    #[serde(default)]
+    pub devices_file: Option<PathBuf>,
+
+    /// Main document statuses that count as a successful load (default: any)
+    ///
+    /// When set, a page answering with any other status fails with
+    /// `ScreenshotError::UnacceptedStatus` instead of being captured.
+    #[serde(default)]
+    pub accept_statuses: Option<AcceptStatuses>,
+}
+
+fn default_max_tabs_per_browser() -> usize {
+    4
+}
+
+fn default_max_sessions() -> usize {
+    32
+}
+
+fn default_session_idle_timeout() -> Duration {
+    Duration::from_secs(600)
 }
 
 impl Default for Config {
@@ -84,6 +172,7 @@ impl Default for Config {
         Self {
             browser_pool_size: 10,
             max_concurrent_screenshots: 200,
+            max_tabs_per_browser: default_max_tabs_per_browser(),
             screenshot_timeout: Duration::from_secs(30),
             retry_attempts: 3,
             output_format: OutputFormat::Png,
@@ -92,6 +181,19 @@ impl Default for Config {
             chrome_path: None,
             user_agent: None,
             memory_limit: Some(1024 * 1024 * 1024), // 1GB
+            pdf: PdfOptions::default(),
+            encoder: EncoderSettings::default(),
+            full_page: FullPageOptions::default(),
+            injection: PageInjection::default(),
+            emulation: Emulation::default(),
+            deterministic: DeterministicOptions::default(),
+            diff: DiffOptions::default(),
+            watch: WatchOptions::default(),
+            isolation: BrowserIsolation::default(),
+            max_sessions: default_max_sessions(),
+            session_idle_timeout: default_session_idle_timeout(),
+            devices_file: None,
+            accept_statuses: None,
         }
     }
 }
@@ -117,7 +219,8 @@ impl Default for Config {
 ///     mobile: true,
 /// };
 /// ```
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Viewport {
     /// Viewport width in pixels (default: 1920)
     pub width: u32,
@@ -147,6 +250,64 @@ impl Default for Viewport {
     }
 }
 
+/// One of several viewports captured from a single page load
+///
+/// Either a device preset name or explicit dimensions, e.g.
+/// `["iPhone 15", {"label": "desktop", "width": 1440, "height": 900}]`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ViewportTarget {
+    /// A device from the `DeviceCatalog`
+    Device(String),
+    /// Explicit viewport dimensions; omitted fields use `Viewport::default()`
+    Custom {
+        #[serde(default)]
+        label: Option<String>,
+        #[serde(flatten)]
+        viewport: Viewport,
+    },
+}
+
+impl ViewportTarget {
+    /// Label identifying this viewport in results and filenames
+    pub fn label(&self) -> String {
+        match self {
+            ViewportTarget::Device(name) => name.clone(),
+            ViewportTarget::Custom { label, viewport } => label
+                .clone()
+                .unwrap_or_else(|| format!("{}x{}", viewport.width, viewport.height)),
+        }
+    }
+}
+
+impl std::str::FromStr for ViewportTarget {
+    type Err = String;
+
+    /// Parse `WIDTHxHEIGHT` as explicit dimensions, anything else as a device name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Viewport must not be empty".to_string());
+        }
+
+        let dimensions = s
+            .split_once('x')
+            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+
+        Ok(match dimensions {
+            Some((width, height)) => ViewportTarget::Custom {
+                label: None,
+                viewport: Viewport {
+                    width,
+                    height,
+                    ..Viewport::default()
+                },
+            },
+            None => ViewportTarget::Device(s.to_string()),
+        })
+    }
+}
+
 /// Performance optimization settings for screenshot rendering
 ///
 /// Controls various browser behaviors to optimize screenshot speed and quality.
@@ -202,8 +363,8 @@ pub struct OptimizationSettings {
 
     /// Wait for network requests to complete (default: false)
     ///
-    /// When true, waits for all network activity to finish before taking
-    /// the screenshot. Increases accuracy but reduces speed.
+    /// When true, waits until no requests have been in flight for 500ms
+    /// before taking the screenshot. Increases accuracy but reduces speed.
     pub wait_for_network_idle: bool,
 
     /// Disable CSS loading (default: false)
@@ -217,6 +378,13 @@ pub struct OptimizationSettings {
     /// Prevents Flash, Java, and other plugins from loading to improve
     /// security and performance.
     pub disable_plugins: bool,
+
+    /// Paths to EasyList / Adblock Plus filter list files (default: none)
+    ///
+    /// Lists are compiled once at startup and shared across the browser pool.
+    /// Exception (`@@`) rules in these lists also override the built-in blocking.
+    #[serde(default)]
+    pub filter_lists: Vec<PathBuf>,
 }
 
 impl Default for OptimizationSettings {
@@ -229,24 +397,568 @@ impl Default for OptimizationSettings {
             wait_for_network_idle: false,
             disable_css: false,
             disable_plugins: true,
+            filter_lists: Vec::new(),
         }
     }
 }
 
-/// Supported output image formats for screenshots
+/// Supported output formats for screenshots
 ///
 /// Each format has different characteristics:
 /// - PNG: Lossless compression, larger files, best quality
 /// - JPEG: Lossy compression, smaller files, good for photos
 /// - WebP: Modern format with excellent compression and quality
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// - PDF: Print-quality document rendered with Chrome's print-to-PDF
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum OutputFormat {
     /// PNG format - lossless compression, best quality
+    #[serde(alias = "png")]
     Png,
     /// JPEG format - lossy compression, smaller files
+    #[serde(alias = "jpeg", alias = "jpg")]
     Jpeg,
     /// WebP format - modern compression, good balance of size and quality
+    #[serde(alias = "webp")]
     Webp,
+    /// PDF document - paginated, vector text, configured by `PdfOptions`
+    #[serde(alias = "pdf")]
+    Pdf,
+}
+
+impl OutputFormat {
+    /// File extension (without the dot) for this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Pdf => "pdf",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    /// Parse a format name as accepted by the `--format` CLI option
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(OutputFormat::Png),
+            "jpeg" | "jpg" => Ok(OutputFormat::Jpeg),
+            "webp" => Ok(OutputFormat::Webp),
+            "pdf" => Ok(OutputFormat::Pdf),
+            _ => Err(format!("Unknown output format: {s}")),
+        }
+    }
+}
+
+/// How requests are isolated from each other inside a browser instance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum BrowserIsolation {
+    /// Pages share the browser's default context (cookies, storage, cache)
+    #[default]
+    Shared,
+    /// Each request gets a fresh incognito context, disposed afterwards
+    Incognito,
+}
+
+/// HTTP status codes of the main document that count as a successful load
+///
+/// Written as a comma separated list of codes, inclusive ranges and classes,
+/// e.g. `"2xx,304"` or `"200-299,404"`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AcceptStatuses(Vec<(u16, u16)>);
+
+impl AcceptStatuses {
+    /// Whether the status code matches any of the ranges
+    pub fn accepts(&self, status: u16) -> bool {
+        self.0
+            .iter()
+            .any(|(min, max)| (*min..=*max).contains(&status))
+    }
+}
+
+impl std::str::FromStr for AcceptStatuses {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_code = |code: &str| {
+            code.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (100..=599).contains(code))
+                .ok_or_else(|| format!("Invalid HTTP status: {code}"))
+        };
+
+        let ranges = s
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let lower = part.to_lowercase();
+                if let Some(class) = lower.strip_suffix("xx") {
+                    let base = parse_code(&format!("{class}00"))?;
+                    Ok((base, base + 99))
+                } else if let Some((min, max)) = part.split_once('-') {
+                    let (min, max) = (parse_code(min)?, parse_code(max)?);
+                    if min > max {
+                        return Err(format!("Invalid status range: {part}"));
+                    }
+                    Ok((min, max))
+                } else {
+                    let code = parse_code(part)?;
+                    Ok((code, code))
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if ranges.is_empty() {
+            return Err("Accepted statuses must not be empty".to_string());
+        }
+
+        Ok(AcceptStatuses(ranges))
+    }
+}
+
+impl TryFrom<String> for AcceptStatuses {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<AcceptStatuses> for String {
+    fn from(statuses: AcceptStatuses) -> Self {
+        statuses
+            .0
+            .iter()
+            .map(|(min, max)| match (min, max) {
+                (min, max) if min == max => min.to_string(),
+                (min, max) if min % 100 == 0 && *max == min + 99 => format!("{}xx", min / 100),
+                (min, max) => format!("{min}-{max}"),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// PNG compression level applied when re-encoding captures locally
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PngCompression {
+    /// Fast, minimal compression
+    Fast,
+    /// Balanced compression level
+    Default,
+    /// Maximum compression, slowest to encode
+    Best,
+}
+
+impl std::str::FromStr for PngCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fast" => Ok(PngCompression::Fast),
+            "default" => Ok(PngCompression::Default),
+            "best" => Ok(PngCompression::Best),
+            _ => Err(format!("Unknown PNG compression level: {s}")),
+        }
+    }
+}
+
+/// PNG scanline filter applied when re-encoding captures locally
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PngFilter {
+    NoFilter,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Pick the best filter per scanline
+    Adaptive,
+}
+
+impl std::str::FromStr for PngFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "nofilter" => Ok(PngFilter::NoFilter),
+            "sub" => Ok(PngFilter::Sub),
+            "up" => Ok(PngFilter::Up),
+            "avg" => Ok(PngFilter::Avg),
+            "paeth" => Ok(PngFilter::Paeth),
+            "adaptive" => Ok(PngFilter::Adaptive),
+            _ => Err(format!("Unknown PNG filter: {s}")),
+        }
+    }
+}
+
+/// Encoder settings for image output
+///
+/// PNG captures from Chrome are written as-is unless a compression level or
+/// filter is set, in which case they are re-encoded with those settings.
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{EncoderSettings, PngCompression};
+///
+/// // Small thumbnails
+/// let thumbnails = EncoderSettings {
+///     jpeg_quality: 60,
+///     ..Default::default()
+/// };
+///
+/// // Archival PNGs
+/// let archive = EncoderSettings {
+///     png_compression: Some(PngCompression::Best),
+///     ..Default::default()
+/// };
+/// # assert!(thumbnails.validate().is_ok() && archive.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EncoderSettings {
+    /// JPEG quality from 1 to 100 (default: 75)
+    pub jpeg_quality: u8,
+
+    /// Encode WebP losslessly (default: true)
+    pub webp_lossless: bool,
+
+    /// Lossy WebP quality from 1 to 100, used when `webp_lossless` is false (default: 80)
+    pub webp_quality: u8,
+
+    /// PNG compression level (default: keep Chrome's encoding)
+    pub png_compression: Option<PngCompression>,
+
+    /// PNG scanline filter (default: keep Chrome's encoding)
+    pub png_filter: Option<PngFilter>,
+}
+
+impl Default for EncoderSettings {
+    fn default() -> Self {
+        Self {
+            jpeg_quality: 75,
+            webp_lossless: true,
+            webp_quality: 80,
+            png_compression: None,
+            png_filter: None,
+        }
+    }
+}
+
+impl EncoderSettings {
+    /// Check that quality values are within range
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if !(1..=100).contains(&self.jpeg_quality) {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "jpeg_quality must be between 1 and 100, got {}",
+                self.jpeg_quality
+            )));
+        }
+        if !(1..=100).contains(&self.webp_quality) {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "webp_quality must be between 1 and 100, got {}",
+                self.webp_quality
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Whether PNG captures need to be re-encoded to apply these settings
+    pub fn reencodes_png(&self) -> bool {
+        self.png_compression.is_some() || self.png_filter.is_some()
+    }
+
+    /// Encoding applied to `format` output, `None` for PDF
+    ///
+    /// `local` is set when the image is encoded here rather than by Chrome. The
+    /// local WebP encoder is lossless only, so `webp_quality` is ignored then.
+    pub fn encoding(&self, format: &OutputFormat, local: bool) -> Option<ImageEncoding> {
+        match format {
+            OutputFormat::Jpeg => Some(ImageEncoding::Lossy(self.jpeg_quality)),
+            OutputFormat::Webp if !self.webp_lossless && !local => {
+                Some(ImageEncoding::Lossy(self.webp_quality))
+            }
+            OutputFormat::Png | OutputFormat::Webp => Some(ImageEncoding::Lossless),
+            OutputFormat::Pdf => None,
+        }
+    }
+}
+
+/// Encoding actually applied to a captured image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageEncoding {
+    /// PNG or lossless WebP
+    Lossless,
+    /// JPEG or lossy WebP at the given quality
+    Lossy(u8),
+}
+
+/// How full-page screenshots are captured
+///
+/// By default Chrome renders the whole page in a single capture, which is
+/// fast but truncates pages taller than its texture limit and leaves
+/// lazy-loaded images unrendered. Tiled mode first scrolls through the page
+/// to trigger lazy loading, then captures it one viewport at a time and
+/// stitches the tiles locally.
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::FullPageOptions;
+///
+/// let options = FullPageOptions {
+///     tiled: true,
+///     max_height: 30_000,
+///     ..Default::default()
+/// };
+/// assert!(options.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FullPageOptions {
+    /// Scroll through the page and capture it in viewport-sized tiles (default: false)
+    pub tiled: bool,
+
+    /// Maximum height captured in tiled mode, in CSS pixels; taller pages are
+    /// cut off (default: 20000)
+    pub max_height: u32,
+
+    /// Pause after each scroll step so lazy content can load, in milliseconds (default: 200)
+    pub scroll_delay_ms: u64,
+
+    /// Hide fixed and sticky elements after the first tile so they are not
+    /// repeated down the page (default: true)
+    pub hide_sticky: bool,
+}
+
+impl Default for FullPageOptions {
+    fn default() -> Self {
+        Self {
+            tiled: false,
+            max_height: 20_000,
+            scroll_delay_ms: 200,
+            hide_sticky: true,
+        }
+    }
+}
+
+/// Largest `max_height` accepted for tiled full-page capture, in CSS pixels
+pub const MAX_FULL_PAGE_HEIGHT: u32 = 100_000;
+
+impl FullPageOptions {
+    /// Check that the height cap is usable
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if self.max_height == 0 || self.max_height > MAX_FULL_PAGE_HEIGHT {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "full_page.max_height must be between 1 and {MAX_FULL_PAGE_HEIGHT}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Paper sizes for PDF output
+///
+/// Named sizes follow the dimensions used by Chrome's print dialog.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum PaperSize {
+    Letter,
+    Legal,
+    Tabloid,
+    Ledger,
+    A0,
+    A1,
+    A2,
+    A3,
+    A4,
+    A5,
+    A6,
+    /// Custom size in inches
+    Custom {
+        width: f64,
+        height: f64,
+    },
+}
+
+impl PaperSize {
+    /// Paper width and height in inches (portrait orientation)
+    pub fn dimensions(&self) -> (f64, f64) {
+        match self {
+            PaperSize::Letter => (8.5, 11.0),
+            PaperSize::Legal => (8.5, 14.0),
+            PaperSize::Tabloid => (11.0, 17.0),
+            PaperSize::Ledger => (17.0, 11.0),
+            PaperSize::A0 => (33.1, 46.8),
+            PaperSize::A1 => (23.4, 33.1),
+            PaperSize::A2 => (16.54, 23.4),
+            PaperSize::A3 => (11.7, 16.54),
+            PaperSize::A4 => (8.27, 11.7),
+            PaperSize::A5 => (5.83, 8.27),
+            PaperSize::A6 => (4.13, 5.83),
+            PaperSize::Custom { width, height } => (*width, *height),
+        }
+    }
+}
+
+impl std::str::FromStr for PaperSize {
+    type Err = String;
+
+    /// Parse a named size (`a4`, `letter`, ...) or `WIDTHxHEIGHT` in inches
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let size = match s.to_lowercase().as_str() {
+            "letter" => PaperSize::Letter,
+            "legal" => PaperSize::Legal,
+            "tabloid" => PaperSize::Tabloid,
+            "ledger" => PaperSize::Ledger,
+            "a0" => PaperSize::A0,
+            "a1" => PaperSize::A1,
+            "a2" => PaperSize::A2,
+            "a3" => PaperSize::A3,
+            "a4" => PaperSize::A4,
+            "a5" => PaperSize::A5,
+            "a6" => PaperSize::A6,
+            custom => {
+                let (width, height) = custom
+                    .split_once('x')
+                    .ok_or_else(|| format!("Unknown paper size: {s}"))?;
+                let width: f64 = width
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid paper width: {width}"))?;
+                let height: f64 = height
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid paper height: {height}"))?;
+                if width <= 0.0 || height <= 0.0 {
+                    return Err(format!("Paper dimensions must be positive: {s}"));
+                }
+                PaperSize::Custom { width, height }
+            }
+        };
+
+        Ok(size)
+    }
+}
+
+/// Page margins for PDF output, in inches
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PdfMargins {
+    pub top: f64,
+    pub bottom: f64,
+    pub left: f64,
+    pub right: f64,
+}
+
+impl PdfMargins {
+    /// Same margin on every side
+    pub fn uniform(inches: f64) -> Self {
+        Self {
+            top: inches,
+            bottom: inches,
+            left: inches,
+            right: inches,
+        }
+    }
+}
+
+impl Default for PdfMargins {
+    fn default() -> Self {
+        // Chrome's default print margin of 1cm
+        Self::uniform(0.4)
+    }
+}
+
+/// Print-to-PDF settings
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{PaperSize, PdfMargins, PdfOptions};
+///
+/// let options = PdfOptions {
+///     paper_size: PaperSize::A4,
+///     landscape: true,
+///     margins: PdfMargins::uniform(0.5),
+///     footer_template: Some(
+///         "<div style='font-size:8px'><span class='pageNumber'></span></div>".to_string(),
+///     ),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PdfOptions {
+    /// Paper size (default: Letter)
+    pub paper_size: PaperSize,
+
+    /// Print in landscape orientation (default: false)
+    pub landscape: bool,
+
+    /// Print background colors and images (default: true)
+    pub print_background: bool,
+
+    /// Page margins in inches (default: 0.4 on every side)
+    pub margins: PdfMargins,
+
+    /// HTML template for the page header (default: none)
+    ///
+    /// Supports Chrome's `date`, `title`, `url`, `pageNumber` and `totalPages`
+    /// classes for injecting values.
+    pub header_template: Option<String>,
+
+    /// HTML template for the page footer (default: none)
+    pub footer_template: Option<String>,
+
+    /// Rendering scale between 0.1 and 2.0 (default: 1.0)
+    pub scale: f64,
+
+    /// Page ranges to print, e.g. `1-5, 8` (default: all pages)
+    pub page_ranges: Option<String>,
+
+    /// Prefer page size defined by CSS `@page` rules (default: false)
+    pub prefer_css_page_size: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            paper_size: PaperSize::Letter,
+            landscape: false,
+            print_background: true,
+            margins: PdfMargins::default(),
+            header_template: None,
+            footer_template: None,
+            scale: 1.0,
+            page_ranges: None,
+            prefer_css_page_size: false,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Whether a header or footer template is configured
+    pub fn has_header_footer(&self) -> bool {
+        self.header_template.is_some() || self.footer_template.is_some()
+    }
+
+    /// Check that the scale is within the range Chrome accepts
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if !(0.1..=2.0).contains(&self.scale) {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "pdf.scale must be between 0.1 and 2.0, got {}",
+                self.scale
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 /// Priority levels for screenshot requests
@@ -271,16 +983,142 @@ impl Default for Priority {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A cookie set in the browser before navigation
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Cookie domain; the cookie is scoped to the request URL when omitted
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Expiry in seconds since the Unix epoch; a session cookie when omitted
+    #[serde(default)]
+    pub expires: Option<f64>,
+}
+
+impl std::str::FromStr for Cookie {
+    type Err = String;
+
+    /// Parse `name=value` into a cookie scoped to the request URL
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Cookie must be given as name=value, got '{s}'"))?;
+
+        Ok(Cookie {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            domain: None,
+            path: None,
+            expires: None,
+        })
+    }
+}
+
+/// HTTP basic authentication credentials
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl std::str::FromStr for BasicAuth {
+    type Err = String;
+
+    /// Parse `user:password`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (username, password) = s
+            .split_once(':')
+            .ok_or_else(|| "Basic auth must be given as user:password".to_string())?;
+
+        Ok(BasicAuth {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+}
+
+/// A single screenshot job
+///
+/// Deserializable from JSON so it can be submitted to the HTTP API; any
+/// omitted field falls back to its default.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ScreenshotRequest {
     pub id: String,
     pub url: String,
     pub priority: Priority,
     pub custom_viewport: Option<Viewport>,
+    /// Named device preset providing viewport, touch, user agent and orientation
+    ///
+    /// `custom_viewport` and `user_agent` take precedence over the preset.
+    pub device: Option<String>,
+    /// Capture the page once per viewport from a single page load
+    ///
+    /// Produces one `ScreenshotResult` per entry, labelled with
+    /// `ViewportTarget::label`. Overrides `custom_viewport` and `device`.
+    pub viewports: Vec<ViewportTarget>,
     pub wait_time: Option<Duration>,
+    /// Conditions that must hold before capture, checked in order after navigation
+    pub wait_for: Vec<WaitCondition>,
+    /// Actions performed in order once the wait conditions are met
+    pub actions: Vec<PageAction>,
+    /// CSS selector of the element to capture; shorthand for `element`
     pub element_selector: Option<String>,
+    /// Elements to capture instead of the viewport, overriding `element_selector`
+    pub element: Option<ElementCapture>,
+    /// Rectangle of the document to capture, in CSS pixels
+    pub clip: Option<Clip>,
     pub full_page: bool,
+    /// Full-page capture settings for this request, overriding `Config::full_page`
+    pub full_page_options: Option<FullPageOptions>,
+    /// Opt out of deterministic rendering; `true` requires `Config::deterministic.enabled`
+    pub deterministic: Option<bool>,
+    /// Selectors of elements recorded in `ScreenshotMetadata::ignore_regions`,
+    /// added to `Config::diff.ignore_selectors`
+    pub ignore_selectors: Vec<String>,
     pub retry_count: usize,
+    /// Output format for this request, overriding `Config::output_format`
+    pub format: Option<OutputFormat>,
+    /// Encoder settings for this request, overriding `Config::encoder`
+    pub encoder: Option<EncoderSettings>,
+    /// Context isolation for this request, overriding `Config::isolation`
+    pub isolation: Option<BrowserIsolation>,
+    /// Named browser context shared by all requests with the same session
+    ///
+    /// Cookies and storage persist across requests in a session until it is
+    /// closed with `ScreenshotService::close_session`.
+    pub session: Option<String>,
+    /// Render the page to PDF with these settings instead of capturing an image
+    pub pdf: Option<PdfOptions>,
+    /// Extra HTTP headers sent with every request the page makes to the
+    /// origin of `url`; requests to other origins never carry them
+    pub headers: HashMap<String, String>,
+    /// Cookies set before navigation
+    pub cookies: Vec<Cookie>,
+    /// Credentials answered to HTTP basic auth challenges from the request's origin
+    pub basic_auth: Option<BasicAuth>,
+    /// User agent for this request, overriding `Config::user_agent`
+    pub user_agent: Option<String>,
+    /// Accepted main document statuses, overriding `Config::accept_statuses`
+    pub accept_statuses: Option<AcceptStatuses>,
+    /// Styles, scripts, hidden and redacted selectors, added to `Config::injection`
+    #[serde(flatten)]
+    pub injection: PageInjection,
+    /// Emulation options, overriding those in `Config::emulation`
+    #[serde(flatten)]
+    pub emulation: Emulation,
 }
 
 impl Default for ScreenshotRequest {
@@ -290,14 +1128,55 @@ impl Default for ScreenshotRequest {
             url: String::new(),
             priority: Priority::default(),
             custom_viewport: None,
+            device: None,
+            viewports: Vec::new(),
             wait_time: None,
+            wait_for: Vec::new(),
+            actions: Vec::new(),
             element_selector: None,
+            element: None,
+            clip: None,
             full_page: false,
+            full_page_options: None,
+            deterministic: None,
+            ignore_selectors: Vec::new(),
             retry_count: 0,
+            format: None,
+            encoder: None,
+            isolation: None,
+            session: None,
+            pdf: None,
+            headers: HashMap::new(),
+            cookies: Vec::new(),
+            basic_auth: None,
+            user_agent: None,
+            accept_statuses: None,
+            injection: PageInjection::default(),
+            emulation: Emulation::default(),
         }
     }
 }
 
+impl ScreenshotRequest {
+    /// Output format for this request, falling back to the service default
+    ///
+    /// A request carrying PDF settings without an explicit format is printed to PDF.
+    pub fn output_format(&self, default: &OutputFormat) -> OutputFormat {
+        match (&self.format, &self.pdf) {
+            (Some(format), _) => format.clone(),
+            (None, Some(_)) => OutputFormat::Pdf,
+            (None, None) => default.clone(),
+        }
+    }
+
+    /// Element capture settings, from `element` or else `element_selector`
+    pub fn element_capture(&self) -> Option<ElementCapture> {
+        self.element
+            .clone()
+            .or_else(|| self.element_selector.clone().map(ElementCapture::new))
+    }
+}
+
 #[derive(Debug)]
 pub struct ScreenshotResult {
     pub request_id: String,
@@ -314,11 +1193,56 @@ pub struct ScreenshotResult {
 #[derive(Debug, Clone)]
 pub struct ScreenshotMetadata {
     pub viewport: Viewport,
+    /// Label of the viewport for multi-viewport requests
+    pub viewport_label: Option<String>,
+    /// Position of the captured element among the matches, when every match
+    /// is captured separately
+    pub element_index: Option<usize>,
     pub page_title: Option<String>,
     pub final_url: Option<String>,
+    /// HTTP status of the main document response
     pub response_status: Option<u16>,
+    /// Redirects followed before the final response, in order
+    pub redirect_chain: Vec<Redirect>,
+    /// Headers of the main document response
+    pub response_headers: HashMap<String, String>,
+    /// MIME type of the main document response
+    pub mime_type: Option<String>,
     pub file_size: usize,
+    /// Encoding of the image, which may differ from the requested settings;
+    /// `None` for PDF output and failed captures
+    pub encoding: Option<ImageEncoding>,
     pub browser_instance_id: usize,
+    /// Number of requests aborted by ad/tracker blocking during the page load
+    pub blocked_requests: usize,
+    /// Boxes of the elements matching the diff ignore selectors, in image
+    /// pixels, skipped when the capture is compared with a baseline
+    pub ignore_regions: Vec<Clip>,
+}
+
+impl ScreenshotMetadata {
+    /// Label distinguishing this result from others of the same request
+    ///
+    /// Combines the viewport label with the 1-based element number, e.g.
+    /// `iPhone 15 element-2`.
+    pub fn label(&self) -> Option<String> {
+        let element = self
+            .element_index
+            .map(|index| format!("element-{}", index + 1));
+
+        match (&self.viewport_label, element) {
+            (Some(viewport), Some(element)) => Some(format!("{viewport} {element}")),
+            (Some(viewport), None) => Some(viewport.clone()),
+            (None, element) => element,
+        }
+    }
+}
+
+/// A redirect response received while loading the main document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Redirect {
+    pub url: String,
+    pub status: u16,
 }
 
 #[derive(Debug, Clone)]
@@ -452,6 +1376,10 @@ pub fn get_chrome_args_with_instance_id(
         args.push(format!("--user-agent={user_agent}"));
     }
 
+    if config.deterministic.enabled {
+        args.extend(config.deterministic.chrome_args());
+    }
+
     args
 }
 
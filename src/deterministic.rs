@@ -0,0 +1,177 @@
+//! Deterministic rendering for reproducible screenshots
+//!
+//! Visual regression needs the same page to produce the same pixels on
+//! every run. Deterministic mode removes the usual sources of noise:
+//!
+//! - the clock starts at a fixed time and runs on Chrome's virtual time, so
+//!   `Date.now()` and timers no longer depend on the wall clock
+//! - `Math.random` is replaced with a seeded generator before any page script
+//! - CSS animations are finished (or cancelled if infinite), transitions
+//!   disabled, text carets hidden and media paused at their first frame
+//! - Chrome renders text without hinting, subpixel positioning or LCD
+//!   antialiasing, in sRGB, and optionally only with locally installed fonts
+
+use crate::ScreenshotError;
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetVirtualTimePolicyParams, VirtualTimePolicy,
+};
+use chromiumoxide::cdp::browser_protocol::network::TimeSinceEpoch;
+use chromiumoxide::cdp::browser_protocol::page::AddScriptToEvaluateOnNewDocumentParams;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+/// 2024-01-01T00:00:00Z in milliseconds since the Unix epoch
+const DEFAULT_START_TIME: u64 = 1_704_067_200_000;
+
+/// Stops animations, transitions, carets and media
+const FREEZE_SCRIPT: &str = r#"(() => {
+    if (!document.getElementById('screenshot-deterministic')) {
+        const style = document.createElement('style');
+        style.id = 'screenshot-deterministic';
+        style.textContent = `*, *::before, *::after {
+            transition: none !important;
+            caret-color: transparent !important;
+            scroll-behavior: auto !important;
+        }`;
+        (document.head || document.documentElement).appendChild(style);
+    }
+    for (const animation of document.getAnimations()) {
+        try {
+            if (animation.effect && animation.effect.getComputedTiming().endTime === Infinity) {
+                animation.cancel();
+            } else {
+                animation.finish();
+            }
+        } catch (e) {
+            animation.cancel();
+        }
+    }
+    for (const media of document.querySelectorAll('video, audio')) {
+        media.pause();
+        media.currentTime = 0;
+    }
+    return true;
+})()"#;
+
+/// Deterministic rendering settings
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::DeterministicOptions;
+///
+/// let options: DeterministicOptions = serde_json::from_str(r#"{
+///     "enabled": true,
+///     "seed": 42
+/// }"#).unwrap();
+/// assert!(options.local_fonts_only);
+/// assert!(options.chrome_args().contains(&"--font-render-hinting=none".to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeterministicOptions {
+    /// Render every request deterministically (requests can opt out)
+    pub enabled: bool,
+    /// Seed for `Math.random`
+    pub seed: u32,
+    /// Time the page clock starts at, in milliseconds since the Unix epoch
+    pub start_time: u64,
+    /// Ignore web fonts and render only with locally installed fonts
+    pub local_fonts_only: bool,
+}
+
+impl Default for DeterministicOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 1,
+            start_time: DEFAULT_START_TIME,
+            local_fonts_only: true,
+        }
+    }
+}
+
+impl DeterministicOptions {
+    /// Chrome flags for stable text rendering and paused media
+    ///
+    /// Only added at browser launch when `enabled` is set in the config.
+    pub fn chrome_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--font-render-hinting=none".to_string(),
+            "--disable-font-subpixel-positioning".to_string(),
+            "--disable-lcd-text".to_string(),
+            "--force-color-profile=srgb".to_string(),
+            "--disable-skia-runtime-opts".to_string(),
+            "--disable-partial-raster".to_string(),
+            "--hide-scrollbars".to_string(),
+            "--autoplay-policy=user-gesture-required".to_string(),
+        ];
+
+        if self.local_fonts_only {
+            args.push("--disable-remote-fonts".to_string());
+        }
+
+        args
+    }
+
+    /// Check a request's `deterministic` override against these settings
+    ///
+    /// Requests can only opt in when the config does: the Chrome flags are
+    /// fixed at launch, so a request on a non-deterministic pool would get
+    /// frozen scripts but unstable text rendering.
+    pub fn check_request(&self, requested: Option<bool>) -> Result<(), ScreenshotError> {
+        if requested == Some(true) && !self.enabled {
+            return Err(ScreenshotError::ConfigurationError(
+                "deterministic requests need deterministic.enabled in the config".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Seeded `Math.random` replacement, run before any page script
+    pub fn random_script(&self) -> String {
+        // mulberry32
+        format!(
+            "(() => {{ \
+             let state = {seed} >>> 0; \
+             Math.random = function random() {{ \
+             state = (state + 0x6D2B79F5) >>> 0; \
+             let t = state; \
+             t = Math.imul(t ^ (t >>> 15), t | 1); \
+             t ^= t + Math.imul(t ^ (t >>> 7), t | 61); \
+             return ((t ^ (t >>> 14)) >>> 0) / 4294967296; \
+             }}; }})();",
+            seed = self.seed
+        )
+    }
+
+    /// Seed `Math.random` and start the virtual clock, before navigation
+    pub async fn prepare(&self, page: &Page) -> Result<(), ScreenshotError> {
+        page.evaluate_on_new_document(AddScriptToEvaluateOnNewDocumentParams::new(
+            self.random_script(),
+        ))
+        .await
+        .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+        // Virtual time fast-forwards through idle periods, so timers still fire
+        let mut params = SetVirtualTimePolicyParams::new(VirtualTimePolicy::Advance);
+        params.initial_virtual_time = Some(TimeSinceEpoch::new(self.start_time as f64 / 1000.0));
+        page.execute(params)
+            .await
+            .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Finish animations, disable transitions, hide carets and pause media
+    ///
+    /// Safe to run repeatedly, e.g. before each viewport of a request.
+    pub async fn freeze(&self, page: &Page) -> Result<(), ScreenshotError> {
+        page.evaluate_expression(FREEZE_SCRIPT)
+            .await
+            .map_err(|e| ScreenshotError::PageError(format!("Failed to freeze page: {e}")))?;
+
+        Ok(())
+    }
+}
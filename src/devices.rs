@@ -0,0 +1,239 @@
+//! Named device emulation presets
+//!
+//! A `DeviceCatalog` maps device names such as `"iPhone 15"` or `"Pixel 7"`
+//! to the viewport, pixel ratio, touch support, user agent and orientation
+//! used to emulate them. The built-in catalogue can be extended or overridden
+//! with a JSON file (see `Config::devices_file`).
+
+use crate::{ScreenshotError, Viewport};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const IOS_USER_AGENT: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+const IPADOS_USER_AGENT: &str = "Mozilla/5.0 (iPad; CPU OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1";
+
+/// Screen orientation of an emulated device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    #[default]
+    Portrait,
+    Landscape,
+}
+
+/// Emulation settings for a named device
+///
+/// `width` and `height` are the CSS pixel dimensions in the device's
+/// natural orientation; a landscape device is emulated with them swapped.
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{DeviceCatalog, Orientation};
+///
+/// let catalog = DeviceCatalog::builtin();
+/// let iphone = catalog.get("iphone 15").unwrap();
+/// assert_eq!(iphone.viewport().width, 393);
+///
+/// let ipad = catalog.get("iPad Pro 11 landscape").unwrap();
+/// assert_eq!(ipad.orientation, Orientation::Landscape);
+/// assert!(ipad.viewport().width > ipad.viewport().height);
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Device {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_scale_factor")]
+    pub device_scale_factor: f64,
+    /// Emulate a mobile browser (meta viewport, overlay scrollbars)
+    #[serde(default)]
+    pub mobile: bool,
+    /// Emulate a touch screen
+    #[serde(default)]
+    pub touch: bool,
+    /// User agent sent by the device; the browser default when omitted
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub orientation: Orientation,
+}
+
+fn default_scale_factor() -> f64 {
+    1.0
+}
+
+impl Device {
+    fn new(name: &str, width: u32, height: u32, device_scale_factor: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            device_scale_factor,
+            mobile: false,
+            touch: false,
+            user_agent: None,
+            orientation: Orientation::Portrait,
+        }
+    }
+
+    fn handheld(mut self, user_agent: &str) -> Self {
+        self.mobile = true;
+        self.touch = true;
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    fn android(self, model: &str) -> Self {
+        self.handheld(&format!(
+            "Mozilla/5.0 (Linux; Android 14; {model}) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36"
+        ))
+    }
+
+    /// The same device rotated to the other orientation
+    pub fn rotated(&self) -> Self {
+        let mut device = self.clone();
+        device.orientation = match self.orientation {
+            Orientation::Portrait => Orientation::Landscape,
+            Orientation::Landscape => Orientation::Portrait,
+        };
+        device
+    }
+
+    /// Viewport for this device in its current orientation
+    pub fn viewport(&self) -> Viewport {
+        let (width, height) = match self.orientation {
+            Orientation::Portrait => (self.width, self.height),
+            Orientation::Landscape => (self.height, self.width),
+        };
+
+        Viewport {
+            width,
+            height,
+            device_scale_factor: self.device_scale_factor,
+            mobile: self.mobile,
+        }
+    }
+}
+
+/// Catalogue of named devices, looked up case-insensitively
+#[derive(Debug, Clone, Default)]
+pub struct DeviceCatalog {
+    devices: BTreeMap<String, Device>,
+}
+
+impl DeviceCatalog {
+    /// Catalogue with the built-in phones, tablets and desktop sizes
+    pub fn builtin() -> Self {
+        let mut catalog = Self::default();
+
+        for device in [
+            Device::new("iPhone SE", 375, 667, 2.0).handheld(IOS_USER_AGENT),
+            Device::new("iPhone 14", 390, 844, 3.0).handheld(IOS_USER_AGENT),
+            Device::new("iPhone 15", 393, 852, 3.0).handheld(IOS_USER_AGENT),
+            Device::new("iPhone 15 Pro", 393, 852, 3.0).handheld(IOS_USER_AGENT),
+            Device::new("iPhone 15 Pro Max", 430, 932, 3.0).handheld(IOS_USER_AGENT),
+            Device::new("Pixel 7", 412, 915, 2.625).android("Pixel 7"),
+            Device::new("Pixel 8", 412, 915, 2.625).android("Pixel 8"),
+            Device::new("Galaxy S23", 360, 780, 3.0).android("SM-S911B"),
+            Device::new("iPad Mini", 768, 1024, 2.0).handheld(IPADOS_USER_AGENT),
+            Device::new("iPad Air", 820, 1180, 2.0).handheld(IPADOS_USER_AGENT),
+            Device::new("iPad Pro 11", 834, 1194, 2.0).handheld(IPADOS_USER_AGENT),
+            Device::new("iPad Pro 12.9", 1024, 1366, 2.0).handheld(IPADOS_USER_AGENT),
+            Device::new("Laptop", 1366, 768, 1.0),
+            Device::new("MacBook Pro 14", 1512, 982, 2.0),
+            Device::new("Desktop 1080p", 1920, 1080, 1.0),
+            Device::new("Desktop 1440p", 2560, 1440, 1.0),
+            Device::new("Desktop 4K", 3840, 2160, 1.0),
+        ] {
+            catalog.insert(device);
+        }
+
+        catalog
+    }
+
+    /// Built-in catalogue extended with the devices in a JSON file
+    pub fn with_file(path: &Path) -> Result<Self, ScreenshotError> {
+        let mut catalog = Self::builtin();
+        catalog.extend_from_file(path)?;
+        Ok(catalog)
+    }
+
+    /// Add devices from a JSON array of `Device` objects
+    ///
+    /// Devices whose name matches an existing entry replace it.
+    pub fn extend_from_file(&mut self, path: &Path) -> Result<(), ScreenshotError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ScreenshotError::ConfigurationError(format!(
+                "Failed to read devices file {}: {e}",
+                path.display()
+            ))
+        })?;
+
+        self.extend_from_json(&content).map_err(|e| {
+            ScreenshotError::ConfigurationError(format!(
+                "Invalid devices file {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Add devices from a JSON array of `Device` objects
+    pub fn extend_from_json(&mut self, json: &str) -> Result<(), ScreenshotError> {
+        let devices: Vec<Device> = serde_json::from_str(json)?;
+        for device in devices {
+            if device.width == 0 || device.height == 0 || device.device_scale_factor <= 0.0 {
+                return Err(ScreenshotError::ConfigurationError(format!(
+                    "Device '{}' must have a non-zero size and scale factor",
+                    device.name
+                )));
+            }
+            self.insert(device);
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, device: Device) {
+        self.devices.insert(device.name.to_lowercase(), device);
+    }
+
+    /// Look up a device by name
+    ///
+    /// A `" landscape"` or `" portrait"` suffix selects that orientation.
+    pub fn get(&self, name: &str) -> Option<Device> {
+        let key = name.trim().to_lowercase();
+        if let Some(device) = self.devices.get(&key) {
+            return Some(device.clone());
+        }
+
+        let (base, orientation) = if let Some(base) = key.strip_suffix(" landscape") {
+            (base, Orientation::Landscape)
+        } else if let Some(base) = key.strip_suffix(" portrait") {
+            (base, Orientation::Portrait)
+        } else {
+            return None;
+        };
+
+        self.devices.get(base.trim_end()).map(|device| {
+            if device.orientation == orientation {
+                device.clone()
+            } else {
+                device.rotated()
+            }
+        })
+    }
+
+    /// Devices in the catalogue, ordered by name
+    pub fn devices(&self) -> impl Iterator<Item = &Device> {
+        self.devices.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+}
@@ -0,0 +1,489 @@
+//! Visual diffs against baseline images
+//!
+//! Pixels are compared by their perceived color difference in YIQ space, as
+//! in pixelmatch, so small rendering noise stays below the threshold.
+//! Pixels that differ only because of anti-aliasing along an edge are
+//! detected and tolerated by default. Ignore regions are given as image-pixel
+//! rectangles, or as selectors whose element boxes are measured at capture
+//! time and stored in `ScreenshotMetadata::ignore_regions`.
+
+use crate::{encode_image, Clip, EncoderSettings, OutputFormat, ScreenshotError, ScreenshotResult};
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Largest possible YIQ color difference between two pixels
+const MAX_YIQ_DELTA: f64 = 35215.0;
+
+/// Highlight for pixels that differ
+const DIFF_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// Highlight for pixels that differ only by anti-aliasing
+const ANTI_ALIASED_COLOR: Rgba<u8> = Rgba([255, 255, 0, 255]);
+/// Opacity of the unchanged baseline drawn under the highlights
+const BACKGROUND_ALPHA: f64 = 0.1;
+
+/// Extensions of the files compared in directory diffs
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// Visual diff settings
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::DiffOptions;
+///
+/// let options: DiffOptions = serde_json::from_str(r#"{
+///     "threshold": 0.2,
+///     "ignore_regions": [{"x": 0, "y": 0, "width": 1920, "height": 80}],
+///     "ignore_selectors": [".ad-slot"],
+///     "fail_threshold": 0.5
+/// }"#).unwrap();
+/// assert!(options.validate().is_ok());
+/// assert!(!options.include_anti_aliasing);
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// Color difference a pixel may have before it counts as changed, from
+    /// 0 (exact) to 1 (anything goes)
+    pub threshold: f64,
+    /// Count anti-aliased pixels as changed instead of tolerating them
+    pub include_anti_aliasing: bool,
+    /// Rectangles skipped by the comparison, in image pixels
+    pub ignore_regions: Vec<Clip>,
+    /// CSS selectors of elements skipped by the comparison, measured at capture
+    pub ignore_selectors: Vec<String>,
+    /// Percentage of changed pixels above which a comparison fails
+    pub fail_threshold: f64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            include_anti_aliasing: false,
+            ignore_regions: Vec::new(),
+            ignore_selectors: Vec::new(),
+            fail_threshold: 0.0,
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "Diff threshold must be between 0 and 1, got {}",
+                self.threshold
+            )));
+        }
+        if !(0.0..=100.0).contains(&self.fail_threshold) {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "Diff fail threshold must be a percentage between 0 and 100, got {}",
+                self.fail_threshold
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn ignores(&self, x: u32, y: u32) -> bool {
+        let (x, y) = (f64::from(x), f64::from(y));
+        self.ignore_regions.iter().any(|region| {
+            x >= region.x
+                && x < region.x + region.width
+                && y >= region.y
+                && y < region.y + region.height
+        })
+    }
+}
+
+/// Outcome of comparing an image with its baseline
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageDiff {
+    /// Size of the compared area, covering both images
+    pub width: u32,
+    pub height: u32,
+    /// The images differ in size; pixels outside either image count as changed
+    pub size_changed: bool,
+    pub different_pixels: u64,
+    /// Anti-aliased pixels tolerated by the comparison
+    pub anti_aliased_pixels: u64,
+    pub ignored_pixels: u64,
+    /// Changed pixels as a percentage of the pixels that were compared
+    pub mismatch_percentage: f64,
+    /// Faded baseline with changed pixels in red and anti-aliasing in yellow
+    #[serde(skip)]
+    pub diff_image: RgbaImage,
+}
+
+impl ImageDiff {
+    /// Whether the mismatch is above the failure threshold percentage
+    pub fn exceeds(&self, fail_threshold: f64) -> bool {
+        self.mismatch_percentage > fail_threshold
+    }
+
+    /// The highlighted diff image as PNG
+    pub fn diff_png(&self) -> Result<Vec<u8>, ScreenshotError> {
+        encode_image(
+            &DynamicImage::ImageRgba8(self.diff_image.clone()),
+            &OutputFormat::Png,
+            &EncoderSettings::default(),
+        )
+    }
+}
+
+/// Compare an image with its baseline
+///
+/// # Examples
+///
+/// ```rust
+/// use image::{DynamicImage, Rgba, RgbaImage};
+/// use screenshot_tool::{diff_images, DiffOptions};
+///
+/// let baseline = RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255]));
+/// let mut current = baseline.clone();
+/// current.put_pixel(5, 5, Rgba([0, 0, 0, 255]));
+///
+/// let diff = diff_images(
+///     &DynamicImage::ImageRgba8(baseline),
+///     &DynamicImage::ImageRgba8(current),
+///     &DiffOptions::default(),
+/// );
+/// assert_eq!(diff.different_pixels, 1);
+/// assert_eq!(diff.mismatch_percentage, 1.0);
+/// ```
+pub fn diff_images(
+    baseline: &DynamicImage,
+    current: &DynamicImage,
+    options: &DiffOptions,
+) -> ImageDiff {
+    let baseline = baseline.to_rgba8();
+    let current = current.to_rgba8();
+
+    let width = baseline.width().max(current.width());
+    let height = baseline.height().max(current.height());
+    let max_delta = MAX_YIQ_DELTA * options.threshold * options.threshold;
+
+    let mut diff_image = RgbaImage::new(width, height);
+    let mut different_pixels = 0;
+    let mut anti_aliased_pixels = 0;
+    let mut ignored_pixels = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let (before, after) = match (pixel(&baseline, x, y), pixel(&current, x, y)) {
+                (Some(before), Some(after)) => (before, after),
+                // Outside one of the images
+                (before, after) => {
+                    if options.ignores(x, y) {
+                        ignored_pixels += 1;
+                        diff_image.put_pixel(
+                            x,
+                            y,
+                            faded(before.or(after).unwrap_or(Rgba([255, 255, 255, 0]))),
+                        );
+                    } else {
+                        different_pixels += 1;
+                        diff_image.put_pixel(x, y, DIFF_COLOR);
+                    }
+                    continue;
+                }
+            };
+
+            if options.ignores(x, y) {
+                ignored_pixels += 1;
+                diff_image.put_pixel(x, y, faded(before));
+                continue;
+            }
+
+            if color_delta(before, after, false).abs() <= max_delta {
+                diff_image.put_pixel(x, y, faded(before));
+            } else if !options.include_anti_aliasing
+                && (anti_aliased(&baseline, &current, x, y)
+                    || anti_aliased(&current, &baseline, x, y))
+            {
+                anti_aliased_pixels += 1;
+                diff_image.put_pixel(x, y, ANTI_ALIASED_COLOR);
+            } else {
+                different_pixels += 1;
+                diff_image.put_pixel(x, y, DIFF_COLOR);
+            }
+        }
+    }
+
+    let compared = u64::from(width) * u64::from(height) - ignored_pixels;
+    let mismatch_percentage = if compared == 0 {
+        0.0
+    } else {
+        different_pixels as f64 * 100.0 / compared as f64
+    };
+
+    ImageDiff {
+        width,
+        height,
+        size_changed: baseline.dimensions() != current.dimensions(),
+        different_pixels,
+        anti_aliased_pixels,
+        ignored_pixels,
+        mismatch_percentage,
+        diff_image,
+    }
+}
+
+/// Compare a captured result with its baseline, also skipping the element
+/// boxes recorded for `ignore_selectors` at capture time
+pub fn diff_result(
+    result: &ScreenshotResult,
+    baseline: &DynamicImage,
+    options: &DiffOptions,
+) -> Result<ImageDiff, ScreenshotError> {
+    if result.format == OutputFormat::Pdf {
+        return Err(ScreenshotError::ConfigurationError(
+            "PDF output cannot be compared visually".to_string(),
+        ));
+    }
+
+    let current = image::load_from_memory(&result.data)
+        .map_err(|e| ScreenshotError::CaptureFailed(format!("Cannot decode capture: {e}")))?;
+    let mut options = options.clone();
+    options
+        .ignore_regions
+        .extend(result.metadata.ignore_regions.iter().copied());
+
+    Ok(diff_images(baseline, &current, &options))
+}
+
+/// Load an image file for comparison
+pub fn load_image(path: &Path) -> Result<DynamicImage, ScreenshotError> {
+    image::open(path)
+        .map_err(|e| ScreenshotError::IoError(format!("Cannot read {}: {e}", path.display())))
+}
+
+/// Compare two image files
+pub fn diff_files(
+    baseline: &Path,
+    current: &Path,
+    options: &DiffOptions,
+) -> Result<ImageDiff, ScreenshotError> {
+    Ok(diff_images(
+        &load_image(baseline)?,
+        &load_image(current)?,
+        options,
+    ))
+}
+
+/// How an image compares with its baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    /// Within the failure threshold
+    Unchanged,
+    /// Above the failure threshold
+    Changed,
+    /// Only in the current images
+    New,
+    /// Only in the baseline images
+    Missing,
+}
+
+/// One image of a directory diff
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffEntry {
+    /// Path relative to the compared directories
+    pub path: PathBuf,
+    pub status: DiffStatus,
+    /// Comparison details, for images present on both sides
+    pub diff: Option<ImageDiff>,
+}
+
+/// Compare every image in `current` with the image at the same relative
+/// path in `baseline`, recursing into subdirectories
+///
+/// Entries are sorted by path. This reads and compares images synchronously;
+/// run it on a blocking thread from async code.
+pub fn diff_directories(
+    baseline: &Path,
+    current: &Path,
+    options: &DiffOptions,
+) -> Result<Vec<DiffEntry>, ScreenshotError> {
+    let baseline_files = image_files(baseline)?;
+    let current_files = image_files(current)?;
+
+    baseline_files
+        .union(&current_files)
+        .map(|path| {
+            let (status, diff) = match (baseline_files.contains(path), current_files.contains(path))
+            {
+                (true, true) => {
+                    let diff = diff_files(&baseline.join(path), &current.join(path), options)?;
+                    let status = if diff.exceeds(options.fail_threshold) {
+                        DiffStatus::Changed
+                    } else {
+                        DiffStatus::Unchanged
+                    };
+                    (status, Some(diff))
+                }
+                (true, false) => (DiffStatus::Missing, None),
+                _ => (DiffStatus::New, None),
+            };
+
+            Ok(DiffEntry {
+                path: path.clone(),
+                status,
+                diff,
+            })
+        })
+        .collect()
+}
+
+/// Image files under `dir`, relative to it
+fn image_files(dir: &Path) -> Result<BTreeSet<PathBuf>, ScreenshotError> {
+    let mut files = BTreeSet::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(next) = pending.pop() {
+        for entry in std::fs::read_dir(&next)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_image(&path) {
+                if let Ok(relative) = path.strip_prefix(dir) {
+                    files.insert(relative.to_path_buf());
+                }
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Whether the path has one of the compared image extensions
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn pixel(image: &RgbaImage, x: u32, y: u32) -> Option<Rgba<u8>> {
+    (x < image.width() && y < image.height()).then(|| *image.get_pixel(x, y))
+}
+
+/// A pixel's brightness blended over white, for the diff background
+fn faded(pixel: Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b] = blend_white(pixel);
+    let value = 255.0 + (rgb_to_y(r, g, b) - 255.0) * BACKGROUND_ALPHA;
+    let value = value.round().clamp(0.0, 255.0) as u8;
+    Rgba([value, value, value, 255])
+}
+
+fn blend_white(pixel: Rgba<u8>) -> [f64; 3] {
+    let alpha = f64::from(pixel[3]) / 255.0;
+    let blend = |channel: u8| 255.0 + (f64::from(channel) - 255.0) * alpha;
+    [blend(pixel[0]), blend(pixel[1]), blend(pixel[2])]
+}
+
+fn rgb_to_y(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.298_895_31 + g * 0.586_622_47 + b * 0.114_482_23
+}
+
+fn rgb_to_i(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.595_977_99 - g * 0.274_176_10 - b * 0.321_801_89
+}
+
+fn rgb_to_q(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.211_470_17 - g * 0.522_617_11 + b * 0.311_146_94
+}
+
+/// Perceived color difference, negative when `b` is brighter than `a`
+fn color_delta(a: Rgba<u8>, b: Rgba<u8>, brightness_only: bool) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+
+    let [r1, g1, b1] = blend_white(a);
+    let [r2, g2, b2] = blend_white(b);
+    let y1 = rgb_to_y(r1, g1, b1);
+    let y2 = rgb_to_y(r2, g2, b2);
+    let y = y1 - y2;
+
+    if brightness_only {
+        return y;
+    }
+
+    let i = rgb_to_i(r1, g1, b1) - rgb_to_i(r2, g2, b2);
+    let q = rgb_to_q(r1, g1, b1) - rgb_to_q(r2, g2, b2);
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+
+    if y1 > y2 {
+        -delta
+    } else {
+        delta
+    }
+}
+
+/// Whether the pixel at `x`,`y` of `image` looks like part of an anti-aliased
+/// edge: it sits between a darker and a brighter neighbour, and one of those
+/// lies inside a flat area in both images
+fn anti_aliased(image: &RgbaImage, other: &RgbaImage, x: u32, y: u32) -> bool {
+    let (width, height) = image.dimensions();
+    let center = *image.get_pixel(x, y);
+
+    let mut zeroes = u32::from(x == 0 || x == width - 1 || y == 0 || y == height - 1);
+    let (mut min, mut max) = (0.0, 0.0);
+    let (mut min_at, mut max_at) = ((0, 0), (0, 0));
+
+    for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+        for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+            if (nx, ny) == (x, y) {
+                continue;
+            }
+
+            let delta = color_delta(center, *image.get_pixel(nx, ny), true);
+            if delta == 0.0 {
+                zeroes += 1;
+                if zeroes > 2 {
+                    return false;
+                }
+            } else if delta < min {
+                min = delta;
+                min_at = (nx, ny);
+            } else if delta > max {
+                max = delta;
+                max_at = (nx, ny);
+            }
+        }
+    }
+
+    // No darker or no brighter neighbour, so not an edge between two colors
+    if min == 0.0 || max == 0.0 {
+        return false;
+    }
+
+    let flat = |(nx, ny)| has_many_siblings(image, nx, ny) && has_many_siblings(other, nx, ny);
+    flat(min_at) || flat(max_at)
+}
+
+/// Whether at least three neighbours have exactly the pixel's color
+fn has_many_siblings(image: &RgbaImage, x: u32, y: u32) -> bool {
+    let (width, height) = image.dimensions();
+    if x >= width || y >= height {
+        return false;
+    }
+    let center = image.get_pixel(x, y);
+
+    let mut zeroes = u32::from(x == 0 || x == width - 1 || y == 0 || y == height - 1);
+    for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+        for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+            if (nx, ny) != (x, y) && image.get_pixel(nx, ny) == center {
+                zeroes += 1;
+                if zeroes > 2 {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
@@ -0,0 +1,354 @@
+//! Clip rectangles and element capture
+//!
+//! A request can capture an explicit rectangle of the page, or the elements
+//! matching a CSS or XPath selector. Selectors reach into shadow roots and
+//! same-origin iframes with `>>>`: `iframe#checkout >>> button.pay` matches
+//! `button.pay` inside the iframe's document, and `my-app >>> .card` matches
+//! `.card` inside the shadow root of `my-app`.
+
+use crate::ScreenshotError;
+use chromiumoxide::cdp::browser_protocol::page::Viewport as CaptureViewport;
+use chromiumoxide::Page;
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Separator between the hops of a selector chain
+pub const SELECTOR_HOP: &str = ">>>";
+
+/// Gap between elements on a contact sheet, in image pixels
+const CONTACT_SHEET_GAP: u32 = 16;
+
+/// A rectangle in CSS pixels, relative to the top-left of the document
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::Clip;
+///
+/// let clip: Clip = "0,100,800,600".parse().unwrap();
+/// // Padding stops at the left edge of the document
+/// assert_eq!(clip.padded(10.0), Clip { x: 0.0, y: 90.0, width: 810.0, height: 620.0 });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Clip {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Clip {
+    /// Grow the rectangle by `padding` on every side, without crossing the
+    /// top or left edge of the document
+    pub fn padded(&self, padding: f64) -> Clip {
+        let x = (self.x - padding).max(0.0);
+        let y = (self.y - padding).max(0.0);
+
+        Clip {
+            x,
+            y,
+            width: self.x + self.width + padding - x,
+            height: self.y + self.height + padding - y,
+        }
+    }
+
+    /// Check that the rectangle has an area and lies within the document
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if self.x < 0.0 || self.y < 0.0 || self.width <= 0.0 || self.height <= 0.0 {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "Clip {},{},{},{} must have a non-negative origin and a positive size",
+                self.x, self.y, self.width, self.height
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Clip> for CaptureViewport {
+    fn from(clip: Clip) -> Self {
+        CaptureViewport {
+            x: clip.x,
+            y: clip.y,
+            width: clip.width,
+            height: clip.height,
+            scale: 1.0,
+        }
+    }
+}
+
+impl std::str::FromStr for Clip {
+    type Err = String;
+
+    /// Parse `x,y,width,height`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid clip '{s}': {e}"))?;
+
+        match values[..] {
+            [x, y, width, height] => Ok(Clip {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!("Clip must be given as x,y,width,height, got '{s}'")),
+        }
+    }
+}
+
+/// Selector language used by `ElementCapture::selector`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectorType {
+    #[default]
+    Css,
+    Xpath,
+}
+
+/// Which of the matching elements are captured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementMatch {
+    /// The first match only
+    #[default]
+    First,
+    /// Every match, as a separate result each
+    All,
+    /// Every match, laid out on a single contact sheet image
+    ContactSheet,
+}
+
+/// What to do when no element matches the selector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotFoundBehavior {
+    /// Fail the capture with `ScreenshotError::ElementNotFound`
+    #[default]
+    Error,
+    /// Produce no result for the request
+    Skip,
+    /// Capture the full page instead
+    FullPage,
+}
+
+impl std::str::FromStr for NotFoundBehavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "error" => Ok(NotFoundBehavior::Error),
+            "skip" => Ok(NotFoundBehavior::Skip),
+            "full-page" => Ok(NotFoundBehavior::FullPage),
+            _ => Err(format!("Unknown not-found behavior: {s}")),
+        }
+    }
+}
+
+/// Element capture settings
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{ElementCapture, ElementMatch, SelectorType};
+///
+/// let capture: ElementCapture = serde_json::from_str(r#"{
+///     "selector": "//article[contains(@class, 'post')]",
+///     "selector_type": "xpath",
+///     "matches": "all",
+///     "padding": 8
+/// }"#).unwrap();
+/// assert_eq!(capture.selector_type, SelectorType::Xpath);
+/// assert_eq!(capture.matches, ElementMatch::All);
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ElementCapture {
+    /// Selector, with `>>>` hops into shadow roots and same-origin iframes
+    pub selector: String,
+    #[serde(default)]
+    pub selector_type: SelectorType,
+    #[serde(default)]
+    pub matches: ElementMatch,
+    /// Space captured around each element, in CSS pixels
+    #[serde(default)]
+    pub padding: f64,
+    #[serde(default)]
+    pub not_found: NotFoundBehavior,
+}
+
+impl ElementCapture {
+    /// Capture the first element matching a CSS selector
+    pub fn new(selector: impl Into<String>) -> Self {
+        Self {
+            selector: selector.into(),
+            selector_type: SelectorType::default(),
+            matches: ElementMatch::default(),
+            padding: 0.0,
+            not_found: NotFoundBehavior::default(),
+        }
+    }
+
+    /// The selector split into its shadow root and iframe hops
+    pub fn hops(&self) -> Vec<&str> {
+        self.selector.split(SELECTOR_HOP).map(str::trim).collect()
+    }
+
+    /// Number of visible elements matching the selector
+    pub async fn count(&self, page: &Page) -> Result<usize, ScreenshotError> {
+        page.evaluate_expression(self.locate_script(None)?)
+            .await
+            .map_err(|e| ScreenshotError::PageError(e.to_string()))?
+            .into_value()
+            .map_err(|e| ScreenshotError::PageError(e.to_string()))
+    }
+
+    /// Scroll the `index`th visible match into view and return its padded
+    /// rectangle, or `None` if it no longer exists
+    pub async fn clip(&self, page: &Page, index: usize) -> Result<Option<Clip>, ScreenshotError> {
+        // An empty array rather than null, which CDP reports as no value
+        let clips: Vec<Clip> = page
+            .evaluate_expression(self.locate_script(Some(index))?)
+            .await
+            .map_err(|e| ScreenshotError::PageError(e.to_string()))?
+            .into_value()
+            .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+        Ok(clips.first().map(|clip| clip.padded(self.padding)))
+    }
+
+    /// Script returning the match count, or the document-relative rectangle
+    /// of one match after scrolling it into view
+    fn locate_script(&self, index: Option<usize>) -> Result<String, ScreenshotError> {
+        let hops = serde_json::to_string(&self.hops())?;
+        let xpath = self.selector_type == SelectorType::Xpath;
+        let index = serde_json::to_string(&index)?;
+
+        Ok(format!(
+            r#"(async () => {{
+    const hops = {hops};
+    const index = {index};
+    const query = (root, selector) => {{
+        if ({xpath}) {{
+            const doc = root.ownerDocument || root;
+            const found = doc.evaluate(selector, root, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+            const nodes = [];
+            for (let i = 0; i < found.snapshotLength; i++) nodes.push(found.snapshotItem(i));
+            return nodes.filter(node => node.nodeType === Node.ELEMENT_NODE);
+        }}
+        return Array.from(root.querySelectorAll(selector));
+    }};
+
+    let root = document;
+    const frames = [];
+    for (const hop of hops.slice(0, -1)) {{
+        const host = query(root, hop)[0];
+        if (host && host.shadowRoot) {{
+            root = host.shadowRoot;
+        }} else if (host && host.contentDocument) {{
+            frames.push(host);
+            root = host.contentDocument;
+        }} else {{
+            return index === null ? 0 : [];
+        }}
+    }}
+
+    const visible = query(root, hops[hops.length - 1]).filter(el => {{
+        const rect = el.getBoundingClientRect();
+        return rect.width > 0 && rect.height > 0;
+    }});
+    if (index === null) return visible.length;
+
+    const element = visible[index];
+    if (!element) return [];
+    element.scrollIntoView({{ block: 'nearest', inline: 'nearest' }});
+    await new Promise(resolve => requestAnimationFrame(() => requestAnimationFrame(resolve)));
+
+    // Rectangles are relative to their own frame's viewport
+    let x = window.scrollX, y = window.scrollY;
+    for (const frame of frames) {{
+        const rect = frame.getBoundingClientRect();
+        x += rect.left + frame.clientLeft;
+        y += rect.top + frame.clientTop;
+    }}
+    const rect = element.getBoundingClientRect();
+    return [{{ x: x + rect.left, y: y + rect.top, width: rect.width, height: rect.height }}];
+}})()"#
+        ))
+    }
+}
+
+/// Lay images out on a white grid with cells sized to the largest image
+///
+/// # Examples
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use screenshot_tool::contact_sheet;
+///
+/// let images = vec![DynamicImage::ImageRgba8(RgbaImage::new(100, 50)); 3];
+/// let sheet = contact_sheet(&images).unwrap();
+/// // Two columns, two rows, 16px gaps
+/// assert_eq!((sheet.width(), sheet.height()), (248, 148));
+/// ```
+pub fn contact_sheet(images: &[DynamicImage]) -> Result<DynamicImage, ScreenshotError> {
+    if images.is_empty() {
+        return Err(ScreenshotError::CaptureFailed(
+            "No elements to lay out on a contact sheet".to_string(),
+        ));
+    }
+
+    let columns = contact_sheet_columns(images.len());
+    let rows = (images.len() as u32).div_ceil(columns);
+    let cell_width = images.iter().map(DynamicImage::width).max().unwrap_or(0);
+    let cell_height = images.iter().map(DynamicImage::height).max().unwrap_or(0);
+
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width + (columns + 1) * CONTACT_SHEET_GAP,
+        rows * cell_height + (rows + 1) * CONTACT_SHEET_GAP,
+        Rgba([255, 255, 255, 255]),
+    );
+
+    for (image, (x, y)) in images.iter().zip(contact_sheet_cells(images)) {
+        imageops::overlay(&mut sheet, &image.to_rgba8(), i64::from(x), i64::from(y));
+    }
+
+    Ok(DynamicImage::ImageRgba8(sheet))
+}
+
+/// Top-left corner of each image on the sheet `contact_sheet` lays out
+///
+/// # Examples
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use screenshot_tool::contact_sheet_cells;
+///
+/// let images = vec![DynamicImage::ImageRgba8(RgbaImage::new(100, 50)); 3];
+/// assert_eq!(contact_sheet_cells(&images), vec![(16, 16), (132, 16), (16, 82)]);
+/// ```
+pub fn contact_sheet_cells(images: &[DynamicImage]) -> Vec<(u32, u32)> {
+    if images.is_empty() {
+        return Vec::new();
+    }
+
+    let columns = contact_sheet_columns(images.len());
+    let cell_width = images.iter().map(DynamicImage::width).max().unwrap_or(0);
+    let cell_height = images.iter().map(DynamicImage::height).max().unwrap_or(0);
+
+    (0..images.len() as u32)
+        .map(|index| {
+            let (column, row) = (index % columns, index / columns);
+            (
+                CONTACT_SHEET_GAP + column * (cell_width + CONTACT_SHEET_GAP),
+                CONTACT_SHEET_GAP + row * (cell_height + CONTACT_SHEET_GAP),
+            )
+        })
+        .collect()
+}
+
+fn contact_sheet_columns(count: usize) -> u32 {
+    (count as f64).sqrt().ceil() as u32
+}
@@ -0,0 +1,293 @@
+//! Media, locale, timezone and geolocation emulation
+//!
+//! Applied with CDP `Emulation` commands while the tab is still blank, so the
+//! page sees the emulated environment from its first script onwards.
+
+use crate::ScreenshotError;
+use chromiumoxide::cdp::browser_protocol::browser::{GrantPermissionsParams, PermissionType};
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    MediaFeature, SetEmulatedMediaParams, SetGeolocationOverrideParams, SetLocaleOverrideParams,
+    SetTimezoneOverrideParams,
+};
+use chromiumoxide::cdp::browser_protocol::target::GetTargetInfoParams;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+/// Value reported for `prefers-color-scheme`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+        }
+    }
+}
+
+impl std::str::FromStr for ColorScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "light" => Ok(ColorScheme::Light),
+            "dark" => Ok(ColorScheme::Dark),
+            _ => Err(format!("Unknown color scheme: {s}")),
+        }
+    }
+}
+
+/// CSS media type the page is rendered for
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Screen,
+    Print,
+}
+
+impl MediaType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Screen => "screen",
+            MediaType::Print => "print",
+        }
+    }
+}
+
+impl std::str::FromStr for MediaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "screen" => Ok(MediaType::Screen),
+            "print" => Ok(MediaType::Print),
+            _ => Err(format!("Unknown media type: {s}")),
+        }
+    }
+}
+
+/// Emulated position reported by the Geolocation API
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Geolocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Accuracy in meters
+    #[serde(default = "default_accuracy")]
+    pub accuracy: f64,
+}
+
+fn default_accuracy() -> f64 {
+    100.0
+}
+
+impl Geolocation {
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if !(-90.0..=90.0).contains(&self.latitude)
+            || !(-180.0..=180.0).contains(&self.longitude)
+            || self.accuracy < 0.0
+        {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "Invalid geolocation {},{} (accuracy {})",
+                self.latitude, self.longitude, self.accuracy
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Geolocation {
+    type Err = String;
+
+    /// Parse `latitude,longitude` or `latitude,longitude,accuracy`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid geolocation '{s}': {e}"))?;
+
+        match values[..] {
+            [latitude, longitude] => Ok(Geolocation {
+                latitude,
+                longitude,
+                accuracy: default_accuracy(),
+            }),
+            [latitude, longitude, accuracy] => Ok(Geolocation {
+                latitude,
+                longitude,
+                accuracy,
+            }),
+            _ => Err(format!(
+                "Geolocation must be given as latitude,longitude[,accuracy], got '{s}'"
+            )),
+        }
+    }
+}
+
+/// Environment emulated for the page
+///
+/// Flattened into both `Config` and `ScreenshotRequest`; options set on a
+/// request override the configured ones.
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{ColorScheme, Emulation};
+///
+/// let emulation: Emulation = serde_json::from_str(r#"{
+///     "color_scheme": "dark",
+///     "locale": "de-DE",
+///     "timezone": "Europe/Berlin",
+///     "geolocation": {"latitude": 52.52, "longitude": 13.40}
+/// }"#).unwrap();
+/// assert_eq!(emulation.color_scheme, Some(ColorScheme::Dark));
+/// assert_eq!(emulation.geolocation.unwrap().accuracy, 100.0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Emulation {
+    /// `prefers-color-scheme` media feature
+    pub color_scheme: Option<ColorScheme>,
+    /// Render for screen or print media
+    pub media: Option<MediaType>,
+    /// `prefers-reduced-motion: reduce` when true
+    pub reduced_motion: Option<bool>,
+    /// Locale for `Intl`, `navigator.language` and the `Accept-Language` header
+    pub locale: Option<String>,
+    /// IANA timezone ID, e.g. `America/New_York`
+    pub timezone: Option<String>,
+    /// Position reported to the page; geolocation permission is granted
+    pub geolocation: Option<Geolocation>,
+}
+
+impl Emulation {
+    /// These options overridden by any set in `other`
+    pub fn merged(&self, other: &Emulation) -> Emulation {
+        Emulation {
+            color_scheme: other.color_scheme.or(self.color_scheme),
+            media: other.media.or(self.media),
+            reduced_motion: other.reduced_motion.or(self.reduced_motion),
+            locale: other.locale.clone().or_else(|| self.locale.clone()),
+            timezone: other.timezone.clone().or_else(|| self.timezone.clone()),
+            geolocation: other.geolocation.or(self.geolocation),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if let Some(geolocation) = &self.geolocation {
+            geolocation.validate()?;
+        }
+        if self.locale.as_deref().is_some_and(str::is_empty) {
+            return Err(ScreenshotError::ConfigurationError(
+                "Locale must not be empty".to_string(),
+            ));
+        }
+        if self.timezone.as_deref().is_some_and(str::is_empty) {
+            return Err(ScreenshotError::ConfigurationError(
+                "Timezone must not be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Media type and features for `Emulation.setEmulatedMedia`
+    pub fn media_params(&self) -> Option<SetEmulatedMediaParams> {
+        let mut features = Vec::new();
+        if let Some(color_scheme) = self.color_scheme {
+            features.push(MediaFeature::new(
+                "prefers-color-scheme",
+                color_scheme.as_str(),
+            ));
+        }
+        if let Some(reduced_motion) = self.reduced_motion {
+            let value = if reduced_motion {
+                "reduce"
+            } else {
+                "no-preference"
+            };
+            features.push(MediaFeature::new("prefers-reduced-motion", value));
+        }
+
+        if self.media.is_none() && features.is_empty() {
+            return None;
+        }
+
+        Some(SetEmulatedMediaParams {
+            media: self.media.map(|media| media.as_str().to_string()),
+            features: (!features.is_empty()).then_some(features),
+        })
+    }
+
+    /// Apply the media, locale, timezone and geolocation overrides
+    ///
+    /// The `Accept-Language` header goes with the user agent override and is
+    /// set separately.
+    pub async fn apply(&self, page: &Page) -> Result<(), ScreenshotError> {
+        if let Some(params) = self.media_params() {
+            execute(page, params).await?;
+        }
+
+        if let Some(locale) = &self.locale {
+            execute(
+                page,
+                SetLocaleOverrideParams {
+                    locale: Some(locale.clone()),
+                },
+            )
+            .await?;
+        }
+
+        if let Some(timezone) = &self.timezone {
+            page.execute(SetTimezoneOverrideParams::new(timezone.clone()))
+                .await
+                .map_err(|e| {
+                    ScreenshotError::ConfigurationError(format!(
+                        "Invalid timezone '{timezone}': {e}"
+                    ))
+                })?;
+        }
+
+        if let Some(geolocation) = &self.geolocation {
+            // Granted in the tab's own browser context, which may be isolated
+            let target = page
+                .execute(GetTargetInfoParams {
+                    target_id: Some(page.target_id().clone()),
+                })
+                .await
+                .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+            let mut grant = GrantPermissionsParams::new(vec![PermissionType::Geolocation]);
+            grant.browser_context_id = target.result.target_info.browser_context_id.clone();
+            execute(page, grant).await?;
+
+            execute(
+                page,
+                SetGeolocationOverrideParams {
+                    latitude: Some(geolocation.latitude),
+                    longitude: Some(geolocation.longitude),
+                    accuracy: Some(geolocation.accuracy),
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn execute<T: chromiumoxide::types::Command>(
+    page: &Page,
+    params: T,
+) -> Result<(), ScreenshotError> {
+    page.execute(params)
+        .await
+        .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+    Ok(())
+}
@@ -54,6 +54,21 @@ pub enum ScreenshotError {
 
     #[error("Semaphore acquire error: {0}")]
     SemaphoreError(String),
+
+    #[error("Timed out after {1:?} waiting for {0}")]
+    WaitTimeout(String, Duration),
+
+    #[error("Waiting for {0} failed: {1}")]
+    WaitConditionFailed(String, String),
+
+    #[error("Action {0} ({1}) failed: {2}")]
+    ActionFailed(usize, String, String),
+
+    #[error("Unaccepted HTTP status {0} from {1}")]
+    UnacceptedStatus(u16, String),
+
+    #[error("Too many open sessions (limit {0})")]
+    TooManySessions(usize),
 }
 
 impl ScreenshotError {
@@ -64,8 +79,27 @@ impl ScreenshotError {
                 | ScreenshotError::UrlLoadFailed(_)
                 | ScreenshotError::NetworkError(_)
                 | ScreenshotError::Timeout(_)
+                | ScreenshotError::WaitTimeout(_, _)
                 | ScreenshotError::PageError(_)
                 | ScreenshotError::BrowserProcessDied(_)
+        ) || matches!(self, ScreenshotError::UnacceptedStatus(status, _) if *status >= 500)
+    }
+
+    /// Whether the error indicates a browser problem and should count towards
+    /// the circuit breaker
+    ///
+    /// Errors caused by the page itself, such as an unaccepted HTTP status or
+    /// a failed page command, say nothing about the health of the browser pool.
+    /// Operation timeouts still count, since a hung browser surfaces as one.
+    pub fn is_browser_failure(&self) -> bool {
+        matches!(
+            self,
+            ScreenshotError::BrowserUnavailable
+                | ScreenshotError::BrowserLaunchFailed(_)
+                | ScreenshotError::BrowserProcessDied(_)
+                | ScreenshotError::ChromeError(_)
+                | ScreenshotError::CaptureFailed(_)
+                | ScreenshotError::Timeout(_)
         )
     }
 
@@ -79,6 +113,21 @@ impl ScreenshotError {
             _ => ErrorSeverity::Medium,
         }
     }
+
+    /// Coarse error category used for metrics reporting
+    pub fn category(&self) -> &'static str {
+        match self {
+            ScreenshotError::NetworkError(_)
+            | ScreenshotError::UrlLoadFailed(_)
+            | ScreenshotError::UnacceptedStatus(_, _) => "network",
+            ScreenshotError::Timeout(_) | ScreenshotError::WaitTimeout(_, _) => "timeout",
+            ScreenshotError::BrowserUnavailable
+            | ScreenshotError::BrowserLaunchFailed(_)
+            | ScreenshotError::BrowserProcessDied(_)
+            | ScreenshotError::ChromeError(_) => "browser",
+            _ => "capture",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,705 @@
+//! EasyList / Adblock Plus filter list support for request blocking
+//!
+//! Parses the network-filter subset of the Adblock Plus syntax (domain anchors,
+//! wildcards, separators, `$` options and `@@` exceptions) and compiles it into
+//! a `FilterSet` that is built once and shared by every page in the browser pool.
+//! Element hiding rules and regular-expression filters are skipped.
+
+use crate::ScreenshotError;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use url::Url;
+
+/// Outcome of checking a request against a `FilterSet`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterVerdict {
+    /// A blocking rule matched and no exception rule applies
+    Block,
+    /// An `@@` exception rule matched
+    Allow,
+    /// No rule matched the request
+    NoMatch,
+}
+
+/// Request context needed to evaluate filter rules
+#[derive(Debug, Clone)]
+pub struct FilterRequest {
+    url: String,
+    url_lower: String,
+    host: String,
+    resource_type: ResourceKind,
+    document_host: Option<String>,
+}
+
+impl FilterRequest {
+    /// Build a request context; returns `None` for URLs without a host
+    ///
+    /// `resource_type` accepts lowercase CDP resource type names (e.g. `script`, `xhr`).
+    pub fn new(url: &str, resource_type: &str, document_url: Option<&str>) -> Option<Self> {
+        let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+        let document_host = document_url
+            .and_then(|u| Url::parse(u).ok())
+            .and_then(|u| u.host_str().map(|h| h.to_lowercase()));
+
+        Some(Self {
+            url: url.to_string(),
+            url_lower: url.to_lowercase(),
+            host,
+            resource_type: ResourceKind::from_cdp(resource_type),
+            document_host,
+        })
+    }
+
+    /// Whether the request goes to a different site than the document
+    ///
+    /// Returns `None` when the document is unknown. Sites are compared by their
+    /// last two host labels, which is an approximation of the registrable domain.
+    fn is_third_party(&self) -> Option<bool> {
+        self.document_host
+            .as_deref()
+            .map(|document| base_domain(document) != base_domain(&self.host))
+    }
+}
+
+/// Resource types understood by filter options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ResourceKind {
+    Script,
+    Image,
+    Stylesheet,
+    Object,
+    XmlHttpRequest,
+    Subdocument,
+    Font,
+    Media,
+    WebSocket,
+    Ping,
+    Document,
+    Other,
+}
+
+impl ResourceKind {
+    fn from_cdp(resource_type: &str) -> Self {
+        match resource_type {
+            "script" => Self::Script,
+            "image" => Self::Image,
+            "stylesheet" => Self::Stylesheet,
+            "xhr" | "fetch" => Self::XmlHttpRequest,
+            "subdocument" => Self::Subdocument,
+            "font" => Self::Font,
+            "media" => Self::Media,
+            "websocket" => Self::WebSocket,
+            "ping" | "beacon" => Self::Ping,
+            "document" => Self::Document,
+            _ => Self::Other,
+        }
+    }
+
+    fn from_option(option: &str) -> Option<Self> {
+        let kind = match option {
+            "script" => Self::Script,
+            "image" => Self::Image,
+            "stylesheet" => Self::Stylesheet,
+            "object" | "object-subrequest" => Self::Object,
+            "xmlhttprequest" | "xhr" => Self::XmlHttpRequest,
+            "subdocument" => Self::Subdocument,
+            "font" => Self::Font,
+            "media" => Self::Media,
+            "websocket" => Self::WebSocket,
+            "ping" => Self::Ping,
+            "document" => Self::Document,
+            "other" => Self::Other,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// A single element of a compiled URL pattern
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(Vec<u8>),
+    /// `*`: any sequence of characters
+    Wildcard,
+    /// `^`: a separator character or the end of the URL
+    Separator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anchor {
+    None,
+    /// `|` at the start: match from the beginning of the URL
+    Start,
+    /// `||`: match from the start of the host or any of its subdomains
+    Domain,
+}
+
+/// A compiled network filter rule
+#[derive(Debug, Clone)]
+pub struct FilterRule {
+    tokens: Vec<Token>,
+    anchor: Anchor,
+    end_anchor: bool,
+    exception: bool,
+    match_case: bool,
+    third_party: Option<bool>,
+    include_types: Vec<ResourceKind>,
+    exclude_types: Vec<ResourceKind>,
+    include_domains: Vec<String>,
+    exclude_domains: Vec<String>,
+}
+
+impl FilterRule {
+    /// Parse one line of a filter list
+    ///
+    /// Returns `None` for comments, element hiding rules, regular expressions,
+    /// and rules using options this engine does not support.
+    pub fn parse(line: &str) -> Option<Self> {
+        Self::parse_line(line).ok().flatten()
+    }
+
+    /// Parse one line, telling lines that are not request rules (`Ok(None)`)
+    /// apart from rules this engine cannot apply (`Err` with the reason)
+    fn parse_line(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+            return Ok(None);
+        }
+
+        // Element hiding and snippet rules
+        if line.contains("##")
+            || line.contains("#@#")
+            || line.contains("#?#")
+            || line.contains("#$#")
+        {
+            return Ok(None);
+        }
+
+        let (exception, line) = match line.strip_prefix("@@") {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        let (pattern, options) = match line.rfind('$') {
+            Some(index) if !line[index + 1..].contains('/') => (&line[..index], &line[index + 1..]),
+            _ => (line, ""),
+        };
+
+        // Regular-expression rules are not supported
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            return Err("regular expressions are not supported".to_string());
+        }
+
+        let mut rule = Self {
+            tokens: Vec::new(),
+            anchor: Anchor::None,
+            end_anchor: false,
+            exception,
+            match_case: false,
+            third_party: None,
+            include_types: Vec::new(),
+            exclude_types: Vec::new(),
+            include_domains: Vec::new(),
+            exclude_domains: Vec::new(),
+        };
+
+        if !options.is_empty() {
+            for option in options.split(',') {
+                let option = option.trim();
+                if !rule.apply_option(option) {
+                    return Err(format!("unsupported option '{option}'"));
+                }
+            }
+        }
+
+        let mut pattern = pattern;
+        if let Some(rest) = pattern.strip_prefix("||") {
+            rule.anchor = Anchor::Domain;
+            pattern = rest;
+        } else if let Some(rest) = pattern.strip_prefix('|') {
+            rule.anchor = Anchor::Start;
+            pattern = rest;
+        }
+        if let Some(rest) = pattern.strip_suffix('|') {
+            rule.end_anchor = true;
+            pattern = rest;
+        }
+
+        rule.tokens = tokenize(pattern, rule.match_case);
+
+        // A rule without any literal would match every request
+        if !rule.tokens.iter().any(|t| matches!(t, Token::Literal(_)))
+            && rule.include_domains.is_empty()
+        {
+            return Err("the rule would match every request".to_string());
+        }
+
+        Ok(Some(rule))
+    }
+
+    /// Apply a single `$` option; returns false for unsupported options
+    fn apply_option(&mut self, option: &str) -> bool {
+        let (negated, name) = match option.strip_prefix('~') {
+            Some(rest) => (true, rest),
+            None => (false, option),
+        };
+
+        if let Some(domains) = name.strip_prefix("domain=") {
+            for domain in domains.split('|') {
+                match domain.strip_prefix('~') {
+                    Some(excluded) => self.exclude_domains.push(excluded.to_lowercase()),
+                    None => self.include_domains.push(domain.to_lowercase()),
+                }
+            }
+            return true;
+        }
+
+        match name {
+            "third-party" | "3p" => self.third_party = Some(!negated),
+            "first-party" | "1p" => self.third_party = Some(negated),
+            "match-case" => self.match_case = true,
+            _ => match ResourceKind::from_option(name) {
+                Some(kind) if negated => self.exclude_types.push(kind),
+                Some(kind) => self.include_types.push(kind),
+                None => return false,
+            },
+        }
+
+        true
+    }
+
+    /// Whether this is an `@@` exception rule
+    pub fn is_exception(&self) -> bool {
+        self.exception
+    }
+
+    /// Check the rule against a request, including its options
+    pub fn matches(&self, request: &FilterRequest) -> bool {
+        let url = if self.match_case {
+            &request.url
+        } else {
+            &request.url_lower
+        };
+
+        self.options_match(request) && self.pattern_matches(url.as_bytes(), &request.host)
+    }
+
+    fn options_match(&self, request: &FilterRequest) -> bool {
+        if !self.include_types.is_empty() && !self.include_types.contains(&request.resource_type) {
+            return false;
+        }
+
+        if self.exclude_types.contains(&request.resource_type) {
+            return false;
+        }
+
+        if let Some(third_party) = self.third_party {
+            if request.is_third_party() != Some(third_party) {
+                return false;
+            }
+        }
+
+        if !self.include_domains.is_empty() || !self.exclude_domains.is_empty() {
+            let Some(document_host) = request.document_host.as_deref() else {
+                return false;
+            };
+
+            if self
+                .exclude_domains
+                .iter()
+                .any(|d| host_matches_domain(document_host, d))
+            {
+                return false;
+            }
+
+            if !self.include_domains.is_empty()
+                && !self
+                    .include_domains
+                    .iter()
+                    .any(|d| host_matches_domain(document_host, d))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn pattern_matches(&self, url: &[u8], host: &str) -> bool {
+        match self.anchor {
+            Anchor::Start => match_tokens(&self.tokens, url, self.end_anchor),
+            Anchor::None => (0..=url.len())
+                .any(|start| match_tokens(&self.tokens, &url[start..], self.end_anchor)),
+            Anchor::Domain => {
+                let Some(host_start) = find_host_start(url, host) else {
+                    return false;
+                };
+
+                // Try the host itself and every subdomain boundary within it
+                std::iter::once(host_start)
+                    .chain(
+                        host.bytes()
+                            .enumerate()
+                            .filter(|(_, b)| *b == b'.')
+                            .map(|(i, _)| host_start + i + 1),
+                    )
+                    .any(|start| match_tokens(&self.tokens, &url[start..], self.end_anchor))
+            }
+        }
+    }
+
+    /// Literal domain a `||` rule is anchored to, used to index the rule
+    ///
+    /// Only returned when the domain is terminated by `/` or `^`; otherwise
+    /// `||example.com` could also match hosts like `example.company.com`.
+    fn anchored_domain(&self) -> Option<String> {
+        if self.anchor != Anchor::Domain {
+            return None;
+        }
+
+        let Some(Token::Literal(literal)) = self.tokens.first() else {
+            return None;
+        };
+        let literal = String::from_utf8_lossy(literal);
+
+        let domain = match literal.split_once('/') {
+            Some((domain, _)) => domain,
+            None if matches!(self.tokens.get(1), Some(Token::Separator)) => literal.as_ref(),
+            None => return None,
+        };
+
+        (!domain.is_empty() && !domain.contains(':')).then(|| domain.to_lowercase())
+    }
+
+    /// Longest alphanumeric run that every matching URL contains as a whole
+    /// token, used to index the rule
+    ///
+    /// A run qualifies only when both of its ends are fixed by the pattern: a
+    /// non-alphanumeric character of the literal, a `^` separator or an anchor.
+    /// Next to a `*` wildcard the run could be part of a longer URL token.
+    fn index_token(&self) -> Option<String> {
+        let mut best: Option<&[u8]> = None;
+
+        for (i, token) in self.tokens.iter().enumerate() {
+            let Token::Literal(literal) = token else {
+                continue;
+            };
+            let bounded_before = match i {
+                0 => self.anchor != Anchor::None,
+                _ => self.tokens[i - 1] == Token::Separator,
+            };
+            let bounded_after = match self.tokens.get(i + 1) {
+                Some(next) => *next == Token::Separator,
+                None => self.end_anchor,
+            };
+
+            for (start, end) in token_runs(literal) {
+                let bounded =
+                    (start > 0 || bounded_before) && (end < literal.len() || bounded_after);
+                if bounded && best.is_none_or(|b| end - start > b.len()) {
+                    best = Some(&literal[start..end]);
+                }
+            }
+        }
+
+        best.map(|run| String::from_utf8_lossy(run).to_ascii_lowercase())
+    }
+}
+
+/// Rules indexed by one of their tokens, plus the rules without a usable token
+#[derive(Debug, Default)]
+struct RuleIndex {
+    by_token: HashMap<String, Vec<FilterRule>>,
+    untokenized: Vec<FilterRule>,
+}
+
+impl RuleIndex {
+    fn insert(&mut self, rule: FilterRule) {
+        match rule.index_token() {
+            Some(token) => self.by_token.entry(token).or_default().push(rule),
+            None => self.untokenized.push(rule),
+        }
+    }
+
+    /// Whether any rule indexed under one of `url_tokens` matches the request
+    fn matches(&self, request: &FilterRequest, url_tokens: &HashSet<&str>) -> bool {
+        url_tokens
+            .iter()
+            .filter_map(|token| self.by_token.get(*token))
+            .flatten()
+            .chain(&self.untokenized)
+            .any(|rule| rule.matches(request))
+    }
+}
+
+/// A compiled collection of filter rules
+///
+/// Domain-anchored rules are indexed by domain, and generic and exception rules
+/// by a token of their pattern, so that a lookup only scans the rules relevant
+/// to the request host and the tokens of its URL.
+#[derive(Debug, Default)]
+pub struct FilterSet {
+    domain_blocks: HashMap<String, Vec<FilterRule>>,
+    generic_blocks: RuleIndex,
+    exceptions: RuleIndex,
+    rule_count: usize,
+}
+
+impl FilterSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse filter list text, skipping unsupported lines
+    pub fn parse(content: &str) -> Self {
+        let mut set = Self::new();
+        set.add_list(content);
+        set
+    }
+
+    /// Load and compile every filter list file in `paths`
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<Self, ScreenshotError> {
+        let mut set = Self::new();
+
+        for path in paths {
+            let path = path.as_ref();
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                ScreenshotError::ConfigurationError(format!(
+                    "Failed to read filter list {}: {e}",
+                    path.display()
+                ))
+            })?;
+
+            let added = set.add_list(&content);
+            tracing::info!("Loaded {} filter rules from {}", added, path.display());
+        }
+
+        Ok(set)
+    }
+
+    /// Add the rules from one filter list; returns the number of rules compiled
+    ///
+    /// Rules using syntax or options this engine does not support are skipped
+    /// and logged rather than applied partially.
+    pub fn add_list(&mut self, content: &str) -> usize {
+        let before = self.rule_count;
+        let mut skipped = 0;
+
+        for line in content.lines() {
+            match FilterRule::parse_line(line) {
+                Ok(Some(rule)) => self.add_rule(rule),
+                Ok(None) => {}
+                Err(reason) => {
+                    skipped += 1;
+                    tracing::debug!("Skipping filter rule '{}': {}", line.trim(), reason);
+                }
+            }
+        }
+
+        if skipped > 0 {
+            tracing::warn!(
+                "Skipped {} unsupported filter rules (enable debug logging to list them)",
+                skipped
+            );
+        }
+
+        self.rule_count - before
+    }
+
+    pub fn add_rule(&mut self, rule: FilterRule) {
+        self.rule_count += 1;
+
+        if rule.is_exception() {
+            self.exceptions.insert(rule);
+        } else if let Some(domain) = rule.anchored_domain() {
+            self.domain_blocks.entry(domain).or_default().push(rule);
+        } else {
+            self.generic_blocks.insert(rule);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rule_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rule_count == 0
+    }
+
+    /// Evaluate a request; exception rules take precedence over blocking rules
+    pub fn check(&self, request: &FilterRequest) -> FilterVerdict {
+        let url_tokens: HashSet<&str> = token_runs(request.url_lower.as_bytes())
+            .map(|(start, end)| &request.url_lower[start..end])
+            .collect();
+
+        if self.exceptions.matches(request, &url_tokens) {
+            return FilterVerdict::Allow;
+        }
+
+        let domain_match = parent_domains(&request.host)
+            .filter_map(|domain| self.domain_blocks.get(domain))
+            .flatten()
+            .any(|rule| rule.matches(request));
+
+        if domain_match || self.generic_blocks.matches(request, &url_tokens) {
+            FilterVerdict::Block
+        } else {
+            FilterVerdict::NoMatch
+        }
+    }
+}
+
+fn tokenize(pattern: &str, match_case: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut literal = Vec::new();
+
+    for byte in pattern.bytes() {
+        let special = match byte {
+            b'*' => Some(Token::Wildcard),
+            b'^' => Some(Token::Separator),
+            _ => None,
+        };
+
+        match special {
+            Some(token) => {
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                }
+                // Collapse repeated wildcards
+                if !(token == Token::Wildcard && tokens.last() == Some(&Token::Wildcard)) {
+                    tokens.push(token);
+                }
+            }
+            None if match_case => literal.push(byte),
+            None => literal.push(byte.to_ascii_lowercase()),
+        }
+    }
+
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    tokens
+}
+
+/// Match tokens against the start of `input`
+///
+/// Greedy glob matching: on a mismatch only the most recent wildcard is
+/// extended, since every other token matches a fixed number of bytes. This
+/// keeps patterns with many wildcards at O(pattern * input).
+fn match_tokens(tokens: &[Token], input: &[u8], end_anchor: bool) -> bool {
+    let mut token = 0;
+    let mut position = 0;
+    // Token after the last wildcard, and the input position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    loop {
+        let matched = match tokens.get(token) {
+            None if !end_anchor || position == input.len() => return true,
+            None => false,
+            Some(Token::Wildcard) => {
+                if token + 1 == tokens.len() {
+                    return true;
+                }
+                backtrack = Some((token + 1, position));
+                token += 1;
+                continue;
+            }
+            Some(Token::Literal(literal)) => {
+                let matched = input[position..].starts_with(literal);
+                if matched {
+                    position += literal.len();
+                }
+                matched
+            }
+            // `^` also matches the end of the URL
+            Some(Token::Separator) if position == input.len() => true,
+            Some(Token::Separator) => {
+                let matched = is_separator(input[position]);
+                if matched {
+                    position += 1;
+                }
+                matched
+            }
+        };
+
+        if matched {
+            token += 1;
+            continue;
+        }
+
+        // Let the last wildcard swallow one more byte and retry from there
+        match backtrack {
+            Some((after, start)) if start < input.len() => {
+                backtrack = Some((after, start + 1));
+                token = after;
+                position = start + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Byte ranges of the maximal alphanumeric runs in `input`
+fn token_runs(input: &[u8]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut position = 0;
+    std::iter::from_fn(move || {
+        let start = position
+            + input[position..]
+                .iter()
+                .position(u8::is_ascii_alphanumeric)?;
+        let end = input[start..]
+            .iter()
+            .position(|b| !b.is_ascii_alphanumeric())
+            .map_or(input.len(), |len| start + len);
+        position = end;
+        Some((start, end))
+    })
+}
+
+fn is_separator(byte: u8) -> bool {
+    !(byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b'%'))
+}
+
+/// Byte offset of the host within a (lowercased) URL
+fn find_host_start(url: &[u8], host: &str) -> Option<usize> {
+    let scheme_end = url.windows(3).position(|w| w == b"://")? + 3;
+    let authority = &url[scheme_end..];
+
+    // Skip any userinfo before the host
+    let authority_len = authority
+        .iter()
+        .position(|b| matches!(b, b'/' | b'?' | b'#'))
+        .unwrap_or(authority.len());
+    let host_offset = authority[..authority_len]
+        .iter()
+        .rposition(|b| *b == b'@')
+        .map(|at| at + 1)
+        .unwrap_or(0);
+
+    authority[host_offset..]
+        .starts_with(host.as_bytes())
+        .then_some(scheme_end + host_offset)
+}
+
+fn host_matches_domain(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain) && host.as_bytes()[host.len() - domain.len() - 1] == b'.')
+}
+
+/// A host followed by each of its parent domains, e.g. `a.b.com`, `b.com`, `com`
+pub fn parent_domains(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(host), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
+}
+
+fn base_domain(host: &str) -> &str {
+    let mut dots = host.rmatch_indices('.');
+    dots.next();
+    match dots.next() {
+        Some((index, _)) => &host[index + 1..],
+        None => host,
+    }
+}
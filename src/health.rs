@@ -88,9 +88,9 @@ impl SystemHealthChecker {
             return HealthLevel::Warning;
         }
 
-        // Check utilization
-        let utilization = if stats.total_instances > 0 {
-            stats.busy_instances as f64 / stats.total_instances as f64
+        // Check utilization of tab slots across the pool
+        let utilization = if stats.tab_capacity > 0 {
+            stats.active_tabs as f64 / stats.tab_capacity as f64
         } else {
             0.0
         };
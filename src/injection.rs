@@ -0,0 +1,253 @@
+//! Stylesheet and script injection, element hiding and redaction
+//!
+//! Injection runs once the wait conditions are met and before any scripted
+//! actions. Hidden and redacted selectors are enforced with an injected
+//! stylesheet, so elements the page re-renders later are covered too.
+//! Redacted elements are additionally blacked out or blurred in the final
+//! image using their bounding boxes, measured immediately before capture.
+
+use crate::{Clip, ScreenshotError};
+use chromiumoxide::Page;
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Blur radius applied to redacted regions, in image pixels
+const REDACTION_BLUR_SIGMA: f32 = 16.0;
+
+/// How redacted elements are obscured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionStyle {
+    /// Solid black boxes
+    #[default]
+    Blackout,
+    /// Heavy gaussian blur
+    Blur,
+}
+
+impl std::str::FromStr for RedactionStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "blackout" | "black" => Ok(RedactionStyle::Blackout),
+            "blur" => Ok(RedactionStyle::Blur),
+            _ => Err(format!("Unknown redaction style: {s}")),
+        }
+    }
+}
+
+/// Styles, scripts and element hiding applied to the page before capture
+///
+/// Flattened into both `Config` and `ScreenshotRequest`; a request's lists
+/// are applied after the configured ones.
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{PageInjection, RedactionStyle};
+///
+/// let injection: PageInjection = serde_json::from_str(r##"{
+///     "hide_selectors": ["#cookie-banner", ".chat-widget"],
+///     "redact_selectors": [".email"],
+///     "redaction": "blur"
+/// }"##).unwrap();
+/// assert_eq!(injection.redaction_style(), RedactionStyle::Blur);
+/// assert!(injection.stylesheet().contains("visibility: hidden"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PageInjection {
+    /// CSS added to the page in `<style>` elements
+    pub inject_styles: Vec<String>,
+    /// JavaScript evaluated in the page, in order (promises are awaited)
+    pub inject_scripts: Vec<String>,
+    /// CSS selectors of elements made invisible, keeping their layout space
+    pub hide_selectors: Vec<String>,
+    /// CSS selectors of elements obscured in the captured image; not
+    /// supported with PDF output
+    pub redact_selectors: Vec<String>,
+    /// How redacted elements are obscured (default: blackout)
+    pub redaction: Option<RedactionStyle>,
+}
+
+impl PageInjection {
+    /// These settings followed by `other`'s, whose redaction style wins
+    pub fn merged(&self, other: &PageInjection) -> PageInjection {
+        let join = |a: &[String], b: &[String]| [a, b].concat();
+
+        PageInjection {
+            inject_styles: join(&self.inject_styles, &other.inject_styles),
+            inject_scripts: join(&self.inject_scripts, &other.inject_scripts),
+            hide_selectors: join(&self.hide_selectors, &other.hide_selectors),
+            redact_selectors: join(&self.redact_selectors, &other.redact_selectors),
+            redaction: other.redaction.or(self.redaction),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inject_styles.is_empty()
+            && self.inject_scripts.is_empty()
+            && self.hide_selectors.is_empty()
+            && self.redact_selectors.is_empty()
+    }
+
+    pub fn redaction_style(&self) -> RedactionStyle {
+        self.redaction.unwrap_or_default()
+    }
+
+    /// Stylesheet hiding and obscuring the configured selectors
+    pub fn stylesheet(&self) -> String {
+        let mut css = String::new();
+
+        for selector in &self.hide_selectors {
+            css.push_str(&format!(
+                "{selector} {{ visibility: hidden !important; }}\n"
+            ));
+        }
+
+        let filter = match self.redaction_style() {
+            RedactionStyle::Blackout => "brightness(0)",
+            RedactionStyle::Blur => "blur(12px)",
+        };
+        for selector in &self.redact_selectors {
+            css.push_str(&format!("{selector} {{ filter: {filter} !important; }}\n"));
+        }
+
+        css
+    }
+
+    /// Add the stylesheets and evaluate the scripts
+    pub async fn apply(&self, page: &Page) -> Result<(), ScreenshotError> {
+        let generated = self.stylesheet();
+        let styles = self
+            .inject_styles
+            .iter()
+            .chain(std::iter::once(&generated))
+            .filter(|css| !css.trim().is_empty());
+
+        for css in styles {
+            let css = serde_json::to_string(css)?;
+            page.evaluate_expression(format!(
+                "(() => {{ \
+                 const style = document.createElement('style'); \
+                 style.textContent = {css}; \
+                 (document.head || document.documentElement).appendChild(style); \
+                 return true; }})()"
+            ))
+            .await
+            .map_err(|e| ScreenshotError::PageError(format!("Failed to inject stylesheet: {e}")))?;
+        }
+
+        for (index, script) in self.inject_scripts.iter().enumerate() {
+            page.evaluate_expression(script.as_str())
+                .await
+                .map_err(|e| {
+                    ScreenshotError::PageError(format!("Injected script {} failed: {e}", index + 1))
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Document-relative boxes of the visible elements matching `redact_selectors`
+    pub async fn redaction_boxes(&self, page: &Page) -> Result<Vec<Clip>, ScreenshotError> {
+        selector_boxes(page, &self.redact_selectors).await
+    }
+}
+
+/// Document-relative boxes of the visible elements matching any of `selectors`
+pub async fn selector_boxes(
+    page: &Page,
+    selectors: &[String],
+) -> Result<Vec<Clip>, ScreenshotError> {
+    if selectors.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let selectors = serde_json::to_string(selectors)?;
+    page.evaluate_expression(format!(
+        "(() => {{ \
+         const boxes = []; \
+         for (const selector of {selectors}) {{ \
+         for (const el of document.querySelectorAll(selector)) {{ \
+         const rect = el.getBoundingClientRect(); \
+         if (rect.width > 0 && rect.height > 0) boxes.push({{ \
+         x: rect.left + window.scrollX, y: rect.top + window.scrollY, \
+         width: rect.width, height: rect.height }}); \
+         }} }} \
+         return boxes; }})()"
+    ))
+    .await
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))?
+    .into_value()
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))
+}
+
+/// The part of the document currently shown in the viewport
+pub async fn viewport_region(page: &Page) -> Result<Clip, ScreenshotError> {
+    page.evaluate_expression(
+        "({ x: window.scrollX, y: window.scrollY, width: window.innerWidth, height: window.innerHeight })",
+    )
+    .await
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))?
+    .into_value()
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))
+}
+
+/// A document-relative box in the pixels of a `width` x `height` image
+/// showing the `region` of the document
+///
+/// The box is grown to whole pixels and cut to the image, or `None` if it
+/// lies outside. The image may be at a higher device pixel ratio, taken from
+/// the image and region widths.
+pub fn image_rect(clip: &Clip, region: Clip, width: u32, height: u32) -> Option<Clip> {
+    if region.width <= 0.0 {
+        return None;
+    }
+    let scale = f64::from(width) / region.width;
+
+    let left = ((clip.x - region.x) * scale).floor().max(0.0);
+    let top = ((clip.y - region.y) * scale).floor().max(0.0);
+    let right = ((clip.x + clip.width - region.x) * scale)
+        .ceil()
+        .min(f64::from(width));
+    let bottom = ((clip.y + clip.height - region.y) * scale)
+        .ceil()
+        .min(f64::from(height));
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some(Clip {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+/// Obscure `boxes` in an image showing the `region` of the document
+///
+/// Boxes and region are in CSS pixels, mapped with `image_rect`.
+pub fn redact_image(image: &mut RgbaImage, region: Clip, boxes: &[Clip], style: RedactionStyle) {
+    for clip in boxes {
+        let Some(rect) = image_rect(clip, region, image.width(), image.height()) else {
+            continue;
+        };
+        let (x, y) = (rect.x as u32, rect.y as u32);
+        let (width, height) = (rect.width as u32, rect.height as u32);
+
+        match style {
+            RedactionStyle::Blackout => {
+                let black = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+                imageops::replace(image, &black, i64::from(x), i64::from(y));
+            }
+            RedactionStyle::Blur => {
+                let area = imageops::crop_imm(image, x, y, width, height).to_image();
+                let blurred = imageops::blur(&area, REDACTION_BLUR_SIGMA);
+                imageops::replace(image, &blurred, i64::from(x), i64::from(y));
+            }
+        }
+    }
+}
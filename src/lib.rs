@@ -148,15 +148,67 @@ pub mod health;
 /// Utility functions and helpers
 pub mod utils;
 
+/// EasyList / Adblock Plus filter list parsing and matching
+pub mod filters;
+
+/// HTTP API server exposing the screenshot service
+pub mod server;
+
+/// Wait conditions checked between navigation and capture
+pub mod wait;
+
+/// Scripted page actions run before capture
+pub mod actions;
+
+/// Named device emulation presets
+pub mod devices;
+
+/// Tiled full-page capture for tall and lazy-loading pages
+pub mod tiling;
+
+/// Clip rectangles and element capture
+pub mod elements;
+
+/// Stylesheet and script injection, element hiding and redaction
+pub mod injection;
+
+/// Color scheme, media, locale, timezone and geolocation emulation
+pub mod emulation;
+
+/// Deterministic rendering for reproducible screenshots
+pub mod deterministic;
+
+/// Visual diffs against baseline images
+pub mod diff;
+
+/// Baseline store and approval workflow for visual regression
+pub mod baseline;
+
+/// Scheduled recapture and change alerts for watched pages
+pub mod watch;
+
 #[cfg(test)]
 mod tests;
 
+pub use actions::*;
+pub use baseline::*;
 pub use browser_pool::*;
 pub use cli::*;
 pub use config::*;
+pub use deterministic::*;
+pub use devices::*;
+pub use diff::*;
+pub use elements::*;
+pub use emulation::*;
 pub use error::*;
+pub use filters::*;
 pub use health::*;
+pub use injection::*;
 pub use metrics::*;
 pub use screenshot_service::*;
+pub use server::*;
+pub use tiling::*;
 pub use utils::*;
+pub use wait::*;
+pub use watch::*;
 pub use worker::*;
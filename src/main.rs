@@ -1,8 +1,5 @@
 use clap::Parser;
-use screenshot_tool::{
-    setup_logging, Cli, CliRunner, Config, HealthMonitor, Metrics, MetricsCollector,
-};
-use std::sync::Arc;
+use screenshot_tool::{setup_logging, Cli, CliRunner, Config, HealthMonitor, MetricsCollector};
 use std::time::Duration;
 use tokio::signal;
 use tracing::{error, info};
@@ -24,7 +21,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli_runner = CliRunner::new(config.clone(), &args).await?;
 
     // Setup metrics and monitoring
-    let metrics = Arc::new(Metrics::new());
+    let metrics = cli_runner.metrics.clone();
     let metrics_collector = MetricsCollector::new(metrics.clone());
 
     // Start metrics collection
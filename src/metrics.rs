@@ -1,10 +1,14 @@
-use metrics::{Counter, Gauge, Histogram};
-// use metrics::{counter, gauge, histogram};
+use metrics::{
+    describe_counter, describe_gauge, describe_histogram, register_counter, register_gauge,
+    register_histogram, Counter, Gauge, Histogram, Unit,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{error, info};
 
 pub struct Metrics {
     pub screenshots_taken: Counter,
@@ -22,23 +26,67 @@ pub struct Metrics {
 }
 
 impl Metrics {
+    /// Register all metrics with the globally installed recorder
+    ///
+    /// Handles registered before a recorder is installed are no-ops, so this
+    /// should be called after `PrometheusExporter::start` when exporting.
     pub fn new() -> Self {
+        Self::describe();
+
         Self {
-            screenshots_taken: Counter::noop(),
-            screenshots_failed: Counter::noop(),
-            screenshot_duration: Histogram::noop(),
-            browser_pool_utilization: Gauge::noop(),
-            memory_usage: Gauge::noop(),
-            error_count: Counter::noop(),
-            queue_size: Gauge::noop(),
-            active_requests: Gauge::noop(),
-            browser_restarts: Counter::noop(),
-            network_errors: Counter::noop(),
-            timeout_errors: Counter::noop(),
-            retry_count: Counter::noop(),
+            screenshots_taken: register_counter!("screenshots_taken_total"),
+            screenshots_failed: register_counter!("screenshots_failed_total"),
+            screenshot_duration: register_histogram!("screenshot_duration_seconds"),
+            browser_pool_utilization: register_gauge!("browser_pool_utilization_percent"),
+            memory_usage: register_gauge!("memory_usage_bytes"),
+            error_count: register_counter!("errors_total"),
+            queue_size: register_gauge!("queue_size"),
+            active_requests: register_gauge!("active_requests"),
+            browser_restarts: register_counter!("browser_restarts_total"),
+            network_errors: register_counter!("network_errors_total"),
+            timeout_errors: register_counter!("timeout_errors_total"),
+            retry_count: register_counter!("retries_total"),
         }
     }
 
+    fn describe() {
+        describe_counter!(
+            "screenshots_taken_total",
+            "Number of screenshots captured successfully"
+        );
+        describe_counter!(
+            "screenshots_failed_total",
+            "Number of screenshots that failed after all retries"
+        );
+        describe_histogram!(
+            "screenshot_duration_seconds",
+            Unit::Seconds,
+            "Time taken to capture a screenshot"
+        );
+        describe_gauge!(
+            "browser_pool_utilization_percent",
+            "Percentage of browser instances currently busy"
+        );
+        describe_gauge!(
+            "memory_usage_bytes",
+            Unit::Bytes,
+            "Resident memory used by the process"
+        );
+        describe_counter!("errors_total", "Number of screenshot errors");
+        describe_gauge!(
+            "queue_size",
+            "Number of requests submitted to workers and not yet completed"
+        );
+        describe_gauge!("active_requests", "Number of screenshots in progress");
+        describe_counter!(
+            "browser_restarts_total",
+            "Number of browser instance restarts"
+        );
+        describe_counter!("network_errors_total", "Number of network errors");
+        describe_counter!("timeout_errors_total", "Number of screenshot timeouts");
+        describe_counter!("retries_total", "Number of screenshot retry attempts");
+    }
+
     pub fn record_screenshot(&self, duration: Duration, success: bool) {
         if success {
             self.screenshots_taken.increment(1);
@@ -50,6 +98,10 @@ impl Metrics {
     }
 
     pub fn record_browser_usage(&self, active_instances: usize, total_instances: usize) {
+        if total_instances == 0 {
+            return;
+        }
+
         let utilization = (active_instances as f64 / total_instances as f64) * 100.0;
         self.browser_pool_utilization.set(utilization);
     }
@@ -248,27 +300,51 @@ pub struct PerformanceStats {
     pub error_rates: HashMap<String, usize>,
 }
 
+/// Buckets (in seconds) used for the `screenshot_duration_seconds` histogram
+const SCREENSHOT_DURATION_BUCKETS: &[f64] = &[0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0];
+
 pub struct PrometheusExporter {
-    port: u16,
+    addr: SocketAddr,
 }
 
 impl PrometheusExporter {
-    pub fn new(_metrics: Arc<Metrics>, port: u16) -> Self {
-        Self { port }
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
     }
 
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+    /// Install the Prometheus recorder and serve `/metrics` on the exporter address
+    ///
+    /// Must be called from within a tokio runtime. The returned handle can be
+    /// used to render the same metrics elsewhere, e.g. from the API server.
+    pub async fn start(&self) -> Result<PrometheusHandle, Box<dyn std::error::Error>> {
+        let (recorder, exporter) = Self::builder()?.with_http_listener(self.addr).build()?;
+        let handle = recorder.handle();
 
         metrics::set_boxed_recorder(Box::new(recorder))?;
 
-        // Start the HTTP server for metrics endpoint
-        info!("Starting Prometheus metrics server on port {}", self.port);
+        info!("Starting Prometheus metrics server on {}", self.addr);
 
-        // TODO: Implement actual HTTP server
-        // This would typically use a web framework to serve the /metrics endpoint
+        tokio::spawn(async move {
+            if let Err(e) = exporter.await {
+                error!("Prometheus metrics server stopped: {:?}", e);
+            }
+        });
+
+        Ok(handle)
+    }
+
+    /// Install the Prometheus recorder without starting a listener
+    ///
+    /// Used when metrics are rendered by another HTTP server.
+    pub fn install_recorder() -> Result<PrometheusHandle, Box<dyn std::error::Error>> {
+        Ok(Self::builder()?.install_recorder()?)
+    }
 
-        Ok(())
+    fn builder() -> Result<PrometheusBuilder, Box<dyn std::error::Error>> {
+        Ok(PrometheusBuilder::new().set_buckets_for_metric(
+            Matcher::Full("screenshot_duration_seconds".to_string()),
+            SCREENSHOT_DURATION_BUCKETS,
+        )?)
     }
 }
 
@@ -5,19 +5,228 @@
 //! screenshot operations.
 
 use crate::{
-    BrowserPool, CircuitBreaker, Config, OutputFormat, Priority, RetryConfig, ScreenshotError,
-    ScreenshotMetadata, ScreenshotRequest, ScreenshotResult,
+    capture_tiled, contact_sheet, contact_sheet_cells, encode_image, image_rect, load_lazy_content,
+    merge_headers, redact_image, run_actions, selector_boxes, transcode_png, viewport_region,
+    BasicAuth, BrowserContextMode, BrowserIsolation, BrowserPool, CircuitBreaker, Clip, Config,
+    DeterministicOptions, Device, DeviceCatalog, ElementCapture, ElementMatch, Emulation,
+    EncoderSettings, ImageEncoding, Metrics, NetworkTracker, NotFoundBehavior, Orientation,
+    OutputFormat, PageInjection, PdfOptions, Priority, RedactionStyle, Redirect,
+    RequestInterceptor, RetryConfig, ScreenshotError, ScreenshotMetadata, ScreenshotRequest,
+    ScreenshotResult, Viewport, ViewportTarget, WaitCondition,
 };
 // use chromiumoxide::browser::Browser;
-use chromiumoxide::handler::viewport::Viewport as ChromeViewport;
-use chromiumoxide::page::{Page, ScreenshotParams};
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    ScreenOrientation, ScreenOrientationType, SetDeviceMetricsOverrideParams,
+    SetTouchEmulationEnabledParams,
+};
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    AuthChallengeResponse, AuthChallengeResponseResponse, AuthChallengeSource,
+    ContinueRequestParams, ContinueWithAuthParams, EnableParams as FetchEnableParams,
+    EventAuthRequired, EventRequestPaused, FailRequestParams, HeaderEntry,
+};
+use chromiumoxide::cdp::browser_protocol::network::{
+    CookieParam, ErrorReason, ResourceType, Response, SetCookiesParams, SetUserAgentOverrideParams,
+    TimeSinceEpoch,
+};
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, PrintToPdfParams};
+use chromiumoxide::handler::http::HttpRequest;
+use chromiumoxide::page::{Page, ScreenshotParams, ScreenshotParamsBuilder};
 use futures::future::try_join_all;
-use std::collections::VecDeque;
+use futures::StreamExt;
+use image::{DynamicImage, RgbaImage};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, timeout};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Main document response collected from the page's network events
+#[derive(Debug, Default)]
+struct DocumentResponse {
+    status: Option<u16>,
+    url: Option<String>,
+    redirect_chain: Vec<Redirect>,
+    headers: HashMap<String, String>,
+    mime_type: Option<String>,
+}
+
+impl DocumentResponse {
+    fn from_request(request: &HttpRequest) -> Self {
+        let status = |response: &Response| u16::try_from(response.status).ok();
+
+        let redirect_chain = request
+            .redirect_chain
+            .iter()
+            .filter_map(|hop| {
+                let response = hop.response.as_ref()?;
+                Some(Redirect {
+                    url: response.url.clone(),
+                    status: status(response)?,
+                })
+            })
+            .collect();
+
+        let Some(response) = &request.response else {
+            return Self {
+                redirect_chain,
+                ..Default::default()
+            };
+        };
+
+        let headers = response
+            .headers
+            .inner()
+            .as_object()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = value
+                            .as_str()
+                            .map(str::to_string)
+                            .unwrap_or_else(|| value.to_string());
+                        (name.clone(), value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            status: status(response),
+            url: Some(response.url.clone()),
+            redirect_chain,
+            headers,
+            mime_type: Some(response.mime_type.clone()),
+        }
+    }
+}
+
+/// Viewport, device and label a capture is taken with
+#[derive(Debug, Clone)]
+struct ViewportPlan {
+    /// Set for multi-viewport requests
+    label: Option<String>,
+    viewport: Viewport,
+    device: Option<Device>,
+}
+
+/// Element boxes measured right before capture, in document CSS pixels
+#[derive(Debug, Clone)]
+struct CaptureMarks {
+    /// Obscured in the image
+    redacted: Vec<Clip>,
+    style: RedactionStyle,
+    /// Reported in `ScreenshotMetadata::ignore_regions` for visual diffs
+    ignored: Vec<Clip>,
+}
+
+impl CaptureMarks {
+    /// Obscure the redacted boxes in an image showing the document `region`,
+    /// returning the ignored boxes in image pixels
+    fn apply(&self, image: &mut RgbaImage, region: Clip) -> Vec<Clip> {
+        redact_image(image, region, &self.redacted, self.style);
+
+        self.ignored
+            .iter()
+            .filter_map(|clip| image_rect(clip, region, image.width(), image.height()))
+            .collect()
+    }
+}
+
+/// Encoded capture and the ignored boxes it shows, in image pixels
+#[derive(Debug)]
+struct Capture {
+    data: Vec<u8>,
+    encoding: Option<ImageEncoding>,
+    ignore_regions: Vec<Clip>,
+}
+
+impl Capture {
+    fn new(data: Vec<u8>, encoding: Option<ImageEncoding>) -> Self {
+        Self {
+            data,
+            encoding,
+            ignore_regions: Vec::new(),
+        }
+    }
+}
+
+/// Encoding of an image encoded locally rather than by Chrome
+///
+/// Lossy WebP is only produced by Chrome, so tiled, composed and marked WebP
+/// captures fall back to lossless encoding.
+fn local_encoding(format: &OutputFormat, encoder: &EncoderSettings) -> Option<ImageEncoding> {
+    if *format == OutputFormat::Webp && !encoder.webp_lossless {
+        warn!("Lossy WebP is only available for Chrome captures; encoding losslessly");
+    }
+    encoder.encoding(format, true)
+}
+
+/// Wait two animation frames so layout and paint catch up with a resize
+async fn wait_for_layout(page: &Page) -> Result<(), ScreenshotError> {
+    page.evaluate_expression(
+        "new Promise(resolve => requestAnimationFrame(() => requestAnimationFrame(() => resolve(true))))",
+    )
+    .await
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Times basic auth credentials are offered before a challenge is cancelled
+const MAX_AUTH_ATTEMPTS: usize = 3;
+
+/// Request interception attached to a single page
+///
+/// Aborts the background task answering `Fetch.requestPaused` events when dropped.
+struct PageInterception {
+    task: JoinHandle<()>,
+    blocked: Arc<AtomicUsize>,
+}
+
+impl PageInterception {
+    fn blocked_count(&self) -> usize {
+        self.blocked.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for PageInterception {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Answer a `Fetch.authRequired` challenge, cancelling it when no credentials apply
+async fn answer_auth_challenge(
+    page: &Page,
+    challenge: &EventAuthRequired,
+    credentials: Option<&BasicAuth>,
+) {
+    let response = match credentials {
+        Some(credentials) => AuthChallengeResponse {
+            response: AuthChallengeResponseResponse::ProvideCredentials,
+            username: Some(credentials.username.clone()),
+            password: Some(credentials.password.clone()),
+        },
+        None => AuthChallengeResponse::new(AuthChallengeResponseResponse::CancelAuth),
+    };
+
+    if let Err(e) = page
+        .execute(ContinueWithAuthParams::new(
+            challenge.request_id.clone(),
+            response,
+        ))
+        .await
+    {
+        warn!(
+            "Failed to answer auth challenge from {}: {}",
+            challenge.auth_challenge.origin, e
+        );
+    }
+}
 
 /// High-performance screenshot service with browser pool management
 ///
@@ -52,13 +261,36 @@ pub struct ScreenshotService {
     circuit_breaker: Arc<CircuitBreaker>,
     concurrency_limiter: Arc<Semaphore>,
     retry_config: RetryConfig,
+    metrics: Arc<Metrics>,
+    active_requests: Arc<AtomicUsize>,
+    interceptor: Arc<RequestInterceptor>,
+    devices: Arc<DeviceCatalog>,
 }
 
 impl ScreenshotService {
     pub async fn new(config: Config) -> Result<Self, ScreenshotError> {
-        let browser_pool = Arc::new(BrowserPool::new(config.clone()).await?);
+        Self::with_metrics(config, Arc::new(Metrics::new())).await
+    }
+
+    /// Create a service that reports to the given metrics registry
+    pub async fn with_metrics(
+        config: Config,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, ScreenshotError> {
+        config.encoder.validate()?;
+        config.emulation.validate()?;
+        config.pdf.validate()?;
+        config.full_page.validate()?;
+        let devices = Arc::new(match &config.devices_file {
+            Some(path) => DeviceCatalog::with_file(path)?,
+            None => DeviceCatalog::builtin(),
+        });
+
+        let browser_pool =
+            Arc::new(BrowserPool::with_metrics(config.clone(), metrics.clone()).await?);
         let circuit_breaker = Arc::new(CircuitBreaker::new(5, Duration::from_secs(30)));
         let concurrency_limiter = Arc::new(Semaphore::new(config.max_concurrent_screenshots));
+        let interceptor = Arc::new(RequestInterceptor::from_settings(&config.optimization)?);
 
         Ok(Self {
             browser_pool,
@@ -67,9 +299,17 @@ impl ScreenshotService {
             circuit_breaker,
             concurrency_limiter,
             retry_config: RetryConfig::default(),
+            metrics,
+            active_requests: Arc::new(AtomicUsize::new(0)),
+            interceptor,
+            devices,
         })
     }
 
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
     pub async fn screenshot_urls(
         &self,
         urls: Vec<String>,
@@ -85,6 +325,10 @@ impl ScreenshotService {
         self.process_requests(requests).await
     }
 
+    /// Capture a single request
+    ///
+    /// A multi-viewport or multi-element request only yields its first result
+    /// here; use `process_requests` to receive all of them.
     pub async fn screenshot_single(
         &self,
         request: ScreenshotRequest,
@@ -119,7 +363,16 @@ impl ScreenshotService {
 
                 tokio::spawn(async move {
                     let _permit = semaphore.acquire().await?;
-                    service.take_screenshot_with_retry(request).await
+
+                    let active = service.active_requests.fetch_add(1, Ordering::Relaxed) + 1;
+                    service.metrics.set_active_requests(active);
+
+                    let result = service.take_screenshot_with_retry(request).await;
+
+                    let active = service.active_requests.fetch_sub(1, Ordering::Relaxed) - 1;
+                    service.metrics.set_active_requests(active);
+
+                    result
                 })
             })
             .collect();
@@ -128,14 +381,17 @@ impl ScreenshotService {
             .await
             .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
 
-        results.into_iter().collect::<Result<Vec<_>, _>>()
+        // Multi-viewport requests contribute one result per viewport
+        let results = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(results.into_iter().flatten().collect())
     }
 
     async fn take_screenshot_with_retry(
         &self,
         mut request: ScreenshotRequest,
-    ) -> Result<ScreenshotResult, ScreenshotError> {
+    ) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
         let mut last_error = None;
+        let start_time = Instant::now();
 
         for attempt in 0..self.retry_config.max_attempts {
             if !self.circuit_breaker.can_execute() {
@@ -145,19 +401,26 @@ impl ScreenshotService {
             request.retry_count = attempt;
 
             match self.take_screenshot(request.clone()).await {
-                Ok(mut result) => {
+                Ok(mut results) => {
                     self.circuit_breaker.record_success();
-                    result.success = true;
-                    return Ok(result);
+                    for result in &mut results {
+                        self.metrics.record_screenshot(result.duration, true);
+                        result.success = true;
+                    }
+                    return Ok(results);
                 }
                 Err(e) => {
                     last_error = Some(e.clone());
-                    self.circuit_breaker.record_failure();
+                    if e.is_browser_failure() {
+                        self.circuit_breaker.record_failure();
+                    }
+                    self.metrics.record_error(e.category());
 
                     if !e.is_retryable() || attempt == self.retry_config.max_attempts - 1 {
                         break;
                     }
 
+                    self.metrics.record_retry();
                     let delay = self.calculate_retry_delay(attempt);
                     debug!(
                         "Retrying screenshot for {} after {:?} (attempt {}/{})",
@@ -171,31 +434,54 @@ impl ScreenshotService {
             }
         }
 
-        // Return failed result
-        Ok(ScreenshotResult {
-            request_id: request.id,
-            url: request.url,
-            data: Vec::new(),
-            format: self.config.output_format.clone(),
-            timestamp: SystemTime::now(),
-            duration: Duration::from_secs(0),
-            success: false,
-            error: last_error,
-            metadata: ScreenshotMetadata {
-                viewport: self.config.viewport.clone(),
-                page_title: None,
-                final_url: None,
-                response_status: None,
-                file_size: 0,
-                browser_instance_id: 0,
-            },
-        })
+        self.metrics.record_screenshot(start_time.elapsed(), false);
+
+        // Return a failed result for every viewport that was requested
+        let labels: Vec<Option<String>> = if request.viewports.is_empty() {
+            vec![None]
+        } else {
+            request
+                .viewports
+                .iter()
+                .map(|target| Some(target.label()))
+                .collect()
+        };
+
+        Ok(labels
+            .into_iter()
+            .map(|viewport_label| ScreenshotResult {
+                format: request.output_format(&self.config.output_format),
+                request_id: request.id.clone(),
+                url: request.url.clone(),
+                data: Vec::new(),
+                timestamp: SystemTime::now(),
+                duration: Duration::from_secs(0),
+                success: false,
+                error: last_error.clone(),
+                metadata: ScreenshotMetadata {
+                    viewport: self.config.viewport.clone(),
+                    viewport_label,
+                    element_index: None,
+                    page_title: None,
+                    final_url: None,
+                    response_status: None,
+                    redirect_chain: Vec::new(),
+                    response_headers: HashMap::new(),
+                    mime_type: None,
+                    file_size: 0,
+                    encoding: None,
+                    browser_instance_id: 0,
+                    blocked_requests: 0,
+                    ignore_regions: Vec::new(),
+                },
+            })
+            .collect())
     }
 
     async fn take_screenshot(
         &self,
         request: ScreenshotRequest,
-    ) -> Result<ScreenshotResult, ScreenshotError> {
+    ) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
         let start_time = Instant::now();
 
         // Validate URL
@@ -203,75 +489,372 @@ impl ScreenshotService {
             return Err(ScreenshotError::InvalidUrl(request.url.clone()));
         }
 
-        // Get browser instance
-        let browser_handle = self.browser_pool.get_browser().await?;
-        let browser_instance_id = browser_handle.instance_id;
+        if let Some(encoder) = &request.encoder {
+            encoder.validate()?;
+        }
+        if let Some(pdf) = &request.pdf {
+            pdf.validate()?;
+        }
+        if let Some(full_page) = &request.full_page_options {
+            full_page.validate()?;
+        }
+        // Printed pages are vector output; there is no image to redact
+        if request.output_format(&self.config.output_format) == OutputFormat::Pdf
+            && !self.injection_for(&request).redact_selectors.is_empty()
+        {
+            return Err(ScreenshotError::ConfigurationError(
+                "redact_selectors are not supported with PDF output".to_string(),
+            ));
+        }
+        self.emulation_for(&request).validate()?;
+        self.config
+            .deterministic
+            .check_request(request.deterministic)?;
+        let plans = self.viewport_plans(&request)?;
 
-        // Create new page
-        let browser = browser_handle.browser.lock().await;
-        let page = browser
-            .new_page(&request.url)
-            .await
-            .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+        // Lease a blank tab so interception is active before the first request
+        let lease = self
+            .browser_pool
+            .acquire_page(&self.context_mode(&request))
+            .await?;
 
         let result = self
-            .capture_screenshot_with_timeout(&page, &request, browser_instance_id, start_time)
+            .load_and_capture(
+                lease.page(),
+                &request,
+                &plans,
+                lease.instance_id,
+                start_time,
+            )
             .await;
 
-        // Close page
-        let _ = page.close().await;
+        // Close page and return the tab slot
+        lease.close().await;
 
         result
     }
 
-    async fn capture_screenshot_with_timeout(
+    /// Wait conditions for a request, including network idle when enabled in config
+    fn wait_conditions(&self, request: &ScreenshotRequest) -> Vec<WaitCondition> {
+        let mut conditions = request.wait_for.clone();
+
+        if self.config.optimization.wait_for_network_idle
+            && !conditions.iter().any(WaitCondition::needs_network_tracking)
+        {
+            conditions.push(WaitCondition::NetworkIdle {
+                idle_ms: None,
+                timeout_ms: None,
+            });
+        }
+
+        conditions
+    }
+
+    fn lookup_device(&self, name: &str) -> Result<Device, ScreenshotError> {
+        self.devices
+            .get(name)
+            .ok_or_else(|| ScreenshotError::ConfigurationError(format!("Unknown device: {name}")))
+    }
+
+    /// Viewports a request is captured at, in order
+    ///
+    /// Without `viewports` a request is captured once, at its explicit
+    /// viewport, then its device preset, then the configured default.
+    fn viewport_plans(
         &self,
-        page: &Page,
         request: &ScreenshotRequest,
-        browser_instance_id: usize,
-        start_time: Instant,
-    ) -> Result<ScreenshotResult, ScreenshotError> {
-        let capture_future =
-            self.capture_screenshot(page, request, browser_instance_id, start_time);
+    ) -> Result<Vec<ViewportPlan>, ScreenshotError> {
+        if request.viewports.is_empty() {
+            let device = request
+                .device
+                .as_deref()
+                .map(|name| self.lookup_device(name))
+                .transpose()?;
+            let viewport = request
+                .custom_viewport
+                .clone()
+                .or_else(|| device.as_ref().map(Device::viewport))
+                .unwrap_or_else(|| self.config.viewport.clone());
 
-        match timeout(self.config.screenshot_timeout, capture_future).await {
-            Ok(result) => result,
-            Err(_) => Err(ScreenshotError::Timeout(self.config.screenshot_timeout)),
+            return Ok(vec![ViewportPlan {
+                label: None,
+                viewport,
+                device,
+            }]);
         }
+
+        request
+            .viewports
+            .iter()
+            .map(|target| {
+                let device = match target {
+                    ViewportTarget::Device(name) => Some(self.lookup_device(name)?),
+                    ViewportTarget::Custom { .. } => None,
+                };
+                let viewport = match (target, &device) {
+                    (ViewportTarget::Custom { viewport, .. }, _) => viewport.clone(),
+                    (_, Some(device)) => device.viewport(),
+                    (_, None) => self.config.viewport.clone(),
+                };
+
+                Ok(ViewportPlan {
+                    label: Some(target.label()),
+                    viewport,
+                    device,
+                })
+            })
+            .collect()
     }
 
-    async fn capture_screenshot(
+    /// User agent override for a capture: the request's, then the device's
+    fn user_agent_for<'a>(
+        &self,
+        request: &'a ScreenshotRequest,
+        plan: &'a ViewportPlan,
+    ) -> Option<&'a str> {
+        request.user_agent.as_deref().or(plan
+            .device
+            .as_ref()
+            .and_then(|device| device.user_agent.as_deref()))
+    }
+
+    /// Device presets available to requests
+    pub fn devices(&self) -> &DeviceCatalog {
+        &self.devices
+    }
+
+    /// Browser context a request's tab should be opened in
+    fn context_mode(&self, request: &ScreenshotRequest) -> BrowserContextMode {
+        if let Some(session) = &request.session {
+            return BrowserContextMode::Session(session.clone());
+        }
+
+        match request.isolation.unwrap_or(self.config.isolation) {
+            // Permission grants outlive the tab; one only this request asked
+            // for must not reach later pages in the shared context
+            BrowserIsolation::Shared
+                if request.emulation.geolocation.is_some()
+                    && self.config.emulation.geolocation.is_none() =>
+            {
+                BrowserContextMode::Incognito
+            }
+            BrowserIsolation::Shared => BrowserContextMode::Shared,
+            BrowserIsolation::Incognito => BrowserContextMode::Incognito,
+        }
+    }
+
+    /// Dispose a named session and everything stored in its browser context
+    ///
+    /// Returns `false` if the session does not exist.
+    pub async fn close_session(&self, name: &str) -> bool {
+        self.browser_pool.close_session(name).await
+    }
+
+    async fn load_and_capture(
         &self,
         page: &Page,
         request: &ScreenshotRequest,
+        plans: &[ViewportPlan],
         browser_instance_id: usize,
         start_time: Instant,
-    ) -> Result<ScreenshotResult, ScreenshotError> {
-        // Set viewport
-        let viewport = request
-            .custom_viewport
-            .as_ref()
-            .unwrap_or(&self.config.viewport);
-        let _chrome_viewport = ChromeViewport {
-            width: viewport.width,
-            height: viewport.height,
-            device_scale_factor: Some(viewport.device_scale_factor),
-            emulating_mobile: viewport.mobile,
-            has_touch: viewport.mobile,
-            is_landscape: viewport.width > viewport.height,
+    ) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
+        // The tab is still on about:blank, so everything configured here
+        // applies to the first request of the navigation
+        self.prepare_page(page, request, &plans[0]).await?;
+        let interception = self.intercept_requests(page, request).await?;
+
+        // Network idle can only be measured if tracking starts before navigation
+        let network = if self
+            .wait_conditions(request)
+            .iter()
+            .any(WaitCondition::needs_network_tracking)
+        {
+            Some(NetworkTracker::start(page).await?)
+        } else {
+            None
         };
 
-        // Set viewport using Chrome DevTools Protocol
-        let viewport = request
-            .custom_viewport
+        let response = self.navigate(page, &request.url).await?;
+        debug!(
+            "Navigated to {} (status {:?}, {} redirects)",
+            request.url,
+            response.status,
+            response.redirect_chain.len()
+        );
+
+        let accept_statuses = request
+            .accept_statuses
             .as_ref()
-            .unwrap_or(&self.config.viewport);
+            .or(self.config.accept_statuses.as_ref());
+        if let (Some(accept), Some(status)) = (accept_statuses, response.status) {
+            if !accept.accepts(status) {
+                let url = response.url.unwrap_or_else(|| request.url.clone());
+                return Err(ScreenshotError::UnacceptedStatus(status, url));
+            }
+        }
+
+        let mut results = self
+            .capture_screenshot_with_timeout(
+                page,
+                request,
+                plans,
+                browser_instance_id,
+                start_time,
+                network.as_ref(),
+            )
+            .await?;
+
+        let blocked_requests = interception.map(|interception| interception.blocked_count());
+        if let Some(blocked) = blocked_requests {
+            debug!("Blocked {} requests while loading {}", blocked, request.url);
+        }
+
+        for result in &mut results {
+            result.metadata.response_status = response.status;
+            result.metadata.redirect_chain = response.redirect_chain.clone();
+            result.metadata.response_headers = response.headers.clone();
+            result.metadata.mime_type = response.mime_type.clone();
+            if response.url.is_some() {
+                result.metadata.final_url = response.url.clone();
+            }
+            result.metadata.blocked_requests = blocked_requests.unwrap_or(0);
+        }
+
+        Ok(results)
+    }
+
+    /// Navigate to the URL and wait for the main document response
+    async fn navigate(&self, page: &Page, url: &str) -> Result<DocumentResponse, ScreenshotError> {
+        page.goto(url)
+            .await
+            .map_err(|e| ScreenshotError::UrlLoadFailed(e.to_string()))?;
+
+        let request = page
+            .wait_for_navigation_response()
+            .await
+            .map_err(|e| ScreenshotError::UrlLoadFailed(e.to_string()))?;
+
+        Ok(request
+            .map(|request| DocumentResponse::from_request(&request))
+            .unwrap_or_default())
+    }
+
+    /// Apply viewport and environment emulation, user agent and cookies before
+    /// navigation
+    ///
+    /// Extra headers are added by `intercept_requests`, which can limit them
+    /// to the captured origin.
+    async fn prepare_page(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        plan: &ViewportPlan,
+    ) -> Result<(), ScreenshotError> {
+        self.emulate(page, plan, None).await?;
+        self.emulation_for(request).apply(page).await?;
+        if let Some(deterministic) = self.deterministic_for(request) {
+            deterministic.prepare(page).await?;
+        }
+
+        if self.user_agent_for(request, plan).is_some()
+            || self.emulation_for(request).locale.is_some()
+        {
+            self.override_user_agent(page, request, plan).await?;
+        }
+
+        if !request.cookies.is_empty() {
+            let cookies = request
+                .cookies
+                .iter()
+                .map(|cookie| {
+                    let mut param = CookieParam::new(cookie.name.clone(), cookie.value.clone());
+                    match &cookie.domain {
+                        Some(domain) => param.domain = Some(domain.clone()),
+                        None => param.url = Some(request.url.clone()),
+                    }
+                    param.path = cookie.path.clone();
+                    param.expires = cookie.expires.map(TimeSinceEpoch::new);
+                    param
+                })
+                .collect();
+
+            page.execute(SetCookiesParams::new(cookies))
+                .await
+                .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the plan's user agent, falling back to the configured or browser
+    /// default, along with the `Accept-Language` for an emulated locale
+    async fn override_user_agent(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        plan: &ViewportPlan,
+    ) -> Result<(), ScreenshotError> {
+        let user_agent = match self
+            .user_agent_for(request, plan)
+            .or(self.config.user_agent.as_deref())
+        {
+            Some(user_agent) => user_agent.to_string(),
+            None => page
+                .user_agent()
+                .await
+                .map_err(|e| ScreenshotError::PageError(e.to_string()))?,
+        };
+
+        let mut params = SetUserAgentOverrideParams::new(user_agent);
+        params.accept_language = self.emulation_for(request).locale;
+        page.set_user_agent(params)
+            .await
+            .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Deterministic rendering settings, if enabled for the request
+    fn deterministic_for(&self, request: &ScreenshotRequest) -> Option<&DeterministicOptions> {
+        request
+            .deterministic
+            .unwrap_or(self.config.deterministic.enabled)
+            .then_some(&self.config.deterministic)
+    }
 
-        let emulation_params = chromiumoxide::cdp::browser_protocol::emulation::SetDeviceMetricsOverrideParams::builder()
+    /// Emulation options from the config overridden by the request's
+    fn emulation_for(&self, request: &ScreenshotRequest) -> Emulation {
+        self.config.emulation.merged(&request.emulation)
+    }
+
+    /// Emulate a plan's viewport, orientation and touch support
+    ///
+    /// Touch emulation is only sent when it differs from the previous plan.
+    async fn emulate(
+        &self,
+        page: &Page,
+        plan: &ViewportPlan,
+        previous: Option<&ViewportPlan>,
+    ) -> Result<(), ScreenshotError> {
+        let viewport = &plan.viewport;
+        let device = &plan.device;
+
+        let mut emulation_params = SetDeviceMetricsOverrideParams::builder()
             .width(viewport.width)
             .height(viewport.height)
             .device_scale_factor(viewport.device_scale_factor)
-            .mobile(viewport.mobile)
+            .mobile(viewport.mobile);
+        if let Some(device) = &device {
+            emulation_params = emulation_params.screen_orientation(match device.orientation {
+                Orientation::Portrait => {
+                    ScreenOrientation::new(ScreenOrientationType::PortraitPrimary, 0)
+                }
+                Orientation::Landscape => {
+                    ScreenOrientation::new(ScreenOrientationType::LandscapePrimary, 90)
+                }
+            });
+        }
+        let emulation_params = emulation_params
             .build()
             .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
 
@@ -279,20 +862,283 @@ impl ScreenshotService {
             .await
             .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
 
-        // Navigate to URL (chromiumoxide handles this automatically during new_page)
-
-        // Wait for page load
-        if self.config.optimization.wait_for_network_idle {
-            page.wait_for_navigation()
+        let touch = |plan: &ViewportPlan| plan.device.as_ref().is_some_and(|device| device.touch);
+        if touch(plan) != previous.is_some_and(touch) {
+            page.execute(SetTouchEmulationEnabledParams::new(touch(plan)))
                 .await
                 .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
         }
 
+        Ok(())
+    }
+
+    /// Enable CDP Fetch interception to abort requests rejected by the
+    /// interceptor, add the request's extra headers and answer basic auth
+    /// challenges
+    async fn intercept_requests(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+    ) -> Result<Option<PageInterception>, ScreenshotError> {
+        let filtering = self.interceptor.is_enabled();
+        if !filtering && request.basic_auth.is_none() && request.headers.is_empty() {
+            return Ok(None);
+        }
+
+        let mut events = page
+            .event_listener::<EventRequestPaused>()
+            .await
+            .map_err(|e| ScreenshotError::ResourceBlockingError(e.to_string()))?;
+        let mut auth_events = page
+            .event_listener::<EventAuthRequired>()
+            .await
+            .map_err(|e| ScreenshotError::ResourceBlockingError(e.to_string()))?;
+
+        let enable = FetchEnableParams::builder()
+            .handle_auth_requests(request.basic_auth.is_some())
+            .build();
+        page.execute(enable)
+            .await
+            .map_err(|e| ScreenshotError::ResourceBlockingError(e.to_string()))?;
+
+        let blocked = Arc::new(AtomicUsize::new(0));
+        let interceptor = self.interceptor.clone();
+        let page = page.clone();
+        let counter = blocked.clone();
+        let document_url = request.url.clone();
+        let credentials = request.basic_auth.clone();
+        let extra_headers = request.headers.clone();
+
+        // Credentials and extra headers are only sent to the origin being
+        // captured, never to third parties the page loads from. Credentials
+        // are offered a few times at most, so rejected ones do not loop forever
+        let origin = url::Url::parse(&document_url)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_default();
+        let mut auth_attempts = 0;
+
+        let task = tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    Some(event) = events.next() => event,
+                    Some(challenge) = auth_events.next() => {
+                        let offer = credentials.as_ref().filter(|_| {
+                            challenge.auth_challenge.source != Some(AuthChallengeSource::Proxy)
+                                && challenge.auth_challenge.origin == origin
+                                && auth_attempts < MAX_AUTH_ATTEMPTS
+                        });
+                        if offer.is_some() {
+                            auth_attempts += 1;
+                        }
+                        answer_auth_challenge(&page, &challenge, offer).await;
+                        continue;
+                    }
+                    else => break,
+                };
+
+                // Never block the top-level document being captured; documents
+                // loaded into other frames are matched as subdocuments
+                let main_frame = event.frame_id.as_ref() == page.target_id().as_ref();
+                let resource_type = match event.resource_type {
+                    ResourceType::Document if main_frame => None,
+                    ResourceType::Document => Some("subdocument".to_string()),
+                    ref other => Some(other.as_ref().to_lowercase()),
+                };
+
+                let block = filtering
+                    && resource_type.is_some_and(|resource_type| {
+                        interceptor.should_block_from(
+                            &event.request.url,
+                            &resource_type,
+                            Some(&document_url),
+                        )
+                    });
+
+                let outcome = if block {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    page.execute(FailRequestParams::new(
+                        event.request_id.clone(),
+                        ErrorReason::BlockedByClient,
+                    ))
+                    .await
+                    .map(|_| ())
+                } else {
+                    let mut params = ContinueRequestParams::new(event.request_id.clone());
+                    let same_origin = url::Url::parse(&event.request.url)
+                        .is_ok_and(|url| url.origin().ascii_serialization() == origin);
+                    if same_origin && !extra_headers.is_empty() {
+                        let headers = merge_headers(event.request.headers.inner(), &extra_headers);
+                        params.headers = Some(
+                            headers
+                                .into_iter()
+                                .map(|(name, value)| HeaderEntry::new(name, value))
+                                .collect(),
+                        );
+                    }
+                    page.execute(params).await.map(|_| ())
+                };
+
+                if let Err(e) = outcome {
+                    warn!(
+                        "Failed to resolve intercepted request {}: {}",
+                        event.request.url, e
+                    );
+                }
+            }
+        });
+
+        Ok(Some(PageInterception { task, blocked }))
+    }
+
+    async fn capture_screenshot_with_timeout(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        plans: &[ViewportPlan],
+        browser_instance_id: usize,
+        start_time: Instant,
+        network: Option<&NetworkTracker>,
+    ) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
+        let capture_future = self.capture_viewports(
+            page,
+            request,
+            plans,
+            browser_instance_id,
+            start_time,
+            network,
+        );
+
+        match timeout(self.config.screenshot_timeout, capture_future).await {
+            Ok(result) => result,
+            Err(_) => Err(ScreenshotError::Timeout(self.config.screenshot_timeout)),
+        }
+    }
+
+    /// Capture the loaded page once per viewport plan
+    ///
+    /// Later viewports resize the page in place. The page is only reloaded
+    /// when the user agent changes, since sites sniff it during load.
+    async fn capture_viewports(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        plans: &[ViewportPlan],
+        browser_instance_id: usize,
+        start_time: Instant,
+        network: Option<&NetworkTracker>,
+    ) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
+        self.settle_page(page, request, network).await?;
+
+        let mut results = Vec::with_capacity(plans.len());
+        for (index, plan) in plans.iter().enumerate() {
+            if let Some(previous) = index.checked_sub(1).map(|previous| &plans[previous]) {
+                self.emulate(page, plan, Some(previous)).await?;
+
+                if self.user_agent_for(request, plan) != self.user_agent_for(request, previous) {
+                    self.override_user_agent(page, request, plan).await?;
+
+                    debug!("Reloading {} for viewport {:?}", request.url, plan.label);
+                    self.navigate(page, &request.url).await?;
+                    self.settle_page(page, request, network).await?;
+                } else {
+                    wait_for_layout(page).await?;
+                }
+            }
+
+            if let Some(deterministic) = self.deterministic_for(request) {
+                deterministic.freeze(page).await?;
+            }
+
+            let captures = self
+                .capture_screenshot(page, request, plan, browser_instance_id, start_time)
+                .await?;
+            for mut result in captures {
+                result.metadata.viewport_label = plan.label.clone();
+                results.push(result);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Wait conditions, scripted actions and the fixed wait, in that order
+    async fn settle_page(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        network: Option<&NetworkTracker>,
+    ) -> Result<(), ScreenshotError> {
+        // Wait until the page is ready
+        for condition in self.wait_conditions(request) {
+            debug!("Waiting for {} on {}", condition.describe(), request.url);
+            condition.wait(page, network).await?;
+        }
+
+        // Injected styles and scripts, including hidden and redacted elements
+        let injection = self.injection_for(request);
+        if !injection.is_empty() {
+            injection.apply(page).await?;
+        }
+
+        // Scripted interactions such as dismissing banners or logging in
+        if !request.actions.is_empty() {
+            debug!(
+                "Running {} actions on {}",
+                request.actions.len(),
+                request.url
+            );
+            run_actions(page, &request.actions).await?;
+        }
+
         // Additional wait time if specified
         if let Some(wait_time) = request.wait_time {
             sleep(wait_time).await;
         }
 
+        Ok(())
+    }
+
+    /// Styles, scripts and selectors from the config followed by the request's
+    fn injection_for(&self, request: &ScreenshotRequest) -> PageInjection {
+        self.config.injection.merged(&request.injection)
+    }
+
+    /// Boxes to obscure or report for the captured image, or `None` if there
+    /// are none
+    async fn capture_marks(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+    ) -> Result<Option<CaptureMarks>, ScreenshotError> {
+        let injection = self.injection_for(request);
+        let redacted = injection.redaction_boxes(page).await?;
+
+        let ignore_selectors = [
+            self.config.diff.ignore_selectors.as_slice(),
+            request.ignore_selectors.as_slice(),
+        ]
+        .concat();
+        let ignored = selector_boxes(page, &ignore_selectors).await?;
+
+        Ok(
+            (!redacted.is_empty() || !ignored.is_empty()).then(|| CaptureMarks {
+                redacted,
+                style: injection.redaction_style(),
+                ignored,
+            }),
+        )
+    }
+
+    /// Capture the settled page, producing one result per captured element
+    /// (or none when missing elements are skipped)
+    async fn capture_screenshot(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        plan: &ViewportPlan,
+        browser_instance_id: usize,
+        start_time: Instant,
+    ) -> Result<Vec<ScreenshotResult>, ScreenshotError> {
         // Get page information
         let page_title = page.get_title().await.unwrap_or_default();
         let final_url = page
@@ -300,112 +1146,412 @@ impl ScreenshotService {
             .await
             .unwrap_or_else(|_| Some(request.url.clone()));
 
-        // Take screenshot
-        let screenshot_data = if let Some(selector) = &request.element_selector {
-            self.screenshot_element(page, selector).await?
+        // Take screenshot, or print the page when PDF output is requested
+        let format = request.output_format(&self.config.output_format);
+        let encoder = request.encoder.as_ref().unwrap_or(&self.config.encoder);
+
+        // Redacted and ignored boxes are measured right before capture; tiled
+        // full pages measure them again once lazy content has loaded
+        let marks = if format == OutputFormat::Pdf {
+            None
+        } else {
+            self.capture_marks(page, request).await?
+        };
+        let marks = marks.as_ref();
+
+        // Element captures may yield several images, tagged with their match index
+        let captures = if format == OutputFormat::Pdf {
+            let options = request.pdf.as_ref().unwrap_or(&self.config.pdf);
+            vec![(None, self.print_pdf(page, options).await?.into())]
+        } else if let Some(clip) = request.clip {
+            clip.validate()?;
+            vec![(
+                None,
+                self.screenshot_clip(page, clip, &format, encoder, marks)
+                    .await?,
+            )]
+        } else if let Some(element) = request.element_capture() {
+            self.screenshot_elements(page, request, &element, &format, encoder, marks)
+                .await?
         } else if request.full_page {
-            self.screenshot_full_page(page).await?
+            vec![(
+                None,
+                self.screenshot_full_page(page, request, &format, encoder, marks)
+                    .await?,
+            )]
         } else {
-            self.screenshot_viewport(page).await?
+            vec![(
+                None,
+                self.screenshot_viewport(page, &format, encoder, marks)
+                    .await?,
+            )]
         };
 
         let duration = start_time.elapsed();
 
-        Ok(ScreenshotResult {
-            request_id: request.id.clone(),
-            url: request.url.clone(),
-            data: screenshot_data.clone(),
-            format: self.config.output_format.clone(),
-            timestamp: SystemTime::now(),
-            duration,
-            success: true,
-            error: None,
-            metadata: ScreenshotMetadata {
-                viewport: viewport.clone(),
-                page_title,
-                final_url,
-                response_status: None, // chromiumoxide doesn't expose response status easily
-                file_size: screenshot_data.len(),
-                browser_instance_id,
-            },
-        })
+        Ok(captures
+            .into_iter()
+            .map(|(element_index, capture)| ScreenshotResult {
+                request_id: request.id.clone(),
+                url: request.url.clone(),
+                format: format.clone(),
+                timestamp: SystemTime::now(),
+                duration,
+                success: true,
+                error: None,
+                metadata: ScreenshotMetadata {
+                    viewport: plan.viewport.clone(),
+                    viewport_label: None,
+                    element_index,
+                    page_title: page_title.clone(),
+                    final_url: final_url.clone(),
+                    // Filled in from the document response by load_and_capture
+                    response_status: None,
+                    redirect_chain: Vec::new(),
+                    response_headers: HashMap::new(),
+                    mime_type: None,
+                    file_size: capture.data.len(),
+                    encoding: capture.encoding,
+                    browser_instance_id,
+                    blocked_requests: 0,
+                    ignore_regions: capture.ignore_regions,
+                },
+                data: capture.data,
+            })
+            .collect())
     }
 
-    async fn screenshot_viewport(&self, page: &Page) -> Result<Vec<u8>, ScreenshotError> {
-        let screenshot_params = ScreenshotParams::builder()
-            .format(chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat::Png)
-            .build();
+    async fn print_pdf(
+        &self,
+        page: &Page,
+        options: &PdfOptions,
+    ) -> Result<Vec<u8>, ScreenshotError> {
+        let (paper_width, paper_height) = options.paper_size.dimensions();
 
-        let png_data = page
-            .screenshot(screenshot_params)
-            .await
-            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+        let params = PrintToPdfParams {
+            landscape: Some(options.landscape),
+            display_header_footer: Some(options.has_header_footer()),
+            print_background: Some(options.print_background),
+            scale: Some(options.scale),
+            paper_width: Some(paper_width),
+            paper_height: Some(paper_height),
+            margin_top: Some(options.margins.top),
+            margin_bottom: Some(options.margins.bottom),
+            margin_left: Some(options.margins.left),
+            margin_right: Some(options.margins.right),
+            page_ranges: options.page_ranges.clone(),
+            // Chrome falls back to its default header/footer when only one is set
+            header_template: options
+                .has_header_footer()
+                .then(|| options.header_template.clone().unwrap_or_default()),
+            footer_template: options
+                .has_header_footer()
+                .then(|| options.footer_template.clone().unwrap_or_default()),
+            prefer_css_page_size: Some(options.prefer_css_page_size),
+            ..Default::default()
+        };
 
-        self.convert_image_format(png_data).await
+        page.pdf(params)
+            .await
+            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))
     }
 
-    async fn screenshot_full_page(&self, page: &Page) -> Result<Vec<u8>, ScreenshotError> {
-        let screenshot_params = ScreenshotParams::builder()
-            .format(chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat::Png)
-            .full_page(true)
-            .build();
+    async fn screenshot_viewport(
+        &self,
+        page: &Page,
+        format: &OutputFormat,
+        encoder: &EncoderSettings,
+        marks: Option<&CaptureMarks>,
+    ) -> Result<Capture, ScreenshotError> {
+        let marks = match marks {
+            Some(marks) => Some((marks, viewport_region(page).await?)),
+            None => None,
+        };
 
-        let png_data = page
-            .screenshot(screenshot_params)
+        self.capture_image(page, ScreenshotParams::builder(), format, encoder, marks)
             .await
-            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
-
-        self.convert_image_format(png_data).await
     }
 
-    async fn screenshot_element(
+    async fn screenshot_full_page(
         &self,
         page: &Page,
-        selector: &str,
-    ) -> Result<Vec<u8>, ScreenshotError> {
-        let element = page
-            .find_element(selector)
-            .await
-            .map_err(|e| ScreenshotError::ElementNotFound(e.to_string()))?;
+        request: &ScreenshotRequest,
+        format: &OutputFormat,
+        encoder: &EncoderSettings,
+        marks: Option<&CaptureMarks>,
+    ) -> Result<Capture, ScreenshotError> {
+        let options = request
+            .full_page_options
+            .as_ref()
+            .unwrap_or(&self.config.full_page);
+        if options.tiled {
+            load_lazy_content(page, options).await?;
+            // Lazy content shifts the layout below it, so boxes measured
+            // before the scroll pass are stale
+            let marks = match marks {
+                Some(_) => self.capture_marks(page, request).await?,
+                None => None,
+            };
+            let (image, region) = capture_tiled(page, options).await?;
 
-        let png_data = element
-            .screenshot(chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat::Png)
+            let format = format.clone();
+            let encoder = encoder.clone();
+            return tokio::task::spawn_blocking(move || {
+                let mut image = image.to_rgba8();
+                let ignore_regions = match marks {
+                    Some(marks) => marks.apply(&mut image, region),
+                    None => Vec::new(),
+                };
+                Ok(Capture {
+                    data: encode_image(&DynamicImage::ImageRgba8(image), &format, &encoder)?,
+                    encoding: local_encoding(&format, &encoder),
+                    ignore_regions,
+                })
+            })
             .await
             .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+        }
+
+        let marks = match marks {
+            Some(marks) => {
+                let content = page
+                    .layout_metrics()
+                    .await
+                    .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?
+                    .css_content_size;
+                let region = Clip {
+                    x: 0.0,
+                    y: 0.0,
+                    width: content.width,
+                    height: content.height,
+                };
+                Some((marks, region))
+            }
+            None => None,
+        };
 
-        self.convert_image_format(png_data).await
+        self.capture_image(
+            page,
+            ScreenshotParams::builder().full_page(true),
+            format,
+            encoder,
+            marks,
+        )
+        .await
     }
 
-    async fn convert_image_format(&self, png_data: Vec<u8>) -> Result<Vec<u8>, ScreenshotError> {
-        match self.config.output_format {
-            OutputFormat::Png => Ok(png_data),
-            OutputFormat::Jpeg => {
-                let img = image::load_from_memory(&png_data)
-                    .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+    async fn screenshot_clip(
+        &self,
+        page: &Page,
+        clip: Clip,
+        format: &OutputFormat,
+        encoder: &EncoderSettings,
+        marks: Option<&CaptureMarks>,
+    ) -> Result<Capture, ScreenshotError> {
+        // Clips are document-relative and may extend below the fold
+        self.capture_image(
+            page,
+            ScreenshotParams::builder()
+                .clip(clip)
+                .capture_beyond_viewport(true),
+            format,
+            encoder,
+            marks.map(|marks| (marks, clip)),
+        )
+        .await
+    }
 
-                let mut jpeg_data = Vec::new();
-                img.write_to(
-                    &mut std::io::Cursor::new(&mut jpeg_data),
-                    image::ImageFormat::Jpeg,
-                )
-                .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+    /// Capture the elements matched by `element`, tagging each capture with its
+    /// match index when every match is returned separately
+    async fn screenshot_elements(
+        &self,
+        page: &Page,
+        request: &ScreenshotRequest,
+        element: &ElementCapture,
+        format: &OutputFormat,
+        encoder: &EncoderSettings,
+        marks: Option<&CaptureMarks>,
+    ) -> Result<Vec<(Option<usize>, Capture)>, ScreenshotError> {
+        let count = match element.matches {
+            ElementMatch::First => element.count(page).await?.min(1),
+            ElementMatch::All | ElementMatch::ContactSheet => element.count(page).await?,
+        };
+
+        if count == 0 {
+            return match element.not_found {
+                NotFoundBehavior::Error => {
+                    Err(ScreenshotError::ElementNotFound(element.selector.clone()))
+                }
+                NotFoundBehavior::Skip => {
+                    debug!("No element matches '{}', skipping", element.selector);
+                    Ok(Vec::new())
+                }
+                NotFoundBehavior::FullPage => {
+                    debug!(
+                        "No element matches '{}', capturing the full page",
+                        element.selector
+                    );
+                    let capture = self
+                        .screenshot_full_page(page, request, format, encoder, marks)
+                        .await?;
+                    Ok(vec![(None, capture)])
+                }
+            };
+        }
+
+        let mut clips = Vec::with_capacity(count);
+        for index in 0..count {
+            // Scrolling an element into view can move or remove the others
+            let clip = element
+                .clip(page, index)
+                .await?
+                .ok_or_else(|| ScreenshotError::ElementNotFound(element.selector.clone()))?;
+            clips.push(clip);
+        }
+
+        match element.matches {
+            ElementMatch::First => Ok(vec![(
+                None,
+                self.screenshot_clip(page, clips[0], format, encoder, marks)
+                    .await?,
+            )]),
+            ElementMatch::All => {
+                let mut captures = Vec::with_capacity(clips.len());
+                for (index, clip) in clips.into_iter().enumerate() {
+                    let capture = self
+                        .screenshot_clip(page, clip, format, encoder, marks)
+                        .await?;
+                    captures.push((Some(index), capture));
+                }
+                Ok(captures)
+            }
+            ElementMatch::ContactSheet => {
+                let mut images = Vec::with_capacity(clips.len());
+                let mut regions = Vec::with_capacity(clips.len());
+                for clip in clips {
+                    let capture = self
+                        .screenshot_clip(
+                            page,
+                            clip,
+                            &OutputFormat::Png,
+                            &EncoderSettings::default(),
+                            marks,
+                        )
+                        .await?;
+                    images.push(
+                        image::load_from_memory(&capture.data)
+                            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?,
+                    );
+                    regions.push(capture.ignore_regions);
+                }
 
-                Ok(jpeg_data)
+                // Layout and encoding are CPU bound; keep them off the async executor
+                let format = format.clone();
+                let encoder = encoder.clone();
+                let capture = tokio::task::spawn_blocking(move || {
+                    let data = encode_image(&contact_sheet(&images)?, &format, &encoder)?;
+                    // Each capture's ignored boxes move with it onto the sheet
+                    let ignore_regions = contact_sheet_cells(&images)
+                        .into_iter()
+                        .zip(regions)
+                        .flat_map(|((x, y), regions)| {
+                            regions.into_iter().map(move |region| Clip {
+                                x: region.x + f64::from(x),
+                                y: region.y + f64::from(y),
+                                ..region
+                            })
+                        })
+                        .collect();
+                    Ok::<_, ScreenshotError>(Capture {
+                        data,
+                        encoding: local_encoding(&format, &encoder),
+                        ignore_regions,
+                    })
+                })
+                .await
+                .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))??;
+                Ok(vec![(None, capture)])
             }
-            OutputFormat::Webp => {
-                let img = image::load_from_memory(&png_data)
-                    .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
-
-                let mut webp_data = Vec::new();
-                img.write_to(
-                    &mut std::io::Cursor::new(&mut webp_data),
-                    image::ImageFormat::WebP,
-                )
-                .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
-
-                Ok(webp_data)
+        }
+    }
+
+    /// Chrome-side encoding for a capture, or `None` when the PNG capture must be
+    /// transcoded locally
+    ///
+    /// Chrome encodes JPEG and lossy WebP itself, which avoids a decode/re-encode
+    /// round trip. Lossless WebP and re-compressed PNG still need the local encoder.
+    fn native_capture_format(
+        format: &OutputFormat,
+        encoder: &EncoderSettings,
+    ) -> Option<(CaptureScreenshotFormat, Option<i64>)> {
+        match format {
+            OutputFormat::Png if !encoder.reencodes_png() => {
+                Some((CaptureScreenshotFormat::Png, None))
             }
+            OutputFormat::Jpeg => Some((
+                CaptureScreenshotFormat::Jpeg,
+                Some(i64::from(encoder.jpeg_quality)),
+            )),
+            OutputFormat::Webp if !encoder.webp_lossless => Some((
+                CaptureScreenshotFormat::Webp,
+                Some(i64::from(encoder.webp_quality)),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Capture an image, obscuring redacted boxes and locating ignored ones
+    /// within the document `region` the capture shows
+    async fn capture_image(
+        &self,
+        page: &Page,
+        params: ScreenshotParamsBuilder,
+        format: &OutputFormat,
+        encoder: &EncoderSettings,
+        marks: Option<(&CaptureMarks, Clip)>,
+    ) -> Result<Capture, ScreenshotError> {
+        // Marks are applied to the pixels, so Chrome must hand over a lossless PNG
+        let native = Self::native_capture_format(format, encoder).filter(|_| marks.is_none());
+        let transcode = native.is_none();
+        let (capture_format, quality) = native.unwrap_or((CaptureScreenshotFormat::Png, None));
+
+        let mut params = params.format(capture_format);
+        if let Some(quality) = quality {
+            params = params.quality(quality);
         }
+
+        let data = page
+            .screenshot(params.build())
+            .await
+            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+
+        if !transcode {
+            return Ok(Capture::new(data, encoder.encoding(format, false)));
+        }
+
+        // Image encoding is CPU bound; keep it off the async executor
+        let format = format.clone();
+        let encoder = encoder.clone();
+        let marks = marks.map(|(marks, region)| (marks.clone(), region));
+        tokio::task::spawn_blocking(move || match marks {
+            Some((marks, region)) => {
+                let mut image = image::load_from_memory(&data)
+                    .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?
+                    .to_rgba8();
+                let ignore_regions = marks.apply(&mut image, region);
+                Ok(Capture {
+                    data: encode_image(&DynamicImage::ImageRgba8(image), &format, &encoder)?,
+                    encoding: local_encoding(&format, &encoder),
+                    ignore_regions,
+                })
+            }
+            None => {
+                let data = transcode_png(&data, &format, &encoder)?;
+                Ok(Capture::new(data, local_encoding(&format, &encoder)))
+            }
+        })
+        .await
+        .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?
     }
 
     fn is_valid_url(&self, url: &str) -> bool {
@@ -458,6 +1604,10 @@ impl Clone for ScreenshotService {
             circuit_breaker: self.circuit_breaker.clone(),
             concurrency_limiter: self.concurrency_limiter.clone(),
             retry_config: self.retry_config.clone(),
+            metrics: self.metrics.clone(),
+            active_requests: self.active_requests.clone(),
+            interceptor: self.interceptor.clone(),
+            devices: self.devices.clone(),
         }
     }
 }
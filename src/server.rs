@@ -0,0 +1,406 @@
+//! HTTP API server for running the screenshot tool as a shared service
+//!
+//! This module exposes the `ScreenshotService` over HTTP so that a single,
+//! warm browser pool can serve screenshot requests from many clients.
+
+use crate::utils::validate_url;
+use crate::{
+    ElementMatch, HealthEndpoint, HealthMonitor, OutputFormat, Redirect, ScreenshotError,
+    ScreenshotRequest, ScreenshotResult, ScreenshotService,
+};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use base64::Engine;
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Options controlling which endpoints the API server exposes
+#[derive(Clone)]
+pub struct ServerOptions {
+    /// Address the server listens on
+    pub addr: SocketAddr,
+    /// Recorder handle backing the `/metrics` endpoint; `None` disables it
+    pub metrics: Option<PrometheusHandle>,
+    /// Expose the `/health` endpoint
+    pub health: bool,
+    /// Maximum number of requests accepted by the batch endpoint
+    pub max_batch_size: usize,
+}
+
+/// Shared state handed to every request handler
+#[derive(Clone)]
+struct AppState {
+    service: Arc<ScreenshotService>,
+    health: Option<Arc<HealthEndpoint>>,
+    metrics: Option<PrometheusHandle>,
+    max_batch_size: usize,
+}
+
+/// Body accepted by `POST /screenshots/batch`
+#[derive(Debug, Deserialize)]
+pub struct BatchRequest {
+    pub requests: Vec<ScreenshotRequest>,
+}
+
+/// Per-request entry returned by `POST /screenshots/batch`
+///
+/// Output bytes are base64 encoded so that the whole batch fits in one JSON document.
+#[derive(Debug, Serialize)]
+pub struct BatchItemResponse {
+    pub request_id: String,
+    pub url: String,
+    pub viewport_label: Option<String>,
+    pub element_index: Option<usize>,
+    pub success: bool,
+    pub format: OutputFormat,
+    pub content_type: &'static str,
+    pub duration_ms: u128,
+    pub page_title: Option<String>,
+    pub final_url: Option<String>,
+    pub response_status: Option<u16>,
+    pub redirect_chain: Vec<Redirect>,
+    pub file_size: usize,
+    pub error: Option<String>,
+    pub data: Option<String>,
+}
+
+impl From<ScreenshotResult> for BatchItemResponse {
+    fn from(result: ScreenshotResult) -> Self {
+        let data = if result.success {
+            Some(base64::engine::general_purpose::STANDARD.encode(&result.data))
+        } else {
+            None
+        };
+
+        Self {
+            request_id: result.request_id,
+            url: result.url,
+            viewport_label: result.metadata.viewport_label,
+            element_index: result.metadata.element_index,
+            success: result.success,
+            content_type: content_type(&result.format),
+            format: result.format,
+            duration_ms: result.duration.as_millis(),
+            page_title: result.metadata.page_title,
+            final_url: result.metadata.final_url,
+            response_status: result.metadata.response_status,
+            redirect_chain: result.metadata.redirect_chain,
+            file_size: result.metadata.file_size,
+            error: result.error.map(|e| e.to_string()),
+            data,
+        }
+    }
+}
+
+/// Response body returned by `POST /screenshots/batch`
+#[derive(Debug, Serialize)]
+pub struct BatchResponse {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchItemResponse>,
+}
+
+/// Error wrapper translating `ScreenshotError` into HTTP responses
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl From<ScreenshotError> for ApiError {
+    fn from(err: ScreenshotError) -> Self {
+        Self {
+            status: status_for_error(&err),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({
+            "error": self.message,
+            "status": self.status.as_u16(),
+        }));
+
+        (self.status, body).into_response()
+    }
+}
+
+/// Map a screenshot error onto the most appropriate HTTP status code
+pub fn status_for_error(err: &ScreenshotError) -> StatusCode {
+    match err {
+        ScreenshotError::InvalidUrl(_)
+        | ScreenshotError::ConfigurationError(_)
+        | ScreenshotError::SerializationError(_) => StatusCode::BAD_REQUEST,
+        ScreenshotError::ElementNotFound(_)
+        | ScreenshotError::WaitConditionFailed(_, _)
+        | ScreenshotError::ActionFailed(_, _, _) => StatusCode::UNPROCESSABLE_ENTITY,
+        ScreenshotError::Timeout(_) | ScreenshotError::WaitTimeout(_, _) => {
+            StatusCode::GATEWAY_TIMEOUT
+        }
+        ScreenshotError::BrowserUnavailable | ScreenshotError::MemoryLimitExceeded => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        ScreenshotError::TooManySessions(_) => StatusCode::TOO_MANY_REQUESTS,
+        ScreenshotError::UrlLoadFailed(_)
+        | ScreenshotError::NetworkError(_)
+        | ScreenshotError::UnacceptedStatus(_, _) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Check that a request URL can be captured on behalf of a network client
+///
+/// Only http and https are accepted; `file://`, `chrome://` or `view-source:`
+/// URLs would expose local files and browser internals to the caller.
+pub fn check_request_url(url: &str) -> Result<(), String> {
+    if url.is_empty() {
+        return Err("Missing required field: url".to_string());
+    }
+    validate_url(url)
+        .map(|_| ())
+        .map_err(|_| format!("Only http and https URLs can be captured: {url}"))
+}
+
+/// MIME type for a screenshot output format
+pub fn content_type(format: &OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Png => "image/png",
+        OutputFormat::Jpeg => "image/jpeg",
+        OutputFormat::Webp => "image/webp",
+        OutputFormat::Pdf => "application/pdf",
+    }
+}
+
+/// HTTP server wrapping a shared `ScreenshotService`
+pub struct ApiServer {
+    service: Arc<ScreenshotService>,
+    options: ServerOptions,
+}
+
+impl ApiServer {
+    pub fn new(service: Arc<ScreenshotService>, options: ServerOptions) -> Self {
+        Self { service, options }
+    }
+
+    pub fn router(&self) -> Router {
+        let health = if self.options.health {
+            let monitor = HealthMonitor::new(
+                self.service.browser_pool.clone(),
+                self.service.clone(),
+                self.service.metrics(),
+            );
+            Some(Arc::new(HealthEndpoint::new(monitor)))
+        } else {
+            None
+        };
+
+        let state = AppState {
+            service: self.service.clone(),
+            health,
+            metrics: self.options.metrics.clone(),
+            max_batch_size: self.options.max_batch_size,
+        };
+
+        let mut router = Router::new()
+            .route("/screenshot", post(screenshot_handler))
+            .route("/screenshots/batch", post(batch_handler))
+            .route("/sessions/:name", delete(close_session_handler));
+
+        if state.health.is_some() {
+            router = router.route("/health", get(health_handler));
+        }
+
+        if state.metrics.is_some() {
+            router = router.route("/metrics", get(metrics_handler));
+        }
+
+        router.with_state(state)
+    }
+
+    pub async fn run(&self) -> Result<(), ScreenshotError> {
+        let router = self.router();
+
+        info!("API server listening on http://{}", self.options.addr);
+        if self.options.metrics.is_some() {
+            info!("Metrics endpoint enabled at /metrics");
+        }
+        if self.options.health {
+            info!("Health endpoint enabled at /health");
+        }
+
+        axum::Server::try_bind(&self.options.addr)
+            .map_err(|e| ScreenshotError::IoError(e.to_string()))?
+            .serve(router.into_make_service())
+            .await
+            .map_err(|e| ScreenshotError::IoError(e.to_string()))
+    }
+}
+
+async fn screenshot_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ScreenshotRequest>,
+) -> Result<Response, ApiError> {
+    check_request_url(&request.url).map_err(ApiError::bad_request)?;
+    if request.viewports.len() > 1 {
+        return Err(ApiError::bad_request(
+            "Multi-viewport requests return several images; use /screenshots/batch",
+        ));
+    }
+    if matches!(&request.element, Some(element) if element.matches == ElementMatch::All) {
+        return Err(ApiError::bad_request(
+            "Capturing every matching element returns several images; use /screenshots/batch",
+        ));
+    }
+
+    // Elements skipped with `"not_found": "skip"` produce no image
+    let results = state.service.process_requests(vec![request]).await?;
+    let Some(result) = results.into_iter().next() else {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    };
+
+    if !result.success {
+        let err = result
+            .error
+            .unwrap_or_else(|| ScreenshotError::CaptureFailed("Unknown error".to_string()));
+        warn!("Screenshot of {} failed: {}", result.url, err);
+        return Err(err.into());
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(content_type(&result.format)),
+    );
+    if let Ok(value) = HeaderValue::from_str(&result.request_id) {
+        headers.insert("x-request-id", value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&result.duration.as_millis().to_string()) {
+        headers.insert("x-screenshot-duration-ms", value);
+    }
+    if let Some(final_url) = &result.metadata.final_url {
+        if let Ok(value) = HeaderValue::from_str(final_url) {
+            headers.insert("x-final-url", value);
+        }
+    }
+    if let Some(status) = result.metadata.response_status {
+        headers.insert("x-response-status", HeaderValue::from(status));
+    }
+
+    Ok((StatusCode::OK, headers, result.data).into_response())
+}
+
+async fn batch_handler(
+    State(state): State<AppState>,
+    Json(batch): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    if batch.requests.is_empty() {
+        return Err(ApiError::bad_request(
+            "Batch must contain at least one request",
+        ));
+    }
+
+    if batch.requests.len() > state.max_batch_size {
+        return Err(ApiError::bad_request(format!(
+            "Batch size {} exceeds maximum of {}",
+            batch.requests.len(),
+            state.max_batch_size
+        )));
+    }
+
+    for request in &batch.requests {
+        check_request_url(&request.url)
+            .map_err(|e| ApiError::bad_request(format!("Request {}: {}", request.id, e)))?;
+    }
+
+    let results = state.service.process_requests(batch.requests).await?;
+    let succeeded = results.iter().filter(|r| r.success).count();
+    let total = results.len();
+
+    Ok(Json(BatchResponse {
+        total,
+        succeeded,
+        failed: total - succeeded,
+        results: results.into_iter().map(BatchItemResponse::from).collect(),
+    }))
+}
+
+async fn close_session_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> StatusCode {
+    if state.service.close_session(&name).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn health_handler(State(state): State<AppState>) -> Response {
+    let Some(endpoint) = &state.health else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let status = endpoint.get_health_status().await;
+    let pool = state.service.browser_pool.get_stats().await;
+
+    let code = if status.overall == crate::HealthLevel::Critical {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    let body = serde_json::json!({
+        "status": format!("{:?}", status.overall),
+        "performance": format!("{:?}", status.performance),
+        "resources": format!("{:?}", status.resources),
+        "errors": format!("{:?}", status.errors),
+        "browser_pool": {
+            "total_instances": pool.total_instances,
+            "healthy_instances": pool.healthy_instances,
+            "busy_instances": pool.busy_instances,
+            "failed_instances": pool.failed_instances,
+            "available_instances": pool.available_instances,
+            "total_screenshots": pool.total_screenshots,
+            "active_tabs": pool.active_tabs,
+            "tab_capacity": pool.tab_capacity,
+        },
+    });
+
+    (code, Json(body)).into_response()
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> Response {
+    match &state.metrics {
+        Some(handle) => (
+            [(
+                header::CONTENT_TYPE,
+                "text/plain; version=0.0.4; charset=utf-8",
+            )],
+            handle.render(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
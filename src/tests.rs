@@ -10,6 +10,7 @@ mod integration_tests {
         let config = Config::default();
         assert_eq!(config.browser_pool_size, 10);
         assert_eq!(config.max_concurrent_screenshots, 200);
+        assert_eq!(config.max_tabs_per_browser, 4);
         assert_eq!(config.screenshot_timeout, Duration::from_secs(30));
         assert_eq!(config.retry_attempts, 3);
         assert!(matches!(config.output_format, OutputFormat::Png));
@@ -95,6 +96,37 @@ mod integration_tests {
         ));
     }
 
+    #[test]
+    fn test_error_category() {
+        use crate::ScreenshotError;
+
+        assert_eq!(
+            ScreenshotError::NetworkError("test".to_string()).category(),
+            "network"
+        );
+        assert_eq!(
+            ScreenshotError::Timeout(Duration::from_secs(1)).category(),
+            "timeout"
+        );
+        assert_eq!(ScreenshotError::BrowserUnavailable.category(), "browser");
+        assert_eq!(
+            ScreenshotError::ElementNotFound("test".to_string()).category(),
+            "capture"
+        );
+    }
+
+    #[test]
+    fn test_metrics_without_recorder() {
+        use crate::Metrics;
+
+        // Without an installed recorder all handles are no-ops
+        let metrics = Metrics::new();
+        metrics.record_screenshot(Duration::from_millis(250), true);
+        metrics.record_error("timeout");
+        metrics.record_browser_usage(0, 0);
+        metrics.set_queue_size(3);
+    }
+
     #[test]
     fn test_circuit_breaker() {
         use crate::CircuitBreaker;
@@ -244,13 +276,237 @@ mod integration_tests {
         assert!(interceptor.should_block("https://googletagmanager.com/script.js", "script"));
         assert!(interceptor.should_block("https://googlesyndication.com/ad.js", "script"));
 
-        // Should block tracker patterns
-        assert!(interceptor.should_block("https://example.com/analytics.js", "script"));
-        assert!(interceptor.should_block("https://example.com/tracking/pixel.gif", "image"));
+        // Should block tracker hosts and subdomains of blocked domains
+        assert!(interceptor.should_block("https://analytics.example.com/collect.js", "script"));
+        assert!(interceptor.should_block("https://tracking.example.net/pixel.gif", "image"));
+        assert!(interceptor.should_block("https://www.google-analytics.com/g/collect", "ping"));
+        assert!(interceptor.should_block("https://www.facebook.com/tr?id=1", "image"));
 
         // Should not block regular content
         assert!(!interceptor.should_block("https://example.com/main.js", "script"));
         assert!(!interceptor.should_block("https://example.com/style.css", "stylesheet"));
+
+        // Tracker names in first-party paths and longer host labels are not trackers
+        assert!(!interceptor.should_block("https://example.com/api/metrics/daily", "xhr"));
+        assert!(!interceptor.should_block("https://example.com/analytics.js", "script"));
+        assert!(!interceptor.should_block("https://cdn.segment-images.com/hero.jpg", "image"));
+        assert!(!interceptor.should_block("https://www.facebook.com/trending", "document"));
+    }
+
+    #[test]
+    fn test_request_interceptor_from_settings() {
+        use crate::{OptimizationSettings, RequestInterceptor};
+
+        let interceptor =
+            RequestInterceptor::from_settings(&OptimizationSettings::default()).unwrap();
+        assert!(interceptor.is_enabled());
+        assert!(interceptor.should_block("https://doubleclick.net/ad.js", "script"));
+
+        let interceptor = RequestInterceptor::from_settings(&OptimizationSettings {
+            block_ads: false,
+            block_trackers: false,
+            block_images: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(interceptor.is_enabled());
+        assert!(interceptor.should_block("https://example.com/logo.png", "image"));
+        assert!(!interceptor.should_block("https://example.com/analytics.js", "script"));
+
+        let interceptor = RequestInterceptor::from_settings(&OptimizationSettings {
+            block_ads: false,
+            block_trackers: false,
+            block_images: false,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!interceptor.is_enabled());
+
+        let missing = RequestInterceptor::from_settings(&OptimizationSettings {
+            filter_lists: vec!["/nonexistent/easylist.txt".into()],
+            ..Default::default()
+        });
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_filter_list_rules() {
+        use crate::{FilterRequest, FilterSet, FilterVerdict};
+
+        let filters = FilterSet::parse(
+            "[Adblock Plus 2.0]\n\
+             ! Title: test list\n\
+             ||ads.example.com^\n\
+             /banner/*/img^\n\
+             ||tracker.net^$third-party\n\
+             ||cdn.net/widget.js$script,domain=news.com\n\
+             @@||ads.example.com/allowed/*\n\
+             example.com##.ad-banner\n",
+        );
+        assert_eq!(filters.len(), 5);
+
+        let check = |url: &str, resource_type: &str, document: &str| {
+            let request = FilterRequest::new(url, resource_type, Some(document)).unwrap();
+            filters.check(&request)
+        };
+
+        // Domain anchors match the domain and its subdomains only
+        assert_eq!(
+            check("https://ads.example.com/a.js", "script", "https://site.com"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check(
+                "https://x.ads.example.com/a.js",
+                "script",
+                "https://site.com"
+            ),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check(
+                "https://badads.example.com/a.js",
+                "script",
+                "https://site.com"
+            ),
+            FilterVerdict::NoMatch
+        );
+
+        // Wildcards and separators
+        assert_eq!(
+            check(
+                "https://site.com/banner/top/img?x=1",
+                "image",
+                "https://site.com"
+            ),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check(
+                "https://site.com/banner/top/imgs",
+                "image",
+                "https://site.com"
+            ),
+            FilterVerdict::NoMatch
+        );
+
+        // $third-party only applies to cross-site requests
+        assert_eq!(
+            check("https://tracker.net/p.gif", "image", "https://site.com"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check(
+                "https://tracker.net/p.gif",
+                "image",
+                "https://www.tracker.net"
+            ),
+            FilterVerdict::NoMatch
+        );
+
+        // Resource type and domain options
+        assert_eq!(
+            check("https://cdn.net/widget.js", "script", "https://news.com"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check("https://cdn.net/widget.js", "image", "https://news.com"),
+            FilterVerdict::NoMatch
+        );
+        assert_eq!(
+            check("https://cdn.net/widget.js", "script", "https://other.com"),
+            FilterVerdict::NoMatch
+        );
+
+        // Exception rules win over blocking rules
+        assert_eq!(
+            check(
+                "https://ads.example.com/allowed/x.js",
+                "script",
+                "https://site.com"
+            ),
+            FilterVerdict::Allow
+        );
+
+        // Generic rules are looked up by a whole token of the URL
+        let filters = FilterSet::parse(
+            "/adserver^\n\
+             ||example.com\n\
+             -ad-*.gif\n\
+             *banner\n\
+             @@/adserver^$image\n",
+        );
+        let check = |url: &str, resource_type: &str| {
+            let request = FilterRequest::new(url, resource_type, None).unwrap();
+            filters.check(&request)
+        };
+
+        assert_eq!(
+            check("https://site.com/x/adserver/a.js", "script"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check("https://site.com/x/adservers/a.js", "script"),
+            FilterVerdict::NoMatch
+        );
+        assert_eq!(
+            check("https://site.com/x/adserver/a.png", "image"),
+            FilterVerdict::Allow
+        );
+        assert_eq!(
+            check("https://example.company.com/", "document"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check("https://site.com/top-ad-1.gif", "image"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check("https://site.com/topbanner.png", "image"),
+            FilterVerdict::Block
+        );
+        assert_eq!(
+            check("https://site.com/bad-add.gif", "image"),
+            FilterVerdict::NoMatch
+        );
+
+        // Many wildcards must not backtrack exponentially
+        let filters = FilterSet::parse(&format!("/{}b\n", "a*".repeat(30)));
+        let url = format!("https://site.com/{}", "a".repeat(4000));
+        let request = FilterRequest::new(&url, "script", None).unwrap();
+        assert_eq!(filters.check(&request), FilterVerdict::NoMatch);
+
+        // Unsupported rules are skipped rather than applied partially
+        let filters = FilterSet::parse(
+            "||ads.example.com^$popup\n\
+             /banner\\d+/\n\
+             ||tracker.net^\n",
+        );
+        assert_eq!(filters.len(), 1);
+    }
+
+    #[test]
+    fn test_request_interceptor_filter_exceptions() {
+        use crate::{FilterSet, RequestInterceptor};
+
+        let mut interceptor = RequestInterceptor::new();
+        interceptor.filters = FilterSet::parse("@@||analytics.example.com^\n");
+
+        // Exceptions override the built-in tracker heuristics
+        assert!(!interceptor.should_block("https://analytics.example.com/a.js", "script"));
+        assert!(interceptor.should_block("https://analytics.other.com/a.js", "script"));
+
+        // Social sites are no longer blocked outright
+        assert!(!interceptor.should_block("https://facebook.com/page", "document"));
+        assert!(!interceptor.should_block("https://twitter.com/home", "script"));
+
+        // Filter lists only apply while ad or tracker blocking is on
+        interceptor.filters = FilterSet::parse("||cdn.example.com^\n");
+        assert!(interceptor.should_block("https://cdn.example.com/lib.js", "script"));
+        interceptor.block_ads = false;
+        interceptor.block_trackers = false;
+        assert!(!interceptor.is_enabled());
+        assert!(!interceptor.should_block("https://cdn.example.com/lib.js", "script"));
     }
 
     #[test]
@@ -325,6 +581,869 @@ mod integration_tests {
         assert_eq!(config.multiplier, 2.0);
     }
 
+    #[test]
+    fn test_screenshot_request_from_json() {
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://example.com", "full_page": true, "priority": "High"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.url, "https://example.com");
+        assert!(request.full_page);
+        assert!(matches!(request.priority, Priority::High));
+        assert!(!request.id.is_empty());
+        assert!(request.custom_viewport.is_none());
+    }
+
+    #[test]
+    fn test_request_output_format() {
+        let mut request = ScreenshotRequest::default();
+        assert_eq!(request.output_format(&OutputFormat::Png), OutputFormat::Png);
+
+        request.format = Some(OutputFormat::Jpeg);
+        assert_eq!(
+            request.output_format(&OutputFormat::Png),
+            OutputFormat::Jpeg
+        );
+
+        request.format = None;
+        request.pdf = Some(crate::PdfOptions::default());
+        assert_eq!(request.output_format(&OutputFormat::Png), OutputFormat::Pdf);
+
+        assert_eq!("JPG".parse::<OutputFormat>().unwrap(), OutputFormat::Jpeg);
+        assert_eq!("webp".parse::<OutputFormat>().unwrap(), OutputFormat::Webp);
+        assert!("gif".parse::<OutputFormat>().is_err());
+
+        let request: ScreenshotRequest =
+            serde_json::from_str(r#"{"url": "https://example.com", "format": "webp"}"#).unwrap();
+        assert_eq!(request.format, Some(OutputFormat::Webp));
+    }
+
+    #[test]
+    fn test_request_http_settings() {
+        use crate::{BasicAuth, Cookie, HttpArgs};
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://staging.example.com",
+                "headers": {"X-Preview": "1"},
+                "cookies": [{"name": "session", "value": "abc", "path": "/", "expires": 1900000000}],
+                "basic_auth": {"username": "qa", "password": "secret"},
+                "user_agent": "ScreenshotBot/1.0"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            request.headers.get("X-Preview").map(String::as_str),
+            Some("1")
+        );
+        assert_eq!(request.cookies[0].domain, None);
+        assert_eq!(request.cookies[0].expires, Some(1_900_000_000.0));
+        assert_eq!(request.basic_auth.as_ref().unwrap().username, "qa");
+        assert!(!format!("{:?}", request.basic_auth).contains("secret"));
+
+        let cookie: Cookie = "theme = dark".parse().unwrap();
+        assert_eq!(
+            (cookie.name.as_str(), cookie.value.as_str()),
+            ("theme", "dark")
+        );
+        assert!("theme".parse::<Cookie>().is_err());
+
+        let auth: BasicAuth = "user:pa:ss".parse().unwrap();
+        assert_eq!(auth.password, "pa:ss");
+        assert!("user".parse::<BasicAuth>().is_err());
+
+        let args = HttpArgs {
+            headers: vec![("Accept-Language".to_string(), "de".to_string())],
+            cookies: vec![cookie],
+            basic_auth: Some(auth),
+            user_agent: Some("Bot".to_string()),
+            accept_statuses: None,
+        };
+        let mut request = ScreenshotRequest::default();
+        args.apply(&mut request);
+        assert_eq!(request.headers.len(), 1);
+        assert_eq!(request.cookies.len(), 1);
+        assert_eq!(request.user_agent.as_deref(), Some("Bot"));
+        assert!(request.basic_auth.is_some());
+
+        // Extra headers replace the browser's own regardless of case
+        let original = serde_json::json!({"accept-language": "en", "User-Agent": "Chrome"});
+        let mut merged = crate::merge_headers(&original, &request.headers);
+        merged.sort();
+        assert_eq!(
+            merged,
+            vec![
+                ("Accept-Language".to_string(), "de".to_string()),
+                ("User-Agent".to_string(), "Chrome".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_viewport_targets() {
+        use crate::{labelled_path, ViewportTarget};
+        use std::path::{Path, PathBuf};
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://example.com", "viewports": [
+                "iPhone 15",
+                {"label": "desktop", "width": 1440, "height": 900},
+                {"width": 768, "height": 1024, "device_scale_factor": 2.0}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.viewports.len(), 3);
+        assert_eq!(
+            request.viewports[0],
+            ViewportTarget::Device("iPhone 15".to_string())
+        );
+        let labels: Vec<String> = request
+            .viewports
+            .iter()
+            .map(ViewportTarget::label)
+            .collect();
+        assert_eq!(labels, vec!["iPhone 15", "desktop", "768x1024"]);
+        match &request.viewports[2] {
+            ViewportTarget::Custom { viewport, .. } => {
+                assert_eq!(viewport.device_scale_factor, 2.0);
+                assert!(!viewport.mobile);
+            }
+            other => panic!("expected custom viewport, got {other:?}"),
+        }
+
+        assert_eq!(
+            "1280x720".parse::<ViewportTarget>().unwrap().label(),
+            "1280x720"
+        );
+        assert_eq!(
+            "Pixel 7".parse::<ViewportTarget>().unwrap(),
+            ViewportTarget::Device("Pixel 7".to_string())
+        );
+        assert!("".parse::<ViewportTarget>().is_err());
+
+        assert_eq!(
+            labelled_path(Path::new("shots/home.png"), "iPhone 15"),
+            PathBuf::from("shots/home-iphone-15.png")
+        );
+        assert_eq!(
+            labelled_path(Path::new("home"), "1440x900"),
+            PathBuf::from("home-1440x900")
+        );
+    }
+
+    #[test]
+    fn test_device_catalog() {
+        use crate::{DeviceCatalog, Orientation};
+
+        let mut catalog = DeviceCatalog::builtin();
+        assert!(!catalog.is_empty());
+
+        let iphone = catalog.get("IPHONE 15").unwrap();
+        assert_eq!(iphone.name, "iPhone 15");
+        assert!(iphone.mobile && iphone.touch);
+        assert!(iphone.user_agent.as_deref().unwrap().contains("iPhone"));
+        let viewport = iphone.viewport();
+        assert_eq!((viewport.width, viewport.height), (393, 852));
+        assert_eq!(viewport.device_scale_factor, 3.0);
+
+        let rotated = catalog.get("iPhone 15 landscape").unwrap();
+        assert_eq!(rotated.orientation, Orientation::Landscape);
+        assert_eq!(rotated.viewport().width, 852);
+        assert_eq!(catalog.get("iPhone 15 portrait").unwrap(), iphone);
+
+        let desktop = catalog.get("Desktop 1080p").unwrap();
+        assert!(!desktop.mobile && desktop.user_agent.is_none());
+        assert!(catalog.get("Nokia 3310").is_none());
+
+        catalog
+            .extend_from_json(
+                r#"[{"name": "Kiosk", "width": 1080, "height": 1920, "touch": true},
+                    {"name": "iphone 15", "width": 400, "height": 800, "device_scale_factor": 2.0}]"#,
+            )
+            .unwrap();
+        let kiosk = catalog.get("kiosk").unwrap();
+        assert_eq!(kiosk.device_scale_factor, 1.0);
+        assert_eq!(kiosk.orientation, Orientation::Portrait);
+        assert_eq!(catalog.get("iPhone 15").unwrap().width, 400);
+
+        assert!(catalog
+            .extend_from_json(r#"[{"name": "Broken", "width": 0, "height": 100}]"#)
+            .is_err());
+        assert!(
+            DeviceCatalog::with_file(std::path::Path::new("/nonexistent/devices.json")).is_err()
+        );
+
+        let request: ScreenshotRequest =
+            serde_json::from_str(r#"{"url": "https://example.com", "device": "Pixel 7"}"#).unwrap();
+        assert_eq!(request.device.as_deref(), Some("Pixel 7"));
+    }
+
+    #[test]
+    fn test_accept_statuses() {
+        use crate::{status_for_error, AcceptStatuses, ScreenshotError};
+
+        let accept: AcceptStatuses = "2xx, 304, 400-404".parse().unwrap();
+        assert!(accept.accepts(200));
+        assert!(accept.accepts(299));
+        assert!(accept.accepts(304));
+        assert!(accept.accepts(404));
+        assert!(!accept.accepts(301));
+        assert!(!accept.accepts(500));
+        assert_eq!(String::from(accept.clone()), "2xx,304,400-404");
+
+        assert!("".parse::<AcceptStatuses>().is_err());
+        assert!("abc".parse::<AcceptStatuses>().is_err());
+        assert!("404-400".parse::<AcceptStatuses>().is_err());
+        assert!("9xx".parse::<AcceptStatuses>().is_err());
+
+        let config: Config = serde_json::from_str(
+            &serde_json::to_string(&Config {
+                accept_statuses: Some(accept.clone()),
+                ..Config::default()
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.accept_statuses, Some(accept));
+
+        let request: ScreenshotRequest =
+            serde_json::from_str(r#"{"url": "https://example.com", "accept_statuses": "2xx"}"#)
+                .unwrap();
+        assert!(request.accept_statuses.unwrap().accepts(204));
+
+        let not_found = ScreenshotError::UnacceptedStatus(404, "https://example.com".to_string());
+        assert!(!not_found.is_retryable());
+        assert!(!not_found.is_browser_failure());
+        assert!(!ScreenshotError::PageError("Node is detached".to_string()).is_browser_failure());
+        assert!(ScreenshotError::ChromeError("target closed".to_string()).is_browser_failure());
+        assert!(ScreenshotError::BrowserProcessDied("SIGKILL".to_string()).is_browser_failure());
+        assert!(ScreenshotError::BrowserLaunchFailed("no chrome".to_string()).is_browser_failure());
+        assert!(ScreenshotError::Timeout(Duration::from_secs(30)).is_browser_failure());
+        assert_eq!(
+            status_for_error(&not_found),
+            axum::http::StatusCode::BAD_GATEWAY
+        );
+        assert!(
+            ScreenshotError::UnacceptedStatus(503, "https://example.com".to_string())
+                .is_retryable()
+        );
+    }
+
+    #[test]
+    fn test_page_actions() {
+        use crate::{parse_batch_entries, status_for_error, PageAction, ScreenshotError};
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r##"{"url": "https://example.com", "actions": [
+                {"type": "click", "selector": "#accept"},
+                {"type": "press_key", "key": "Escape"},
+                {"type": "select_option", "selector": "#size", "value": "xl"}
+            ]}"##,
+        )
+        .unwrap();
+
+        assert_eq!(request.actions.len(), 3);
+        assert_eq!(
+            request.actions[1],
+            PageAction::PressKey {
+                key: "Escape".to_string(),
+                selector: None
+            }
+        );
+        assert_eq!(request.actions[0].describe(), "click '#accept'");
+
+        let entries = parse_batch_entries(
+            "# comment\nhttps://example.com\n\n{\"url\": \"https://github.com\", \"actions\": [{\"type\": \"wait\", \"ms\": 250}]}\n",
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].actions.is_empty());
+        assert_eq!(entries[1].url, "https://github.com");
+        assert_eq!(entries[1].actions, vec![PageAction::Wait { ms: 250 }]);
+        assert!(parse_batch_entries("{\"actions\": []}").is_err());
+
+        let err = ScreenshotError::ActionFailed(
+            2,
+            "click '#menu'".to_string(),
+            "Element not found: #menu".to_string(),
+        );
+        assert_eq!(
+            err.to_string(),
+            "Action 2 (click '#menu') failed: Element not found: #menu"
+        );
+        assert!(!err.is_retryable());
+        assert!(!err.is_browser_failure());
+        let not_found = ScreenshotError::ElementNotFound("#menu".to_string());
+        assert!(!not_found.is_retryable());
+        assert!(!not_found.is_browser_failure());
+        assert_eq!(
+            status_for_error(&err),
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+
+    #[test]
+    fn test_wait_conditions() {
+        use crate::{LoadEvent, ScreenshotError, WaitArgs, WaitCondition, DEFAULT_WAIT_TIMEOUT};
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r##"{"url": "https://example.com", "wait_for": [
+                {"type": "selector", "selector": "#app", "timeout_ms": 2000},
+                {"type": "load_event", "event": "dom_content_loaded"},
+                {"type": "network_idle"}
+            ]}"##,
+        )
+        .unwrap();
+
+        assert_eq!(request.wait_for.len(), 3);
+        assert_eq!(request.wait_for[0].timeout(), Duration::from_millis(2000));
+        assert_eq!(
+            request.wait_for[1],
+            WaitCondition::LoadEvent {
+                event: LoadEvent::DomContentLoaded,
+                timeout_ms: None
+            }
+        );
+        assert_eq!(request.wait_for[1].timeout(), DEFAULT_WAIT_TIMEOUT);
+        assert!(request.wait_for[2].needs_network_tracking());
+        assert!(!request.wait_for[0].needs_network_tracking());
+
+        let args = WaitArgs {
+            wait_for_selector: Some(".loaded".to_string()),
+            network_idle: Some(250),
+            wait_timeout: Some(5000),
+            ..Default::default()
+        };
+        let conditions = args.conditions();
+        assert_eq!(conditions.len(), 2);
+        assert!(conditions[0].needs_network_tracking());
+        assert_eq!(conditions[1].timeout(), Duration::from_secs(5));
+
+        let err =
+            ScreenshotError::WaitTimeout("selector '#app'".to_string(), Duration::from_secs(2));
+        assert!(err.is_retryable());
+        assert!(!err.is_browser_failure());
+        assert_eq!(err.category(), "timeout");
+
+        // A script that throws fails the same way on every attempt
+        let err = ScreenshotError::WaitConditionFailed(
+            "script 'window.ready'".to_string(),
+            "ReferenceError: ready is not defined".to_string(),
+        );
+        assert!(!err.is_retryable());
+        assert!(!err.is_browser_failure());
+    }
+
+    #[test]
+    fn test_browser_isolation_settings() {
+        use crate::BrowserIsolation;
+
+        assert_eq!(Config::default().isolation, BrowserIsolation::Shared);
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://example.com", "isolation": "Incognito", "session": "customer-42"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.isolation, Some(BrowserIsolation::Incognito));
+        assert_eq!(request.session.as_deref(), Some("customer-42"));
+
+        // Configs written before the session limits existed still load
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("max_sessions");
+        object.remove("session_idle_timeout");
+        let config: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(config.max_sessions, 32);
+        assert_eq!(config.session_idle_timeout, Duration::from_secs(600));
+
+        assert_eq!(
+            crate::status_for_error(&crate::ScreenshotError::TooManySessions(32)),
+            axum::http::StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_browser_pool_rejects_zero_tabs() {
+        use crate::{BrowserPool, ScreenshotError};
+
+        let config = Config {
+            browser_pool_size: 1,
+            max_tabs_per_browser: 0,
+            ..Default::default()
+        };
+
+        let result = BrowserPool::new(config).await;
+        assert!(matches!(
+            result,
+            Err(ScreenshotError::ConfigurationError(_))
+        ));
+
+        // Configs written before the tab limit existed still load
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .remove("max_tabs_per_browser");
+        let config: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(config.max_tabs_per_browser, 4);
+    }
+
+    #[test]
+    fn test_encoder_settings() {
+        use crate::{EncoderArgs, EncoderSettings, PngCompression, PngFilter};
+
+        let settings = EncoderSettings::default();
+        assert!(settings.validate().is_ok());
+        assert!(settings.webp_lossless);
+        assert!(!settings.reencodes_png());
+
+        let invalid = EncoderSettings {
+            jpeg_quality: 0,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+        let invalid = EncoderSettings {
+            webp_quality: 101,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+
+        let args = EncoderArgs {
+            jpeg_quality: Some(50),
+            webp_quality: Some(70),
+            png_compression: Some(PngCompression::Best),
+            ..Default::default()
+        };
+        let applied = args.apply(&settings);
+        assert_eq!(applied.jpeg_quality, 50);
+        assert!(!applied.webp_lossless);
+        assert_eq!(applied.webp_quality, 70);
+        assert!(applied.reencodes_png());
+        assert!(EncoderArgs::default().is_empty());
+
+        assert_eq!(
+            "best".parse::<PngCompression>().unwrap(),
+            PngCompression::Best
+        );
+        assert_eq!("none".parse::<PngFilter>().unwrap(), PngFilter::NoFilter);
+        assert!("max".parse::<PngCompression>().is_err());
+    }
+
+    #[test]
+    fn test_element_capture() {
+        use crate::{
+            contact_sheet, contact_sheet_cells, Clip, ElementArgs, ElementCapture, ElementMatch,
+            NotFoundBehavior, ScreenshotMetadata, SelectorType, Viewport,
+        };
+        use image::{DynamicImage, RgbaImage};
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r##"{"url": "https://example.com", "element_selector": "#hero"}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            request.element_capture(),
+            Some(ElementCapture::new("#hero"))
+        );
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r##"{"url": "https://example.com", "element_selector": "#hero",
+                "element": {"selector": "my-app >>> .card", "matches": "contact_sheet", "not_found": "full_page"},
+                "clip": {"x": 0, "y": 200, "width": 800, "height": 600}}"##,
+        )
+        .unwrap();
+        let element = request.element_capture().unwrap();
+        assert_eq!(element.hops(), vec!["my-app", ".card"]);
+        assert_eq!(element.matches, ElementMatch::ContactSheet);
+        assert_eq!(element.not_found, NotFoundBehavior::FullPage);
+        assert_eq!(element.selector_type, SelectorType::Css);
+        assert_eq!(request.clip.unwrap().y, 200.0);
+
+        let clip: Clip = " 10, 20 ,30,40".parse().unwrap();
+        assert_eq!(
+            clip,
+            Clip {
+                x: 10.0,
+                y: 20.0,
+                width: 30.0,
+                height: 40.0
+            }
+        );
+        assert_eq!(clip.padded(15.0).x, 0.0);
+        assert_eq!(clip.padded(15.0).width, 55.0);
+        assert!("1,2,3".parse::<Clip>().is_err());
+        assert!("0,0,0,10".parse::<Clip>().unwrap().validate().is_err());
+
+        assert_eq!(
+            "full-page".parse::<NotFoundBehavior>().unwrap(),
+            NotFoundBehavior::FullPage
+        );
+        assert!("ignore".parse::<NotFoundBehavior>().is_err());
+
+        let mut request = ScreenshotRequest {
+            element_selector: Some("//li".to_string()),
+            ..Default::default()
+        };
+        ElementArgs {
+            xpath: true,
+            all_matches: true,
+            padding: Some(4.0),
+            not_found: Some(NotFoundBehavior::Skip),
+            ..Default::default()
+        }
+        .apply(&mut request);
+        let element = request.element.unwrap();
+        assert_eq!(element.selector_type, SelectorType::Xpath);
+        assert_eq!(element.matches, ElementMatch::All);
+        assert_eq!(element.padding, 4.0);
+        assert_eq!(element.not_found, NotFoundBehavior::Skip);
+
+        let metadata = |viewport_label: Option<&str>, element_index| ScreenshotMetadata {
+            viewport: Viewport::default(),
+            viewport_label: viewport_label.map(str::to_string),
+            element_index,
+            page_title: None,
+            final_url: None,
+            response_status: None,
+            redirect_chain: Vec::new(),
+            response_headers: std::collections::HashMap::new(),
+            mime_type: None,
+            file_size: 0,
+            encoding: None,
+            browser_instance_id: 0,
+            blocked_requests: 0,
+            ignore_regions: Vec::new(),
+        };
+        assert_eq!(metadata(None, None).label(), None);
+        assert_eq!(metadata(None, Some(0)).label().unwrap(), "element-1");
+        assert_eq!(
+            metadata(Some("iPhone 15"), Some(2)).label().unwrap(),
+            "iPhone 15 element-3"
+        );
+
+        let images = vec![
+            DynamicImage::ImageRgba8(RgbaImage::new(40, 10)),
+            DynamicImage::ImageRgba8(RgbaImage::new(20, 30)),
+        ];
+        let sheet = contact_sheet(&images).unwrap();
+        assert_eq!(
+            (sheet.width(), sheet.height()),
+            (2 * 40 + 3 * 16, 30 + 2 * 16)
+        );
+        assert_eq!(contact_sheet_cells(&images), vec![(16, 16), (72, 16)]);
+        assert!(contact_sheet(&[]).is_err());
+    }
+
+    #[test]
+    fn test_full_page_tiling() {
+        use crate::{
+            stitch_tiles, EncoderSettings, FullPageOptions, ImageEncoding, Tile, TilingArgs,
+            MAX_FULL_PAGE_HEIGHT,
+        };
+        use image::{DynamicImage, Rgba, RgbaImage};
+
+        let options: FullPageOptions = serde_json::from_str(r#"{"tiled": true}"#).unwrap();
+        assert!(options.tiled && options.hide_sticky);
+        assert_eq!(options.max_height, 20_000);
+        assert!(FullPageOptions {
+            max_height: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(FullPageOptions {
+            max_height: MAX_FULL_PAGE_HEIGHT + 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+
+        let args = TilingArgs {
+            tiled: true,
+            max_height: Some(5000),
+            keep_sticky: true,
+            ..Default::default()
+        };
+        assert!(TilingArgs::default().is_empty() && !args.is_empty());
+        let applied = args.apply(&FullPageOptions::default());
+        assert!(applied.tiled && !applied.hide_sticky);
+        assert_eq!(applied.max_height, 5000);
+        assert_eq!(applied.scroll_delay_ms, 200);
+
+        // Stitched tiles are encoded locally, where WebP is lossless only
+        let lossy = EncoderSettings {
+            webp_lossless: false,
+            webp_quality: 60,
+            ..Default::default()
+        };
+        assert_eq!(
+            lossy.encoding(&OutputFormat::Webp, false),
+            Some(ImageEncoding::Lossy(60))
+        );
+        assert_eq!(
+            lossy.encoding(&OutputFormat::Webp, true),
+            Some(ImageEncoding::Lossless)
+        );
+        assert_eq!(
+            lossy.encoding(&OutputFormat::Jpeg, true),
+            Some(ImageEncoding::Lossy(75))
+        );
+        assert_eq!(lossy.encoding(&OutputFormat::Pdf, false), None);
+
+        // 2x tiles of a 50x100 CSS pixel viewport over a 250px page; the last
+        // tile is clamped to the bottom and overlaps the second
+        let tile = |offset, color| Tile {
+            offset,
+            image: DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 200, Rgba(color))),
+        };
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let stitched = stitch_tiles(&[tile(0, red), tile(100, green), tile(150, blue)], 50, 250)
+            .unwrap()
+            .to_rgba8();
+
+        assert_eq!(stitched.dimensions(), (100, 500));
+        assert_eq!(stitched.get_pixel(0, 199).0, red);
+        assert_eq!(stitched.get_pixel(0, 200).0, green);
+        assert_eq!(stitched.get_pixel(0, 299).0, green);
+        assert_eq!(stitched.get_pixel(99, 300).0, blue);
+        assert_eq!(stitched.get_pixel(99, 499).0, blue);
+
+        assert!(stitch_tiles(&[], 50, 250).is_err());
+        // Over the pixel budget once the 2x ratio is applied, refused before allocating
+        assert!(stitch_tiles(&[tile(0, red)], 50, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn test_page_injection() {
+        use crate::{redact_image, Clip, InjectionArgs, PageInjection, RedactionStyle};
+        use image::{Rgba, RgbaImage};
+
+        let config = Config {
+            injection: PageInjection {
+                hide_selectors: vec!["#cookie-banner".to_string()],
+                redaction: Some(RedactionStyle::Blur),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://example.com", "hide_selectors": [".chat"],
+                "redact_selectors": [".email"], "inject_scripts": ["window.x = 1"]}"#,
+        )
+        .unwrap();
+        assert_eq!(request.injection.inject_scripts, vec!["window.x = 1"]);
+
+        let merged = config.injection.merged(&request.injection);
+        assert_eq!(merged.hide_selectors, vec!["#cookie-banner", ".chat"]);
+        assert_eq!(merged.redaction_style(), RedactionStyle::Blur);
+        assert!(!merged.is_empty() && PageInjection::default().is_empty());
+
+        let css = merged.stylesheet();
+        assert!(css.contains("#cookie-banner { visibility: hidden !important; }"));
+        assert!(css.contains(".email { filter: blur(12px) !important; }"));
+        assert!(PageInjection::default().stylesheet().is_empty());
+
+        assert_eq!(
+            "Blackout".parse::<RedactionStyle>().unwrap(),
+            RedactionStyle::Blackout
+        );
+        assert!("pixelate".parse::<RedactionStyle>().is_err());
+
+        let mut request = ScreenshotRequest::default();
+        InjectionArgs {
+            hide_selectors: vec![".ad".to_string()],
+            redaction: Some(RedactionStyle::Blur),
+            ..Default::default()
+        }
+        .apply(&mut request);
+        assert_eq!(request.injection.hide_selectors, vec![".ad"]);
+        assert_eq!(request.injection.redaction, Some(RedactionStyle::Blur));
+
+        // A 2x image of the document scrolled to y=100, with one box straddling
+        // the top edge and one outside the image
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        let mut image = RgbaImage::from_pixel(200, 100, Rgba(white));
+        let region = Clip {
+            x: 0.0,
+            y: 100.0,
+            width: 100.0,
+            height: 50.0,
+        };
+        let boxes = [
+            Clip {
+                x: 10.0,
+                y: 90.0,
+                width: 20.0,
+                height: 20.0,
+            },
+            Clip {
+                x: 0.0,
+                y: 400.0,
+                width: 10.0,
+                height: 10.0,
+            },
+        ];
+        redact_image(&mut image, region, &boxes, RedactionStyle::Blackout);
+
+        assert_eq!(image.get_pixel(20, 0).0, black);
+        assert_eq!(image.get_pixel(59, 19).0, black);
+        assert_eq!(image.get_pixel(60, 19).0, white);
+        assert_eq!(image.get_pixel(20, 20).0, white);
+        assert_eq!(image.get_pixel(19, 0).0, white);
+    }
+
+    #[test]
+    fn test_paper_size_parsing() {
+        use crate::PaperSize;
+
+        assert_eq!("a4".parse::<PaperSize>().unwrap(), PaperSize::A4);
+        assert_eq!("Letter".parse::<PaperSize>().unwrap(), PaperSize::Letter);
+        assert_eq!(
+            "8x10".parse::<PaperSize>().unwrap(),
+            PaperSize::Custom {
+                width: 8.0,
+                height: 10.0
+            }
+        );
+        assert!("b5".parse::<PaperSize>().is_err());
+        assert!("0x10".parse::<PaperSize>().is_err());
+
+        assert_eq!(PaperSize::Letter.dimensions(), (8.5, 11.0));
+        assert_eq!(PaperSize::A4.dimensions(), (8.27, 11.7));
+    }
+
+    #[test]
+    fn test_pdf_options() {
+        use crate::{PdfMargins, PdfOptions};
+
+        let options = PdfOptions::default();
+        assert!(options.print_background);
+        assert!(!options.landscape);
+        assert!(!options.has_header_footer());
+        assert_eq!(options.margins, PdfMargins::uniform(0.4));
+        assert!(options.validate().is_ok());
+        for scale in [0.05, 2.5, f64::NAN] {
+            assert!(PdfOptions {
+                scale,
+                ..Default::default()
+            }
+            .validate()
+            .is_err());
+        }
+
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://example.com", "pdf": {"paper_size": "A4", "landscape": true, "footer_template": "<span class='pageNumber'></span>"}}"#,
+        )
+        .unwrap();
+        let pdf = request.pdf.unwrap();
+        assert_eq!(pdf.paper_size, crate::PaperSize::A4);
+        assert!(pdf.landscape);
+        assert!(pdf.print_background);
+        assert!(pdf.has_header_footer());
+
+        assert_eq!(OutputFormat::Pdf.extension(), "pdf");
+        assert_eq!(OutputFormat::Jpeg.extension(), "jpg");
+        assert_eq!(crate::content_type(&OutputFormat::Pdf), "application/pdf");
+    }
+
+    #[test]
+    fn test_server_error_status_mapping() {
+        use crate::{status_for_error, ScreenshotError};
+        use axum::http::StatusCode;
+
+        assert_eq!(
+            status_for_error(&ScreenshotError::InvalidUrl("test".to_string())),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status_for_error(&ScreenshotError::Timeout(Duration::from_secs(1))),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            status_for_error(&ScreenshotError::BrowserUnavailable),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status_for_error(&ScreenshotError::CaptureFailed("test".to_string())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_server_rejects_non_http_urls() {
+        use crate::check_request_url;
+
+        assert!(check_request_url("https://example.com").is_ok());
+        assert!(check_request_url("http://localhost:8080/page").is_ok());
+        assert!(check_request_url("").is_err());
+
+        let err = check_request_url("file:///etc/passwd").unwrap_err();
+        assert!(err.contains("file:///etc/passwd"));
+        assert!(check_request_url("chrome://settings").is_err());
+        assert!(check_request_url("view-source:https://example.com").is_err());
+        assert_eq!(
+            crate::ApiError::bad_request(err).status(),
+            axum::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    // Unit test helpers
+    fn test_png(color: image::Rgba<u8>) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbaImage::from_pixel(4, 4, color)
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        data
+    }
+
+    /// A successful PNG capture at 390x844@3x, or a failed one without data
+    fn test_result(
+        url: &str,
+        data: Vec<u8>,
+        viewport_label: Option<&str>,
+    ) -> crate::ScreenshotResult {
+        crate::ScreenshotResult {
+            request_id: "test".to_string(),
+            url: url.to_string(),
+            success: !data.is_empty(),
+            error: data
+                .is_empty()
+                .then(|| crate::ScreenshotError::UrlLoadFailed("unreachable".to_string())),
+            data,
+            format: OutputFormat::Png,
+            timestamp: std::time::SystemTime::now(),
+            duration: Duration::from_millis(10),
+            metadata: crate::ScreenshotMetadata {
+                viewport: Viewport {
+                    width: 390,
+                    height: 844,
+                    device_scale_factor: 3.0,
+                    mobile: true,
+                },
+                viewport_label: viewport_label.map(str::to_string),
+                element_index: None,
+                page_title: None,
+                final_url: None,
+                response_status: None,
+                redirect_chain: Vec::new(),
+                response_headers: std::collections::HashMap::new(),
+                mime_type: None,
+                file_size: 0,
+                encoding: None,
+                browser_instance_id: 0,
+                blocked_requests: 0,
+                ignore_regions: Vec::new(),
+            },
+        }
+    }
+
     // Integration test helper
     async fn create_test_service() -> crate::ScreenshotService {
         let config = Config {
@@ -354,6 +1473,557 @@ mod integration_tests {
         }
     }
 
+    #[test]
+    fn test_emulation_options() {
+        use crate::{ColorScheme, Emulation, EmulationArgs, Geolocation, MediaType};
+
+        let config = Config {
+            emulation: Emulation {
+                color_scheme: Some(ColorScheme::Dark),
+                locale: Some("en-GB".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let request: ScreenshotRequest = serde_json::from_str(
+            r#"{"url": "https://example.com", "locale": "fr-FR", "media": "print",
+                "reduced_motion": true, "geolocation": {"latitude": 48.85, "longitude": 2.35}}"#,
+        )
+        .unwrap();
+
+        let merged = config.emulation.merged(&request.emulation);
+        assert_eq!(merged.color_scheme, Some(ColorScheme::Dark));
+        assert_eq!(merged.locale.as_deref(), Some("fr-FR"));
+        assert_eq!(merged.media, Some(MediaType::Print));
+        assert!(merged.validate().is_ok());
+
+        let params = merged.media_params().unwrap();
+        assert_eq!(params.media.as_deref(), Some("print"));
+        let features: Vec<_> = params
+            .features
+            .unwrap()
+            .into_iter()
+            .map(|feature| (feature.name, feature.value))
+            .collect();
+        assert_eq!(
+            features,
+            vec![
+                ("prefers-color-scheme".to_string(), "dark".to_string()),
+                ("prefers-reduced-motion".to_string(), "reduce".to_string()),
+            ]
+        );
+        assert!(Emulation::default().media_params().is_none());
+
+        let geolocation: Geolocation = "40.71, -74.0, 10".parse().unwrap();
+        assert_eq!(geolocation.longitude, -74.0);
+        assert_eq!(geolocation.accuracy, 10.0);
+        assert_eq!("1,2".parse::<Geolocation>().unwrap().accuracy, 100.0);
+        assert!("1".parse::<Geolocation>().is_err());
+        assert!(Emulation {
+            geolocation: Some("91,0".parse().unwrap()),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!("sepia".parse::<ColorScheme>().is_err());
+
+        let mut request = ScreenshotRequest::default();
+        EmulationArgs {
+            color_scheme: Some(ColorScheme::Light),
+            reduced_motion: true,
+            timezone: Some("Asia/Tokyo".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut request);
+        assert_eq!(request.emulation.color_scheme, Some(ColorScheme::Light));
+        assert_eq!(request.emulation.reduced_motion, Some(true));
+        assert_eq!(request.emulation.timezone.as_deref(), Some("Asia/Tokyo"));
+        assert_eq!(request.emulation.locale, None);
+    }
+
+    #[test]
+    fn test_deterministic_mode() {
+        use crate::DeterministicOptions;
+
+        let mut config = Config::default();
+        assert!(!config.deterministic.enabled);
+        assert!(!crate::get_chrome_args(&config).contains(&"--disable-lcd-text".to_string()));
+
+        config.deterministic = serde_json::from_str(r#"{"enabled": true, "seed": 7}"#).unwrap();
+        assert_eq!(config.deterministic.start_time, 1_704_067_200_000);
+        let args = crate::get_chrome_args(&config);
+        assert!(args.contains(&"--disable-lcd-text".to_string()));
+        assert!(args.contains(&"--font-render-hinting=none".to_string()));
+        assert!(args.contains(&"--disable-remote-fonts".to_string()));
+        assert!(config
+            .deterministic
+            .random_script()
+            .contains("let state = 7 >>> 0"));
+
+        let web_fonts = DeterministicOptions {
+            local_fonts_only: false,
+            ..Default::default()
+        };
+        assert!(!web_fonts
+            .chrome_args()
+            .contains(&"--disable-remote-fonts".to_string()));
+
+        let request: ScreenshotRequest =
+            serde_json::from_str(r#"{"url": "https://example.com", "deterministic": false}"#)
+                .unwrap();
+        assert_eq!(request.deterministic, Some(false));
+        assert_eq!(ScreenshotRequest::default().deterministic, None);
+
+        // Opting in needs the launch flags, opting out always works
+        assert!(config.deterministic.check_request(Some(true)).is_ok());
+        assert!(DeterministicOptions::default()
+            .check_request(Some(true))
+            .is_err());
+        assert!(DeterministicOptions::default()
+            .check_request(Some(false))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_visual_diff() {
+        use crate::{diff_directories, diff_images, Clip, DiffArgs, DiffOptions, DiffStatus};
+        use image::{DynamicImage, Rgba, RgbaImage};
+
+        let white = Rgba([255, 255, 255, 255]);
+        let black = Rgba([0, 0, 0, 255]);
+        let image = |pixels: RgbaImage| DynamicImage::ImageRgba8(pixels);
+        // Black on the left, white on the right
+        let edge = RgbaImage::from_fn(10, 10, |x, _| if x < 5 { black } else { white });
+        let options = DiffOptions::default();
+
+        let diff = diff_images(&image(edge.clone()), &image(edge.clone()), &options);
+        assert_eq!(diff.different_pixels, 0);
+        assert!(!diff.exceeds(0.0));
+
+        // Noise below the threshold is not a change
+        let mut noisy = edge.clone();
+        noisy.put_pixel(8, 8, Rgba([250, 250, 250, 255]));
+        assert_eq!(
+            diff_images(&image(edge.clone()), &image(noisy), &options).different_pixels,
+            0
+        );
+
+        // A grey pixel on the edge is anti-aliasing, one in a flat area is not
+        let mut changed = edge.clone();
+        changed.put_pixel(5, 5, Rgba([128, 128, 128, 255]));
+        changed.put_pixel(8, 2, black);
+        let diff = diff_images(&image(edge.clone()), &image(changed.clone()), &options);
+        assert_eq!(diff.anti_aliased_pixels, 1);
+        assert_eq!(diff.different_pixels, 1);
+        assert_eq!(diff.mismatch_percentage, 1.0);
+        assert!(diff.exceeds(0.5) && !diff.exceeds(1.0));
+        assert_eq!(diff.diff_image.get_pixel(8, 2), &Rgba([255, 0, 0, 255]));
+        assert_eq!(diff.diff_image.get_pixel(5, 5), &Rgba([255, 255, 0, 255]));
+
+        let strict = DiffOptions {
+            include_anti_aliasing: true,
+            ..Default::default()
+        };
+        let diff = diff_images(&image(edge.clone()), &image(changed.clone()), &strict);
+        assert_eq!(diff.different_pixels, 2);
+
+        let ignoring = DiffOptions {
+            ignore_regions: vec![Clip {
+                x: 8.0,
+                y: 0.0,
+                width: 2.0,
+                height: 10.0,
+            }],
+            ..Default::default()
+        };
+        let diff = diff_images(&image(edge.clone()), &image(changed), &ignoring);
+        assert_eq!(diff.different_pixels, 0);
+        assert_eq!(diff.ignored_pixels, 20);
+
+        // Rows only one image has count as changed
+        let taller = RgbaImage::from_fn(10, 12, |x, _| if x < 5 { black } else { white });
+        let diff = diff_images(&image(edge.clone()), &image(taller), &options);
+        assert!(diff.size_changed);
+        assert_eq!((diff.width, diff.height), (10, 12));
+        assert_eq!(diff.different_pixels, 20);
+
+        assert!(DiffOptions {
+            threshold: 1.5,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+
+        let applied = DiffArgs {
+            threshold: Some(0.3),
+            ignore_regions: vec!["0,0,10,10".parse().unwrap()],
+            ..Default::default()
+        }
+        .apply(&options);
+        assert_eq!(applied.threshold, 0.3);
+        assert_eq!(applied.ignore_regions.len(), 1);
+        assert_eq!(applied.fail_threshold, 0.0);
+
+        // Directories are matched by relative path
+        let root = std::env::temp_dir().join(format!("screenshot-diff-{}", uuid::Uuid::new_v4()));
+        let (baseline, current) = (root.join("baseline"), root.join("current"));
+        std::fs::create_dir_all(baseline.join("sub")).unwrap();
+        std::fs::create_dir_all(&current).unwrap();
+        let mut flipped = edge.clone();
+        image::imageops::flip_horizontal_in_place(&mut flipped);
+        edge.save(baseline.join("same.png")).unwrap();
+        edge.save(current.join("same.png")).unwrap();
+        edge.save(baseline.join("changed.png")).unwrap();
+        flipped.save(current.join("changed.png")).unwrap();
+        edge.save(current.join("new.png")).unwrap();
+        edge.save(baseline.join("sub/missing.png")).unwrap();
+        std::fs::write(current.join("notes.txt"), "not an image").unwrap();
+
+        let entries = diff_directories(&baseline, &current, &options).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let statuses: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.path.to_string_lossy().into_owned(), entry.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("changed.png".to_string(), DiffStatus::Changed),
+                ("new.png".to_string(), DiffStatus::New),
+                ("same.png".to_string(), DiffStatus::Unchanged),
+                ("sub/missing.png".to_string(), DiffStatus::Missing),
+            ]
+        );
+        assert_eq!(entries[0].diff.as_ref().unwrap().mismatch_percentage, 100.0);
+    }
+
+    #[test]
+    fn test_baseline_store() {
+        use crate::{BaselineKey, BaselineStore, DiffOptions, MissingScope};
+        use image::Rgba;
+
+        let white = Rgba([255, 255, 255, 255]);
+        let black = Rgba([0, 0, 0, 255]);
+
+        let home = test_result("https://example.com/", test_png(white), None);
+        let key = BaselineKey::for_result(&home, Some("iPhone 12"));
+        assert_eq!(key.viewport, "390x844@3x");
+        assert_eq!(key.file_stem(), "example.com__iphone-12__390x844@3x");
+
+        let root =
+            std::env::temp_dir().join(format!("screenshot-baseline-{}", uuid::Uuid::new_v4()));
+        let mut store = BaselineStore::open(&root).unwrap();
+        assert!(store.is_empty());
+        for captured in [
+            &home,
+            &test_result("https://example.com/about", test_png(white), None),
+            &test_result(
+                "https://example.com/pricing",
+                test_png(white),
+                Some("Desktop"),
+            ),
+            &test_result(
+                "https://example.com/pricing",
+                test_png(white),
+                Some("Mobile"),
+            ),
+            &test_result("https://example.com/gone", test_png(white), None),
+        ] {
+            store
+                .accept(
+                    BaselineKey::for_result(captured, Some("iPhone 12")),
+                    captured,
+                )
+                .unwrap();
+        }
+        store.save().unwrap();
+        assert!(store
+            .accept(
+                key.clone(),
+                &test_result("https://example.com/", Vec::new(), None)
+            )
+            .is_err());
+
+        // The manifest round-trips
+        let store = BaselineStore::open(&root).unwrap();
+        assert_eq!(store.len(), 5);
+        let entry = store.get(&key).unwrap();
+        assert_eq!(entry.file, "example.com__iphone-12__390x844@3x.png");
+        assert!(store.image_path(entry).exists());
+
+        let results = vec![
+            home,
+            test_result("https://example.com/about", test_png(black), None),
+            test_result(
+                "https://example.com/pricing",
+                test_png(white),
+                Some("Desktop"),
+            ),
+            test_result("https://example.com/new", test_png(white), None),
+            test_result("https://example.com/broken", Vec::new(), None),
+        ];
+        let diffs = root.join("diffs");
+        let report = store
+            .check(
+                &results,
+                Some("iPhone 12"),
+                MissingScope::All,
+                &DiffOptions::default(),
+                Some(&diffs),
+            )
+            .unwrap();
+        let urls =
+            |keys: &[BaselineKey]| keys.iter().map(|key| key.url.clone()).collect::<Vec<_>>();
+
+        assert!(!report.passed);
+        assert_eq!(report.changed.len(), 1);
+        assert_eq!(report.changed[0].key.url, "https://example.com/about");
+        assert_eq!(report.changed[0].mismatch_percentage, 100.0);
+        assert!(report.changed[0].diff_image.as_ref().unwrap().exists());
+        assert_eq!(urls(&report.new), vec!["https://example.com/new"]);
+        // Pages left out of the run are missing too
+        assert_eq!(
+            urls(&report.missing),
+            vec!["https://example.com/gone", "https://example.com/pricing"]
+        );
+        assert_eq!(report.missing[1].label.as_deref(), Some("Mobile"));
+        assert_eq!(report.unchanged.len(), 2);
+        assert_eq!(report.failed[0].url, "https://example.com/broken");
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["changed"][0]["device"], "iPhone 12");
+        assert_eq!(json["missing"][0]["viewport"], "390x844@3x");
+
+        // A different device is a different baseline
+        let report = store
+            .check(
+                &results[..1],
+                None,
+                MissingScope::All,
+                &DiffOptions::default(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(report.new.len(), 1);
+        assert_eq!(report.missing.len(), 5);
+
+        // Checking a subset of the store
+        let report = store
+            .check(
+                &results,
+                Some("iPhone 12"),
+                MissingScope::CapturedPages,
+                &DiffOptions::default(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(urls(&report.missing), vec!["https://example.com/pricing"]);
+        let report = store
+            .check(
+                &results[..1],
+                Some("iPhone 12"),
+                MissingScope::CapturedPages,
+                &DiffOptions::default(),
+                None,
+            )
+            .unwrap();
+        assert!(report.passed);
+        assert_eq!(report.unchanged, vec![key.clone()]);
+        assert!(report.missing.is_empty());
+
+        // Deleted and corrupt images are reported instead of aborting the check
+        let image = |result: &crate::ScreenshotResult| {
+            store.image_path(
+                store
+                    .get(&BaselineKey::for_result(result, Some("iPhone 12")))
+                    .unwrap(),
+            )
+        };
+        std::fs::remove_file(image(&results[1])).unwrap();
+        std::fs::write(image(&results[2]), b"not an image").unwrap();
+        let report = store
+            .check(
+                &results[..3],
+                Some("iPhone 12"),
+                MissingScope::CapturedPages,
+                &DiffOptions::default(),
+                None,
+            )
+            .unwrap();
+        assert!(!report.passed);
+        assert_eq!(
+            urls(&report.missing),
+            vec!["https://example.com/about", "https://example.com/pricing"]
+        );
+        assert_eq!(report.failed[0].url, "https://example.com/pricing");
+
+        let mut store = store;
+        assert!(store.remove(&key).unwrap());
+        assert!(!root.join("example.com__iphone-12__390x844@3x.png").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watch_mode() {
+        use crate::{
+            perceptual_hash, AlertSink, BaselineKey, ChangeDetection, DiffOptions, PageChange,
+            Schedule, WatchArgs, WatchOptions, WatchStore,
+        };
+        use chrono::{TimeZone, Utc};
+        use image::{DynamicImage, Rgba, RgbaImage};
+
+        let at = |d, h, m| Utc.with_ymd_and_hms(2024, 1, d, h, m, 0).unwrap();
+        let next = |schedule: &str, after| schedule.parse::<Schedule>().unwrap().next_after(after);
+        // 2024-01-01 is a Monday
+        assert_eq!(next("*/15 * * * *", at(1, 10, 7)), Some(at(1, 10, 15)));
+        assert_eq!(next("0 9-17/4 * * *", at(1, 13, 0)), Some(at(1, 17, 0)));
+        assert_eq!(next("0 0 * * sun", at(1, 10, 0)), Some(at(7, 0, 0)));
+        assert_eq!(next("0 0 * * 7", at(1, 10, 0)), Some(at(7, 0, 0)));
+        assert_eq!(next("@daily", at(1, 10, 0)), Some(at(2, 0, 0)));
+        // Day of month or day of week when both are restricted
+        assert_eq!(next("0 12 5 * mon", at(1, 13, 0)), Some(at(5, 12, 0)));
+        // A stepped wildcard day must match as well: odd days that are Mondays
+        assert_eq!(next("0 0 */2 * mon", at(1, 10, 0)), Some(at(15, 0, 0)));
+        assert_eq!(
+            next("0 0 29 feb *", at(1, 0, 0)),
+            Some(Utc.with_ymd_and_hms(2024, 2, 29, 0, 0, 0).unwrap())
+        );
+        assert_eq!(next("0 0 30 feb *", at(1, 0, 0)), None);
+        assert_eq!(
+            next("@every 90s", at(1, 0, 0)),
+            Some(at(1, 0, 1) + chrono::Duration::seconds(30))
+        );
+        for invalid in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "@every 0m",
+            "@every 5",
+            "@every 999999999999999999d",
+            "5-1 * * * *",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{invalid}");
+        }
+        let options: WatchOptions =
+            serde_json::from_str(r#"{"schedule": "@hourly", "detection": "hash"}"#).unwrap();
+        assert_eq!(options.schedule.unwrap().to_string(), "0 * * * *");
+
+        // Perceptual hashes follow structure, not small color shifts
+        let gradient = |offset: u8| {
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, _| {
+                let value = (x * 3) as u8 + offset;
+                Rgba([value, value, value, 255])
+            }))
+        };
+        let flipped = DynamicImage::ImageRgba8(image::imageops::flip_horizontal(&gradient(0)));
+        assert_eq!(
+            perceptual_hash(&gradient(0)),
+            perceptual_hash(&gradient(10))
+        );
+        assert_eq!(
+            (perceptual_hash(&gradient(0)) ^ perceptual_hash(&flipped)).count_ones(),
+            64
+        );
+
+        let white = Rgba([255, 255, 255, 255]);
+        let black = Rgba([0, 0, 0, 255]);
+        let root = std::env::temp_dir().join(format!("screenshot-watch-{}", uuid::Uuid::new_v4()));
+        let mut store = WatchStore::open(&root).unwrap();
+        let options = WatchOptions::default();
+        let diff = DiffOptions::default();
+
+        let round = store
+            .record_round(
+                &[
+                    test_result("https://example.com/", test_png(white), None),
+                    test_result("https://example.com/broken", Vec::new(), None),
+                    test_result("https://example.com/garbled", b"not a png".to_vec(), None),
+                ],
+                None,
+                &options,
+                &diff,
+            )
+            .unwrap();
+        assert_eq!(
+            (round.first_seen, round.failed, round.alerts.len()),
+            (1, 2, 0)
+        );
+        assert!(root.join("watch.json").exists());
+
+        let mut pdf = test_result("https://example.com/", b"%PDF-1.4".to_vec(), None);
+        pdf.format = OutputFormat::Pdf;
+        assert!(store.record_round(&[pdf], None, &options, &diff).is_err());
+
+        let unchanged = [test_result("https://example.com/", test_png(white), None)];
+        let round = store
+            .record_round(&unchanged, None, &options, &diff)
+            .unwrap();
+        assert_eq!((round.unchanged, round.alerts.len()), (1, 0));
+
+        let changed = [test_result("https://example.com/", test_png(black), None)];
+        let round = store.record_round(&changed, None, &options, &diff).unwrap();
+        let alert = &round.alerts[0];
+        assert_eq!(
+            alert.change,
+            PageChange::Pixel {
+                mismatch_percentage: 100.0
+            }
+        );
+        assert!(alert.previous.exists() && alert.current.exists());
+        assert_ne!(alert.previous, alert.current);
+
+        // Only the first capture and the change were stored, and the manifest round-trips
+        let key = BaselineKey::for_result(&changed[0], None);
+        let store = WatchStore::open(&root).unwrap();
+        assert_eq!(store.history(&key).len(), 2);
+        assert_eq!(
+            store.latest(&key).unwrap().file,
+            alert.current.strip_prefix(&root).unwrap().to_str().unwrap()
+        );
+
+        // Flat images hash alike, so hash detection sees no change
+        let mut store = store;
+        let hashing = WatchOptions {
+            detection: ChangeDetection::Hash,
+            ..Default::default()
+        };
+        let round = store
+            .record_round(&unchanged, None, &hashing, &diff)
+            .unwrap();
+        assert_eq!(round.unchanged, 1);
+
+        let alerts = root.join("alerts/changes.jsonl");
+        let sink = AlertSink::File {
+            path: alerts.clone(),
+        };
+        let client = AlertSink::client().unwrap();
+        sink.send(&client, alert).await.unwrap();
+        sink.send(&client, alert).await.unwrap();
+        let lines = std::fs::read_to_string(&alerts).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+        let sent: serde_json::Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(sent["url"], "https://example.com/");
+        assert_eq!(sent["change"]["detection"], "pixel");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let applied = WatchArgs {
+            schedule: Some("@every 1h".parse().unwrap()),
+            webhooks: vec!["https://hooks.example.com/changes".to_string()],
+            ..Default::default()
+        }
+        .apply(&options);
+        assert!(applied.validate().is_ok());
+        assert_eq!(applied.alerts.len(), 1);
+        assert!(WatchOptions {
+            alerts: vec![AlertSink::Webhook {
+                url: "not a url".to_string()
+            }],
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
     #[tokio::test]
     async fn test_service_creation() {
         let service = create_test_service().await;
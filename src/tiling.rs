@@ -0,0 +1,292 @@
+//! Tiled full-page capture
+//!
+//! Chrome's single-shot full-page capture is bounded by its maximum texture
+//! size and never scrolls, so very tall pages come back truncated and
+//! lazy-loaded images below the fold stay empty. Tiled capture scrolls
+//! through the page first to trigger lazy loading, then captures it one
+//! viewport at a time and stitches the tiles with the `image` crate.
+
+use crate::{Clip, FullPageOptions, ScreenshotError};
+use chromiumoxide::cdp::browser_protocol::page::{
+    CaptureScreenshotFormat, Viewport as CaptureViewport,
+};
+use chromiumoxide::page::{Page, ScreenshotParams};
+use image::{imageops, DynamicImage, RgbaImage};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::debug;
+
+/// Waits for images requested during the scroll pass, for at most 5 seconds
+const WAIT_FOR_IMAGES_SCRIPT: &str = r#"Promise.race([
+    Promise.all(Array.from(document.images).filter(img => !img.complete).map(img => new Promise(resolve => {
+        img.addEventListener('load', resolve);
+        img.addEventListener('error', resolve);
+    }))),
+    new Promise(resolve => setTimeout(resolve, 5000)),
+]).then(() => true)"#;
+
+const HIDE_STICKY_SCRIPT: &str = r#"(() => {
+    for (const el of document.querySelectorAll('body *')) {
+        const position = getComputedStyle(el).position;
+        if (position !== 'fixed' && position !== 'sticky') continue;
+        el.dataset.screenshotHidden = el.style.getPropertyValue('visibility') + '|' + el.style.getPropertyPriority('visibility');
+        el.style.setProperty('visibility', 'hidden', 'important');
+    }
+    return true;
+})()"#;
+
+const SHOW_STICKY_SCRIPT: &str = r#"(() => {
+    for (const el of document.querySelectorAll('[data-screenshot-hidden]')) {
+        const [value, priority] = el.dataset.screenshotHidden.split('|');
+        if (value) el.style.setProperty('visibility', value, priority);
+        else el.style.removeProperty('visibility');
+        delete el.dataset.screenshotHidden;
+    }
+    return true;
+})()"#;
+
+/// Largest stitched image, in device pixels (about 400MB as RGBA)
+pub const MAX_STITCHED_PIXELS: u64 = 100_000_000;
+
+/// Document and viewport size in CSS pixels
+#[derive(Debug, Deserialize)]
+struct ScrollMetrics {
+    width: f64,
+    height: f64,
+    viewport_height: f64,
+    scroll_y: f64,
+    device_pixel_ratio: f64,
+}
+
+/// A captured viewport and the document offset it was taken at
+#[derive(Debug, Clone)]
+pub struct Tile {
+    /// Distance from the top of the document in CSS pixels
+    pub offset: u32,
+    pub image: DynamicImage,
+}
+
+/// Capture the whole page in viewport-sized tiles and stitch them together
+///
+/// Call [`load_lazy_content`] first; anything measured on the page before
+/// that, such as redacted boxes, may have moved by the time tiles are taken.
+/// Returns the stitched image and the document rectangle it shows. The page
+/// is scrolled back to where it was and any hidden sticky elements are
+/// restored afterwards, so the page can be captured again.
+pub async fn capture_tiled(
+    page: &Page,
+    options: &FullPageOptions,
+) -> Result<(DynamicImage, Clip), ScreenshotError> {
+    let initial = scroll_metrics(page).await?;
+    let tiles = capture_tiles(page, options).await;
+
+    // Put the page back even when a tile failed
+    if options.hide_sticky {
+        evaluate(page, SHOW_STICKY_SCRIPT).await?;
+    }
+    scroll_to(page, initial.scroll_y).await?;
+
+    let (tiles, width, height) = tiles?;
+    debug!("Stitching {} tiles into {}x{}", tiles.len(), width, height);
+
+    // Stitching a tall page is CPU bound; keep it off the async executor
+    let image = tokio::task::spawn_blocking(move || stitch_tiles(&tiles, width, height))
+        .await
+        .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))??;
+    let region = Clip {
+        x: 0.0,
+        y: 0.0,
+        width: f64::from(width),
+        height: f64::from(height),
+    };
+
+    Ok((image, region))
+}
+
+/// Stitch tiles into one image `width` x `height` CSS pixels tall
+///
+/// Tiles may be captured at a device pixel ratio above 1; the ratio is taken
+/// from the width of the first tile. Where tiles overlap, later tiles win.
+///
+/// # Examples
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use screenshot_tool::{stitch_tiles, Tile};
+///
+/// let tile = |offset| Tile {
+///     offset,
+///     image: DynamicImage::ImageRgba8(RgbaImage::new(100, 60)),
+/// };
+///
+/// // The last tile is clamped to the bottom of a 150px page
+/// let stitched = stitch_tiles(&[tile(0), tile(60), tile(90)], 100, 150).unwrap();
+/// assert_eq!((stitched.width(), stitched.height()), (100, 150));
+/// ```
+pub fn stitch_tiles(
+    tiles: &[Tile],
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage, ScreenshotError> {
+    let first = tiles
+        .first()
+        .ok_or_else(|| ScreenshotError::CaptureFailed("No tiles were captured".to_string()))?;
+    if width == 0 || height == 0 {
+        return Err(ScreenshotError::CaptureFailed(format!(
+            "Cannot stitch a {width}x{height} page"
+        )));
+    }
+
+    let scale = f64::from(first.image.width()) / f64::from(width);
+    let canvas_height = (f64::from(height) * scale).round() as u32;
+    check_pixel_budget(first.image.width(), canvas_height)?;
+    let mut canvas = RgbaImage::new(first.image.width(), canvas_height);
+
+    for tile in tiles {
+        let y = (f64::from(tile.offset) * scale).round() as i64;
+        imageops::overlay(&mut canvas, &tile.image.to_rgba8(), 0, y);
+    }
+
+    Ok(DynamicImage::ImageRgba8(canvas))
+}
+
+/// Scroll down the page one viewport at a time so lazy content starts loading,
+/// then wait for the images it requested
+///
+/// The page is scrolled back to where it was afterwards.
+pub async fn load_lazy_content(
+    page: &Page,
+    options: &FullPageOptions,
+) -> Result<(), ScreenshotError> {
+    let delay = Duration::from_millis(options.scroll_delay_ms);
+    let initial = scroll_metrics(page).await?;
+    let mut y = 0.0;
+
+    loop {
+        // Re-measured each step: lazy content and infinite scroll grow the page
+        let metrics = scroll_metrics(page).await?;
+        let bottom = metrics.height.min(f64::from(options.max_height));
+        if y + metrics.viewport_height >= bottom {
+            break;
+        }
+
+        y += metrics.viewport_height.max(1.0);
+        scroll_to(page, y).await?;
+        sleep(delay).await;
+    }
+
+    evaluate(page, WAIT_FOR_IMAGES_SCRIPT).await?;
+    scroll_to(page, initial.scroll_y).await?;
+
+    Ok(())
+}
+
+async fn capture_tiles(
+    page: &Page,
+    options: &FullPageOptions,
+) -> Result<(Vec<Tile>, u32, u32), ScreenshotError> {
+    scroll_to(page, 0.0).await?;
+    let metrics = scroll_metrics(page).await?;
+
+    let width = metrics.width.floor().max(1.0) as u32;
+    let height = metrics.height.min(f64::from(options.max_height)).ceil() as u32;
+    let viewport_height = metrics.viewport_height.floor().max(1.0) as u32;
+
+    // Refuse before capturing anything: a high device pixel ratio multiplies
+    // the stitched size in both directions
+    let dpr = metrics.device_pixel_ratio.max(1.0);
+    check_pixel_budget(
+        (f64::from(width) * dpr).ceil() as u32,
+        (f64::from(height) * dpr).ceil() as u32,
+    )?;
+
+    let mut tiles = Vec::new();
+    let mut offset = 0;
+
+    while offset < height {
+        // The browser clamps the last scroll to the bottom of the page
+        let scroll_y = scroll_to(page, f64::from(offset)).await?.round() as u32;
+
+        let clip = CaptureViewport {
+            x: 0.0,
+            y: f64::from(scroll_y),
+            width: f64::from(width),
+            height: f64::from(viewport_height),
+            scale: 1.0,
+        };
+        let data = page
+            .screenshot(
+                ScreenshotParams::builder()
+                    .format(CaptureScreenshotFormat::Png)
+                    .clip(clip)
+                    .build(),
+            )
+            .await
+            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+        let image = image::load_from_memory(&data)
+            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+
+        tiles.push(Tile {
+            offset: scroll_y,
+            image,
+        });
+
+        if tiles.len() == 1 && options.hide_sticky {
+            evaluate(page, HIDE_STICKY_SCRIPT).await?;
+        }
+
+        offset += viewport_height;
+    }
+
+    Ok((tiles, width, height))
+}
+
+fn check_pixel_budget(width: u32, height: u32) -> Result<(), ScreenshotError> {
+    if u64::from(width) * u64::from(height) > MAX_STITCHED_PIXELS {
+        return Err(ScreenshotError::CaptureFailed(format!(
+            "A {width}x{height} stitched image exceeds the {MAX_STITCHED_PIXELS} pixel limit; \
+             lower full_page.max_height or the device scale factor"
+        )));
+    }
+
+    Ok(())
+}
+
+async fn scroll_metrics(page: &Page) -> Result<ScrollMetrics, ScreenshotError> {
+    page.evaluate_expression(
+        "({ \
+         width: document.documentElement.clientWidth, \
+         height: Math.max(document.documentElement.scrollHeight, document.body ? document.body.scrollHeight : 0), \
+         viewport_height: window.innerHeight, \
+         scroll_y: window.scrollY, \
+         device_pixel_ratio: window.devicePixelRatio \
+         })",
+    )
+    .await
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))?
+    .into_value()
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))
+}
+
+/// Scroll to `y`, wait for the next paint and return the actual scroll position
+async fn scroll_to(page: &Page, y: f64) -> Result<f64, ScreenshotError> {
+    page.evaluate_expression(format!(
+        "new Promise(resolve => {{ \
+         window.scrollTo(0, {y}); \
+         requestAnimationFrame(() => requestAnimationFrame(() => resolve(window.scrollY))); \
+         }})"
+    ))
+    .await
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))?
+    .into_value()
+    .map_err(|e| ScreenshotError::PageError(e.to_string()))
+}
+
+async fn evaluate(page: &Page, script: &str) -> Result<(), ScreenshotError> {
+    page.evaluate_expression(script)
+        .await
+        .map_err(|e| ScreenshotError::PageError(e.to_string()))?;
+
+    Ok(())
+}
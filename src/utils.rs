@@ -1,4 +1,12 @@
-use std::collections::HashSet;
+use crate::{
+    parent_domains, EncoderSettings, FilterRequest, FilterSet, FilterVerdict, OptimizationSettings,
+    OutputFormat, PngCompression, PngFilter, ScreenshotError,
+};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::{ColorType, DynamicImage, ImageEncoder};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use url::Url;
 
@@ -8,6 +16,9 @@ pub struct RequestInterceptor {
     pub block_images: bool,
     pub blocked_domains: HashSet<String>,
     pub blocked_resources: HashSet<String>,
+    /// Compiled EasyList / Adblock Plus rules, checked before the built-in heuristics
+    /// while `block_ads` or `block_trackers` is set
+    pub filters: FilterSet,
 }
 
 impl RequestInterceptor {
@@ -19,8 +30,6 @@ impl RequestInterceptor {
         blocked_domains.insert("googlesyndication.com".to_string());
         blocked_domains.insert("doubleclick.net".to_string());
         blocked_domains.insert("googleadservices.com".to_string());
-        blocked_domains.insert("facebook.com".to_string());
-        blocked_domains.insert("twitter.com".to_string());
         blocked_domains.insert("analytics.google.com".to_string());
 
         // Common tracker domains
@@ -42,24 +51,65 @@ impl RequestInterceptor {
             block_images: false,
             blocked_domains,
             blocked_resources,
+            filters: FilterSet::new(),
         }
     }
 
+    /// Build an interceptor honoring the blocking flags in the optimization settings
+    ///
+    /// Filter lists referenced by `filter_lists` are read and compiled here, once.
+    pub fn from_settings(settings: &OptimizationSettings) -> Result<Self, ScreenshotError> {
+        Ok(Self {
+            block_ads: settings.block_ads,
+            block_trackers: settings.block_trackers,
+            block_images: settings.block_images,
+            filters: FilterSet::load(&settings.filter_lists)?,
+            ..Self::new()
+        })
+    }
+
+    /// Whether any blocking rule is active, i.e. interception is worth enabling
+    pub fn is_enabled(&self) -> bool {
+        self.block_ads || self.block_trackers || self.block_images
+    }
+
     pub fn should_block(&self, url: &str, resource_type: &str) -> bool {
+        self.should_block_from(url, resource_type, None)
+    }
+
+    /// Check a request made by the document at `document_url`
+    ///
+    /// The document URL is needed for `$third-party` and `$domain=` filter options.
+    pub fn should_block_from(
+        &self,
+        url: &str,
+        resource_type: &str,
+        document_url: Option<&str>,
+    ) -> bool {
+        if (self.block_ads || self.block_trackers) && !self.filters.is_empty() {
+            if let Some(request) = FilterRequest::new(url, resource_type, document_url) {
+                match self.filters.check(&request) {
+                    FilterVerdict::Block => return true,
+                    FilterVerdict::Allow => return false,
+                    FilterVerdict::NoMatch => {}
+                }
+            }
+        }
+
         if let Ok(parsed_url) = Url::parse(url) {
             if let Some(domain) = parsed_url.domain() {
-                // Check blocked domains
-                if self.blocked_domains.contains(domain) {
+                // Check blocked domains, including their subdomains
+                if parent_domains(domain).any(|d| self.blocked_domains.contains(d)) {
                     return true;
                 }
 
-                // Check for ad/tracker patterns in URL
+                // Check for ad patterns in URL
                 let url_lower = url.to_lowercase();
                 if self.block_ads && self.contains_ad_patterns(&url_lower) {
                     return true;
                 }
 
-                if self.block_trackers && self.contains_tracker_patterns(&url_lower) {
+                if self.block_trackers && self.is_tracker(domain, parsed_url.path()) {
                     return true;
                 }
 
@@ -90,8 +140,13 @@ impl RequestInterceptor {
         ad_patterns.iter().any(|pattern| url.contains(pattern))
     }
 
-    fn contains_tracker_patterns(&self, url: &str) -> bool {
-        let tracker_patterns = [
+    /// Whether a request to `domain` and `path` goes to a tracking service
+    ///
+    /// Tracker names are matched against whole host labels, so first-party
+    /// paths such as `/api/metrics` or hosts such as `cdn.segment-images.com`
+    /// are left alone.
+    fn is_tracker(&self, domain: &str, path: &str) -> bool {
+        let tracker_labels = [
             "analytics",
             "tracking",
             "telemetry",
@@ -100,11 +155,18 @@ impl RequestInterceptor {
             "mixpanel",
             "segment",
             "gtag",
-            "facebook.com/tr",
-            "twitter.com/i/adsct",
         ];
+        let tracker_endpoints = [("facebook.com", "/tr"), ("twitter.com", "/i/adsct")];
 
-        tracker_patterns.iter().any(|pattern| url.contains(pattern))
+        domain
+            .split('.')
+            .any(|label| tracker_labels.contains(&label))
+            || tracker_endpoints.iter().any(|(host, endpoint)| {
+                parent_domains(domain).any(|d| d == *host)
+                    && path
+                        .strip_prefix(endpoint)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
     }
 
     pub fn add_blocked_domain(&mut self, domain: String) {
@@ -403,6 +465,108 @@ pub fn is_same_domain(url1: &str, url2: &str) -> bool {
     }
 }
 
+/// Request headers (a CDP `Headers` object) with `extra` merged in
+///
+/// Extra headers replace original headers of the same name, compared case
+/// insensitively.
+pub fn merge_headers(
+    original: &serde_json::Value,
+    extra: &HashMap<String, String>,
+) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = original
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| !extra.keys().any(|extra| extra.eq_ignore_ascii_case(name)))
+        .map(|(name, value)| {
+            let value = value
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string());
+            (name.clone(), value)
+        })
+        .collect();
+
+    headers.extend(
+        extra
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone())),
+    );
+    headers
+}
+
+/// Re-encode a PNG capture into the requested output format
+///
+/// This is CPU bound and should run on a blocking thread. PNG input is returned
+/// unchanged when no PNG compression settings are configured, as is PDF data.
+pub fn transcode_png(
+    png_data: &[u8],
+    format: &OutputFormat,
+    encoder: &EncoderSettings,
+) -> Result<Vec<u8>, ScreenshotError> {
+    if matches!(format, OutputFormat::Pdf)
+        || (matches!(format, OutputFormat::Png) && !encoder.reencodes_png())
+    {
+        return Ok(png_data.to_vec());
+    }
+
+    let img = image::load_from_memory(png_data)
+        .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+    encode_image(&img, format, encoder)
+}
+
+/// Encode a decoded image in the requested format
+///
+/// WebP is always encoded losslessly; lossy WebP is only produced by Chrome.
+pub fn encode_image(
+    img: &DynamicImage,
+    format: &OutputFormat,
+    encoder: &EncoderSettings,
+) -> Result<Vec<u8>, ScreenshotError> {
+    let mut output = Vec::new();
+
+    let encoded = match format {
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            JpegEncoder::new_with_quality(&mut output, encoder.jpeg_quality)
+                .encode_image(&img.to_rgb8())
+        }
+        OutputFormat::Webp => {
+            let rgba = img.to_rgba8();
+            WebPEncoder::new_lossless(&mut output).encode(
+                rgba.as_raw(),
+                rgba.width(),
+                rgba.height(),
+                ColorType::Rgba8,
+            )
+        }
+        OutputFormat::Png | OutputFormat::Pdf => {
+            let compression = match encoder.png_compression {
+                Some(PngCompression::Fast) => CompressionType::Fast,
+                Some(PngCompression::Default) | None => CompressionType::Default,
+                Some(PngCompression::Best) => CompressionType::Best,
+            };
+            let filter = match encoder.png_filter {
+                Some(PngFilter::NoFilter) => FilterType::NoFilter,
+                Some(PngFilter::Sub) => FilterType::Sub,
+                Some(PngFilter::Up) => FilterType::Up,
+                Some(PngFilter::Avg) => FilterType::Avg,
+                Some(PngFilter::Paeth) => FilterType::Paeth,
+                Some(PngFilter::Adaptive) | None => FilterType::Adaptive,
+            };
+            PngEncoder::new_with_quality(&mut output, compression, filter).write_image(
+                img.as_bytes(),
+                img.width(),
+                img.height(),
+                img.color(),
+            )
+        }
+    };
+
+    encoded.map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+    Ok(output)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -460,4 +624,39 @@ mod tests {
         assert!(!is_same_domain("https://example.com", "https://other.com"));
         assert!(!is_same_domain("invalid-url", "https://example.com"));
     }
+
+    #[test]
+    fn test_transcode_png() {
+        let img = image::RgbaImage::from_pixel(4, 4, image::Rgba([200, 100, 50, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(img)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let settings = EncoderSettings::default();
+        let unchanged = transcode_png(&png, &OutputFormat::Png, &settings).unwrap();
+        assert_eq!(unchanged, png);
+
+        let jpeg = transcode_png(&png, &OutputFormat::Jpeg, &settings).unwrap();
+        assert_eq!(
+            image::guess_format(&jpeg).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+
+        let webp = transcode_png(&png, &OutputFormat::Webp, &settings).unwrap();
+        assert_eq!(
+            image::guess_format(&webp).unwrap(),
+            image::ImageFormat::WebP
+        );
+
+        let best = EncoderSettings {
+            png_compression: Some(PngCompression::Best),
+            ..Default::default()
+        };
+        let recompressed = transcode_png(&png, &OutputFormat::Png, &best).unwrap();
+        assert_eq!(
+            image::load_from_memory(&recompressed).unwrap().to_rgba8(),
+            image::load_from_memory(&png).unwrap().to_rgba8()
+        );
+    }
 }
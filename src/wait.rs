@@ -0,0 +1,299 @@
+//! Wait strategies applied after navigation and before capture
+//!
+//! Single-page applications often render after the `load` event, so a request
+//! can list conditions that must hold before the screenshot is taken. Each
+//! condition has its own timeout and fails with a dedicated `ScreenshotError`.
+
+use crate::ScreenshotError;
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, RequestId,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+/// Timeout applied to a wait condition that does not specify one
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Quiet period used for network idle when none is given
+pub const DEFAULT_NETWORK_IDLE: Duration = Duration::from_millis(500);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Page lifecycle events that can be waited for
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadEvent {
+    /// `DOMContentLoaded` has fired (`document.readyState` is `interactive`)
+    DomContentLoaded,
+    /// The `load` event has fired (`document.readyState` is `complete`)
+    Load,
+}
+
+/// A condition that must be met before the page is captured
+///
+/// Conditions are checked in order. Timeouts are in milliseconds and default
+/// to `DEFAULT_WAIT_TIMEOUT`.
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::WaitCondition;
+///
+/// let conditions: Vec<WaitCondition> = serde_json::from_str(r##"[
+///     {"type": "selector", "selector": "#app .loaded"},
+///     {"type": "network_idle", "idle_ms": 500, "timeout_ms": 15000},
+///     {"type": "function", "expression": "window.appReady === true"}
+/// ]"##).unwrap();
+/// assert_eq!(conditions.len(), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WaitCondition {
+    /// An element matching the CSS selector is present and visible
+    Selector {
+        selector: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// A JavaScript expression evaluates to a truthy value (promises are awaited)
+    Function {
+        expression: String,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// No requests have been in flight for `idle_ms` milliseconds
+    NetworkIdle {
+        #[serde(default)]
+        idle_ms: Option<u64>,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// All web fonts used by the document have loaded
+    FontsLoaded {
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// The given page lifecycle event has fired
+    LoadEvent {
+        event: LoadEvent,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+}
+
+impl WaitCondition {
+    /// Timeout for this condition
+    pub fn timeout(&self) -> Duration {
+        let timeout_ms = match self {
+            WaitCondition::Selector { timeout_ms, .. }
+            | WaitCondition::Function { timeout_ms, .. }
+            | WaitCondition::NetworkIdle { timeout_ms, .. }
+            | WaitCondition::FontsLoaded { timeout_ms }
+            | WaitCondition::LoadEvent { timeout_ms, .. } => *timeout_ms,
+        };
+
+        timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_WAIT_TIMEOUT)
+    }
+
+    /// Whether this condition needs a `NetworkTracker` started before navigation
+    pub fn needs_network_tracking(&self) -> bool {
+        matches!(self, WaitCondition::NetworkIdle { .. })
+    }
+
+    /// Short human readable description used in error messages
+    pub fn describe(&self) -> String {
+        match self {
+            WaitCondition::Selector { selector, .. } => format!("selector '{selector}'"),
+            WaitCondition::Function { expression, .. } => format!("function '{expression}'"),
+            WaitCondition::NetworkIdle { idle_ms, .. } => format!(
+                "network idle for {}ms",
+                idle_ms.unwrap_or(DEFAULT_NETWORK_IDLE.as_millis() as u64)
+            ),
+            WaitCondition::FontsLoaded { .. } => "fonts loaded".to_string(),
+            WaitCondition::LoadEvent { event, .. } => format!("{event:?} event"),
+        }
+    }
+
+    /// Wait until the condition holds or its timeout expires
+    pub async fn wait(
+        &self,
+        page: &Page,
+        network: Option<&NetworkTracker>,
+    ) -> Result<(), ScreenshotError> {
+        let timeout = self.timeout();
+        let deadline = Instant::now() + timeout;
+
+        match self {
+            WaitCondition::Selector { selector, .. } => {
+                let selector = serde_json::to_string(selector)?;
+                let script = format!(
+                    "(() => {{ const el = document.querySelector({selector}); \
+                     if (!el) return false; \
+                     const style = window.getComputedStyle(el); \
+                     const rect = el.getBoundingClientRect(); \
+                     return style.visibility !== 'hidden' && style.display !== 'none' \
+                     && rect.width > 0 && rect.height > 0; }})()"
+                );
+                self.poll_script(page, &script, deadline).await
+            }
+            WaitCondition::Function { expression, .. } => {
+                let script = format!("(async () => Boolean(await ({expression})))()");
+                self.poll_script(page, &script, deadline).await
+            }
+            WaitCondition::FontsLoaded { .. } => {
+                let script = "document.fonts.ready.then(() => document.fonts.status === 'loaded')";
+                self.poll_script(page, script, deadline).await
+            }
+            WaitCondition::LoadEvent { event, .. } => {
+                let script = match event {
+                    LoadEvent::DomContentLoaded => "document.readyState !== 'loading'",
+                    LoadEvent::Load => "document.readyState === 'complete'",
+                };
+                self.poll_script(page, script, deadline).await
+            }
+            WaitCondition::NetworkIdle { idle_ms, .. } => {
+                let network = network.ok_or_else(|| {
+                    ScreenshotError::WaitConditionFailed(
+                        self.describe(),
+                        "network tracking was not started before navigation".to_string(),
+                    )
+                })?;
+                let idle = idle_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(DEFAULT_NETWORK_IDLE);
+
+                while !network.is_idle_for(idle) {
+                    if Instant::now() >= deadline {
+                        return Err(ScreenshotError::WaitTimeout(self.describe(), timeout));
+                    }
+                    sleep(Duration::from_millis(50)).await;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Evaluate a boolean script until it returns true or the deadline passes
+    async fn poll_script(
+        &self,
+        page: &Page,
+        script: &str,
+        deadline: Instant,
+    ) -> Result<(), ScreenshotError> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let evaluation = tokio::time::timeout(remaining, page.evaluate_expression(script));
+
+            match evaluation.await {
+                Ok(Ok(result)) => {
+                    if result.into_value::<bool>().unwrap_or(false) {
+                        return Ok(());
+                    }
+                }
+                Ok(Err(e)) => {
+                    return Err(ScreenshotError::WaitConditionFailed(
+                        self.describe(),
+                        e.to_string(),
+                    ));
+                }
+                Err(_) => {}
+            }
+
+            if Instant::now() >= deadline {
+                return Err(ScreenshotError::WaitTimeout(
+                    self.describe(),
+                    self.timeout(),
+                ));
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NetworkState {
+    in_flight: HashSet<RequestId>,
+    last_activity: Instant,
+}
+
+/// Tracks in-flight requests on a page to detect network idle
+///
+/// Must be started before navigation so the document request itself is seen.
+/// The listener task stops when the tracker is dropped.
+pub struct NetworkTracker {
+    state: Arc<Mutex<NetworkState>>,
+    task: JoinHandle<()>,
+}
+
+impl NetworkTracker {
+    pub async fn start(page: &Page) -> Result<Self, ScreenshotError> {
+        let listener_error = |e: chromiumoxide::error::CdpError| {
+            ScreenshotError::PageError(format!("Failed to listen for network events: {e}"))
+        };
+
+        let mut started = page
+            .event_listener::<EventRequestWillBeSent>()
+            .await
+            .map_err(listener_error)?;
+        let mut finished = page
+            .event_listener::<EventLoadingFinished>()
+            .await
+            .map_err(listener_error)?;
+        let mut failed = page
+            .event_listener::<EventLoadingFailed>()
+            .await
+            .map_err(listener_error)?;
+
+        let state = Arc::new(Mutex::new(NetworkState {
+            in_flight: HashSet::new(),
+            last_activity: Instant::now(),
+        }));
+
+        let task_state = state.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let (request_id, done) = tokio::select! {
+                    Some(event) = started.next() => (event.request_id.clone(), false),
+                    Some(event) = finished.next() => (event.request_id.clone(), true),
+                    Some(event) = failed.next() => (event.request_id.clone(), true),
+                    else => break,
+                };
+
+                let mut state = task_state.lock().unwrap();
+                if done {
+                    state.in_flight.remove(&request_id);
+                } else {
+                    state.in_flight.insert(request_id);
+                }
+                state.last_activity = Instant::now();
+            }
+        });
+
+        Ok(Self { state, task })
+    }
+
+    /// Number of requests currently in flight
+    pub fn in_flight(&self) -> usize {
+        self.state.lock().unwrap().in_flight.len()
+    }
+
+    /// Whether no request has been in flight for at least `idle`
+    pub fn is_idle_for(&self, idle: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        state.in_flight.is_empty() && state.last_activity.elapsed() >= idle
+    }
+}
+
+impl Drop for NetworkTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
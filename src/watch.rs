@@ -0,0 +1,795 @@
+//! Change monitoring with scheduled recapture
+//!
+//! `watch` captures a list of pages on a schedule and compares every capture
+//! with the latest stored version of the same page, keyed like baselines by
+//! URL, device, viewport and result label. Only captures that changed are
+//! stored, as timestamped versions, and each change is sent as a
+//! `ChangeAlert` to the configured sinks.
+//!
+//! Changes are detected either by pixel diff, using the `diff` settings, or
+//! by the Hamming distance between 64-bit perceptual hashes (dHash), which
+//! ignores small rendering differences and tiny edits.
+
+use crate::{
+    diff_result, load_image, BaselineKey, DiffOptions, OutputFormat, ScreenshotError,
+    ScreenshotResult,
+};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, TimeZone, Timelike, Utc};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// File name of the watch store manifest
+pub const WATCH_MANIFEST: &str = "watch.json";
+
+/// Time allowed for a webhook to accept an alert
+pub const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How far ahead a cron schedule is searched for its next run
+const CRON_SEARCH_DAYS: i64 = 366 * 5;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// When `watch` recaptures its pages
+///
+/// Either a fixed interval, written `@every 15m`, or a five-field cron
+/// expression (minute, hour, day of month, month, day of week) evaluated in
+/// UTC. Fields take `*`, numbers, ranges, lists and `/` steps, and month and
+/// weekday names; `@hourly`, `@daily`, `@weekly` and `@monthly` are
+/// shorthands.
+///
+/// # Examples
+///
+/// ```rust
+/// use chrono::{TimeZone, Utc};
+/// use screenshot_tool::Schedule;
+///
+/// let schedule: Schedule = "30 9 * * mon-fri".parse().unwrap();
+/// // Saturday 2024-01-06 12:00 UTC
+/// let after = Utc.with_ymd_and_hms(2024, 1, 6, 12, 0, 0).unwrap();
+/// assert_eq!(
+///     schedule.next_after(after),
+///     Some(Utc.with_ymd_and_hms(2024, 1, 8, 9, 30, 0).unwrap())
+/// );
+///
+/// let every: Schedule = "@every 15m".parse().unwrap();
+/// assert_eq!(every.next_after(after), Some(after + chrono::Duration::minutes(15)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Schedule {
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// First run strictly after `after`, if there is one in the next five years
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => {
+                after.checked_add_signed(ChronoDuration::from_std(*interval).ok()?)
+            }
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let expression = match s.to_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            lower => {
+                if let Some(interval) = lower.strip_prefix("@every") {
+                    let interval = parse_interval(interval.trim())?;
+                    if interval.is_zero() {
+                        return Err("Schedule interval must be greater than 0".to_string());
+                    }
+                    return Ok(Schedule::Every(interval));
+                }
+                s
+            }
+        };
+
+        CronSchedule::parse(expression).map(Schedule::Cron)
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "@every {}s", interval.as_secs()),
+            Schedule::Cron(cron) => write!(f, "{}", cron.expression),
+        }
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+/// Parse `90s`, `15m`, `2h` or `1d`
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Interval '{s}' needs a unit (s, m, h or d)"))?;
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("Invalid interval '{s}'"))?;
+
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("Unknown interval unit '{unit}' in '{s}'")),
+    };
+    let seconds = value
+        .checked_mul(unit_seconds)
+        .ok_or_else(|| format!("Interval '{s}' is too long"))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parsed five-field cron expression
+///
+/// Each field is a bit set of the values it matches. As in cron, a day
+/// matches either the day of month or the day of week when both are
+/// restricted; a field starting with `*`, such as `*/2`, is not restricted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "Cron schedule needs 5 fields (minute hour day month weekday), got '{expression}'"
+            ));
+        };
+
+        // Sunday may be written as 7
+        let mut weekday_set = parse_field(weekdays, 0, 7, WEEKDAY_NAMES, 0)?;
+        if weekday_set & (1 << 7) != 0 {
+            weekday_set = (weekday_set | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59, &[], 0)?,
+            hours: parse_field(hours, 0, 23, &[], 0)?,
+            days: parse_field(days, 1, 31, &[], 1)?,
+            months: parse_field(months, 1, 12, MONTH_NAMES, 1)?,
+            weekdays: weekday_set,
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        })
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit = start + ChronoDuration::days(CRON_SEARCH_DAYS);
+        let mut time = start;
+
+        while time < limit {
+            if !contains(self.months, time.month()) {
+                let (year, month) = if time.month() == 12 {
+                    (time.year() + 1, 1)
+                } else {
+                    (time.year(), time.month() + 1)
+                };
+                time = midnight(NaiveDate::from_ymd_opt(year, month, 1)?);
+                continue;
+            }
+            if !self.matches_day(time) {
+                time = midnight(time.date_naive().succ_opt()?);
+                continue;
+            }
+            if !contains(self.hours, time.hour()) {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+                continue;
+            }
+            if !contains(self.minutes, time.minute()) {
+                time += ChronoDuration::minutes(1);
+                continue;
+            }
+
+            return Some(time);
+        }
+
+        None
+    }
+
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        let day = contains(self.days, time.day());
+        let weekday = contains(self.weekdays, time.weekday().num_days_from_sunday());
+
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn contains(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is valid"))
+}
+
+/// Parse one cron field into a bit set of the values between `min` and `max`
+///
+/// `names` are accepted in place of numbers, the first standing for `first`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], first: u32) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_lowercase();
+        let value = match names.iter().position(|name| *name == lower) {
+            Some(index) => index as u32 + first,
+            None => s
+                .parse()
+                .map_err(|_| format!("Invalid value '{s}' in cron field '{field}'"))?,
+        };
+        if !(min..=max).contains(&value) {
+            return Err(format!(
+                "Value {value} in cron field '{field}' is outside {min}-{max}"
+            ));
+        }
+        Ok(value)
+    };
+
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("Invalid step '{step}' in cron field '{field}'"))?;
+                if step == 0 {
+                    return Err(format!(
+                        "Step must be greater than 0 in cron field '{field}'"
+                    ));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else if step > 1 {
+            // `5/15` runs from 5 to the end of the range
+            (value(range)?, max)
+        } else {
+            let single = value(range)?;
+            (single, single)
+        };
+        if start > end {
+            return Err(format!(
+                "Range {start}-{end} in cron field '{field}' is reversed"
+            ));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+/// How a capture is compared with the previous version of the page
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeDetection {
+    /// Pixel diff with the `diff` threshold, ignore regions and failure
+    /// threshold
+    #[default]
+    Pixel,
+    /// Hamming distance between perceptual hashes
+    Hash,
+}
+
+impl std::str::FromStr for ChangeDetection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pixel" => Ok(ChangeDetection::Pixel),
+            "hash" => Ok(ChangeDetection::Hash),
+            _ => Err(format!("Unknown change detection: {s}")),
+        }
+    }
+}
+
+/// 64-bit difference hash (dHash) of an image
+///
+/// Similar images have hashes a small Hamming distance apart.
+pub fn perceptual_hash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+
+    hash
+}
+
+/// Where change alerts are sent
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AlertSink {
+    /// POST each alert as JSON
+    Webhook { url: String },
+    /// Append each alert as a line of JSON
+    File { path: PathBuf },
+}
+
+impl AlertSink {
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        match self {
+            AlertSink::Webhook { url } => {
+                url::Url::parse(url).map_err(|e| {
+                    ScreenshotError::ConfigurationError(format!("Invalid webhook URL '{url}': {e}"))
+                })?;
+            }
+            AlertSink::File { path } => {
+                if path.as_os_str().is_empty() {
+                    return Err(ScreenshotError::ConfigurationError(
+                        "Alert file path must not be empty".to_string(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// HTTP client for webhook sinks, shared by every alert of a watch
+    pub fn client() -> Result<reqwest::Client, ScreenshotError> {
+        reqwest::Client::builder()
+            .timeout(ALERT_TIMEOUT)
+            .build()
+            .map_err(|e| ScreenshotError::NetworkError(e.to_string()))
+    }
+
+    /// Deliver an alert, posting webhooks with `client`
+    pub async fn send(
+        &self,
+        client: &reqwest::Client,
+        alert: &ChangeAlert,
+    ) -> Result<(), ScreenshotError> {
+        match self {
+            AlertSink::Webhook { url } => {
+                client
+                    .post(url)
+                    .json(alert)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| ScreenshotError::NetworkError(format!("Webhook {url}: {e}")))?;
+            }
+            AlertSink::File { path } => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                let mut line = serde_json::to_string(alert)?;
+                line.push('\n');
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(line.as_bytes()).await?;
+                // Tokio writes in the background; wait for the line to land
+                file.flush().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Change monitoring settings
+///
+/// # Examples
+///
+/// ```rust
+/// use screenshot_tool::{AlertSink, ChangeDetection, WatchOptions};
+///
+/// let options: WatchOptions = serde_json::from_str(r#"{
+///     "schedule": "0 */6 * * *",
+///     "detection": "hash",
+///     "alerts": [
+///         {"type": "webhook", "url": "https://hooks.example.com/changes"},
+///         {"type": "file", "path": "changes.jsonl"}
+///     ]
+/// }"#).unwrap();
+/// assert_eq!(options.detection, ChangeDetection::Hash);
+/// assert_eq!(options.hash_distance, 5);
+/// assert!(options.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatchOptions {
+    /// When pages are recaptured
+    pub schedule: Option<Schedule>,
+    pub detection: ChangeDetection,
+    /// Hamming distance between perceptual hashes, out of 64 bits, above
+    /// which a page counts as changed
+    pub hash_distance: u32,
+    /// Sinks every change alert is sent to
+    pub alerts: Vec<AlertSink>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            schedule: None,
+            detection: ChangeDetection::Pixel,
+            hash_distance: 5,
+            alerts: Vec::new(),
+        }
+    }
+}
+
+impl WatchOptions {
+    pub fn validate(&self) -> Result<(), ScreenshotError> {
+        if self.hash_distance > 64 {
+            return Err(ScreenshotError::ConfigurationError(format!(
+                "Hash distance must be at most 64, got {}",
+                self.hash_distance
+            )));
+        }
+        for sink in &self.alerts {
+            sink.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// How a capture differs from the previous version, if it changed
+    ///
+    /// `current_hash` is the capture's perceptual hash; the pixel diff
+    /// decodes the previous version's image from `previous_path`.
+    pub fn detect(
+        &self,
+        result: &ScreenshotResult,
+        current_hash: u64,
+        previous: &WatchVersion,
+        previous_path: &Path,
+        diff: &DiffOptions,
+    ) -> Result<Option<PageChange>, ScreenshotError> {
+        match self.detection {
+            ChangeDetection::Pixel => {
+                let image_diff = diff_result(result, &load_image(previous_path)?, diff)?;
+                Ok(image_diff
+                    .exceeds(diff.fail_threshold)
+                    .then_some(PageChange::Pixel {
+                        mismatch_percentage: image_diff.mismatch_percentage,
+                    }))
+            }
+            ChangeDetection::Hash => {
+                let distance = (current_hash ^ previous.hash).count_ones();
+                Ok((distance > self.hash_distance).then_some(PageChange::Hash { distance }))
+            }
+        }
+    }
+}
+
+/// How much a page changed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "detection", rename_all = "lowercase")]
+pub enum PageChange {
+    Pixel { mismatch_percentage: f64 },
+    Hash { distance: u32 },
+}
+
+impl std::fmt::Display for PageChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageChange::Pixel {
+                mismatch_percentage,
+            } => write!(f, "{mismatch_percentage:.3}% of pixels changed"),
+            PageChange::Hash { distance } => write!(f, "perceptual hash distance {distance}"),
+        }
+    }
+}
+
+/// Notification that a watched page changed
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeAlert {
+    pub id: String,
+    #[serde(flatten)]
+    pub key: BaselineKey,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+    pub change: PageChange,
+    /// Stored image of the previous version
+    pub previous: PathBuf,
+    /// Stored image of the new version
+    pub current: PathBuf,
+}
+
+/// A stored capture of a watched page
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WatchVersion {
+    #[serde(flatten)]
+    pub key: BaselineKey,
+    /// Image file, relative to the store directory
+    pub file: String,
+    pub captured_at: DateTime<Utc>,
+    /// Perceptual hash of the image
+    pub hash: u64,
+}
+
+/// Outcome of one capture round
+#[derive(Debug, Default)]
+pub struct WatchRound {
+    /// Pages captured for the first time
+    pub first_seen: usize,
+    pub unchanged: usize,
+    /// Pages that could not be captured or compared
+    pub failed: usize,
+    pub alerts: Vec<ChangeAlert>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Manifest {
+    versions: Vec<WatchVersion>,
+}
+
+/// A directory of timestamped versions of watched pages
+///
+/// Each page's versions live in a directory named after its key, with a
+/// `watch.json` manifest at the root.
+#[derive(Debug)]
+pub struct WatchStore {
+    root: PathBuf,
+    versions: BTreeMap<BaselineKey, Vec<WatchVersion>>,
+}
+
+impl WatchStore {
+    /// Open the store in `root`, which need not exist yet
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, ScreenshotError> {
+        let root = root.into();
+        let manifest_path = root.join(WATCH_MANIFEST);
+
+        let manifest: Manifest = if manifest_path.exists() {
+            let content = std::fs::read_to_string(&manifest_path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                ScreenshotError::ConfigurationError(format!(
+                    "Invalid watch manifest {}: {e}",
+                    manifest_path.display()
+                ))
+            })?
+        } else {
+            Manifest::default()
+        };
+
+        let mut versions: BTreeMap<BaselineKey, Vec<WatchVersion>> = BTreeMap::new();
+        for version in manifest.versions {
+            versions
+                .entry(version.key.clone())
+                .or_default()
+                .push(version);
+        }
+        for history in versions.values_mut() {
+            history.sort_by_key(|version| version.captured_at);
+        }
+
+        Ok(Self { root, versions })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Number of watched pages
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    /// Stored versions of a page, oldest first
+    pub fn history(&self, key: &BaselineKey) -> &[WatchVersion] {
+        self.versions
+            .get(key)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn latest(&self, key: &BaselineKey) -> Option<&WatchVersion> {
+        self.history(key).last()
+    }
+
+    /// Absolute path of a version's image
+    pub fn image_path(&self, version: &WatchVersion) -> PathBuf {
+        self.root.join(&version.file)
+    }
+
+    /// Store a capture as the newest version of its page
+    pub fn record(
+        &mut self,
+        key: BaselineKey,
+        result: &ScreenshotResult,
+        hash: u64,
+        captured_at: DateTime<Utc>,
+    ) -> Result<&WatchVersion, ScreenshotError> {
+        let directory = key.file_stem();
+        std::fs::create_dir_all(self.root.join(&directory))?;
+
+        let stamp = captured_at.format("%Y%m%dT%H%M%SZ");
+        let extension = result.format.extension();
+        let mut file = format!("{directory}/{stamp}.{extension}");
+        let mut number = 2;
+        while self.root.join(&file).exists() {
+            file = format!("{directory}/{stamp}-{number}.{extension}");
+            number += 1;
+        }
+        std::fs::write(self.root.join(&file), &result.data)?;
+
+        let history = self.versions.entry(key.clone()).or_default();
+        history.push(WatchVersion {
+            key,
+            file,
+            captured_at,
+            hash,
+        });
+
+        Ok(history.last().expect("version was just added"))
+    }
+
+    /// Write the manifest
+    pub fn save(&self) -> Result<(), ScreenshotError> {
+        std::fs::create_dir_all(&self.root)?;
+        let manifest = Manifest {
+            versions: self.versions.values().flatten().cloned().collect(),
+        };
+        std::fs::write(
+            self.root.join(WATCH_MANIFEST),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Compare a round of captures with the latest versions and store the
+    /// ones that changed or are new
+    ///
+    /// `device` is the preset the results were captured with. Alerts are
+    /// returned for sending, and the manifest is saved. A page whose capture
+    /// cannot be decoded, compared or stored counts as failed without ending
+    /// the round. PDF captures are rejected, as they cannot be compared. This
+    /// decodes and compares images synchronously; run it on a blocking thread
+    /// from async code.
+    pub fn record_round(
+        &mut self,
+        results: &[ScreenshotResult],
+        device: Option<&str>,
+        options: &WatchOptions,
+        diff: &DiffOptions,
+    ) -> Result<WatchRound, ScreenshotError> {
+        if results
+            .iter()
+            .any(|result| result.format == OutputFormat::Pdf)
+        {
+            return Err(ScreenshotError::ConfigurationError(
+                "Watch mode compares images and cannot watch PDF output".to_string(),
+            ));
+        }
+
+        let mut round = WatchRound::default();
+        let captured_at = Utc::now();
+
+        for result in results {
+            if !result.success {
+                warn!("Failed to screenshot {}: {:?}", result.url, result.error);
+                round.failed += 1;
+                continue;
+            }
+
+            let key = BaselineKey::for_result(result, device);
+            if let Err(e) = self.record_page(key, result, captured_at, options, diff, &mut round) {
+                warn!("Failed to check {} for changes: {e}", result.url);
+                round.failed += 1;
+            }
+        }
+
+        self.save()?;
+
+        Ok(round)
+    }
+
+    /// Compare one successful capture with its latest version, adding the
+    /// outcome to `round`
+    fn record_page(
+        &mut self,
+        key: BaselineKey,
+        result: &ScreenshotResult,
+        captured_at: DateTime<Utc>,
+        options: &WatchOptions,
+        diff: &DiffOptions,
+        round: &mut WatchRound,
+    ) -> Result<(), ScreenshotError> {
+        let image = image::load_from_memory(&result.data)
+            .map_err(|e| ScreenshotError::CaptureFailed(format!("Cannot decode capture: {e}")))?;
+        let hash = perceptual_hash(&image);
+
+        let Some(previous) = self.latest(&key).cloned() else {
+            let version = self.record(key, result, hash, captured_at)?;
+            info!("Started watching {} ({})", result.url, version.file);
+            round.first_seen += 1;
+            return Ok(());
+        };
+
+        let previous_path = self.image_path(&previous);
+        let Some(change) = options.detect(result, hash, &previous, &previous_path, diff)? else {
+            round.unchanged += 1;
+            return Ok(());
+        };
+
+        let file = self
+            .record(key.clone(), result, hash, captured_at)?
+            .file
+            .clone();
+        let current_path = self.root.join(file);
+        round.alerts.push(ChangeAlert {
+            id: uuid::Uuid::new_v4().to_string(),
+            message: format!("{} changed: {change}", result.url),
+            key,
+            timestamp: captured_at,
+            change,
+            previous: previous_path,
+            current: current_path,
+        });
+
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Config, ScreenshotError, ScreenshotRequest, ScreenshotResult, ScreenshotService};
+use crate::{
+    Config, Metrics, ScreenshotError, ScreenshotRequest, ScreenshotResult, ScreenshotService,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
@@ -29,33 +32,14 @@ impl ScreenshotWorker {
     pub async fn run(
         &self,
         mut requests: mpsc::Receiver<ScreenshotRequest>,
-        results: mpsc::Sender<ScreenshotResult>,
+        results: mpsc::Sender<Vec<ScreenshotResult>>,
     ) {
         info!("Starting screenshot worker {}", self.id);
         self.is_running
             .store(true, std::sync::atomic::Ordering::Relaxed);
 
         while let Some(request) = requests.recv().await {
-            let result = self.process_request(request).await;
-
-            if result.success {
-                self.processed_count
-                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                debug!(
-                    "Worker {} successfully processed request {}",
-                    self.id, result.request_id
-                );
-            } else {
-                self.error_count
-                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                warn!(
-                    "Worker {} failed to process request {}: {:?}",
-                    self.id, result.request_id, result.error
-                );
-            }
-
-            if let Err(e) = results.send(result).await {
-                error!("Worker {} failed to send result: {}", self.id, e);
+            if !self.process_and_send(request, &results).await {
                 break;
             }
         }
@@ -68,7 +52,7 @@ impl ScreenshotWorker {
     pub async fn run_with_shared_receiver(
         &self,
         requests: Arc<Mutex<mpsc::Receiver<ScreenshotRequest>>>,
-        results: mpsc::Sender<ScreenshotResult>,
+        results: mpsc::Sender<Vec<ScreenshotResult>>,
     ) {
         info!("Starting screenshot worker {}", self.id);
         self.is_running
@@ -82,26 +66,7 @@ impl ScreenshotWorker {
 
             match request {
                 Some(request) => {
-                    let result = self.process_request(request).await;
-
-                    if result.success {
-                        self.processed_count
-                            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                        debug!(
-                            "Worker {} successfully processed request {}",
-                            self.id, result.request_id
-                        );
-                    } else {
-                        self.error_count
-                            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                        warn!(
-                            "Worker {} failed to process request {}: {:?}",
-                            self.id, result.request_id, result.error
-                        );
-                    }
-
-                    if let Err(e) = results.send(result).await {
-                        error!("Worker {} failed to send result: {}", self.id, e);
+                    if !self.process_and_send(request, &results).await {
                         break;
                     }
                 }
@@ -114,14 +79,50 @@ impl ScreenshotWorker {
         info!("Screenshot worker {} stopped", self.id);
     }
 
-    async fn process_request(&self, request: ScreenshotRequest) -> ScreenshotResult {
+    /// Process a request and send its results together, returning `false`
+    /// once the result channel is closed
+    async fn process_and_send(
+        &self,
+        request: ScreenshotRequest,
+        results: &mpsc::Sender<Vec<ScreenshotResult>>,
+    ) -> bool {
+        let request_results = self.process_request(request).await;
+
+        for result in &request_results {
+            if result.success {
+                self.processed_count
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                debug!(
+                    "Worker {} successfully processed request {}",
+                    self.id, result.request_id
+                );
+            } else {
+                self.error_count
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                warn!(
+                    "Worker {} failed to process request {}: {:?}",
+                    self.id, result.request_id, result.error
+                );
+            }
+        }
+
+        if let Err(e) = results.send(request_results).await {
+            error!("Worker {} failed to send result: {}", self.id, e);
+            return false;
+        }
+
+        true
+    }
+
+    /// Capture a request, producing one result per requested viewport and element
+    async fn process_request(&self, request: ScreenshotRequest) -> Vec<ScreenshotResult> {
         debug!(
             "Worker {} processing request {} for URL: {}",
             self.id, request.id, request.url
         );
 
-        match self.service.screenshot_single(request.clone()).await {
-            Ok(result) => result,
+        match self.service.process_requests(vec![request.clone()]).await {
+            Ok(results) => results,
             Err(e) => {
                 error!(
                     "Worker {} failed to process request {}: {}",
@@ -129,24 +130,32 @@ impl ScreenshotWorker {
                 );
 
                 // Create error result
-                ScreenshotResult {
+                vec![ScreenshotResult {
+                    format: request.output_format(&self.config.output_format),
                     request_id: request.id,
                     url: request.url,
                     data: Vec::new(),
-                    format: self.config.output_format.clone(),
                     timestamp: std::time::SystemTime::now(),
                     duration: Duration::from_secs(0),
                     success: false,
                     error: Some(e),
                     metadata: crate::ScreenshotMetadata {
                         viewport: self.config.viewport.clone(),
+                        viewport_label: None,
+                        element_index: None,
                         page_title: None,
                         final_url: None,
                         response_status: None,
+                        redirect_chain: Vec::new(),
+                        response_headers: HashMap::new(),
+                        mime_type: None,
                         file_size: 0,
+                        encoding: None,
                         browser_instance_id: 0,
+                        blocked_requests: 0,
+                        ignore_regions: Vec::new(),
                     },
-                }
+                }]
             }
         }
     }
@@ -187,12 +196,15 @@ pub struct WorkerStats {
 pub struct WorkerPool {
     workers: Vec<ScreenshotWorker>,
     request_sender: mpsc::Sender<ScreenshotRequest>,
-    result_receiver: mpsc::Receiver<ScreenshotResult>,
+    result_receiver: mpsc::Receiver<Vec<ScreenshotResult>>,
+    metrics: Arc<Metrics>,
+    pending: Arc<std::sync::atomic::AtomicUsize>,
 }
 
 impl WorkerPool {
     pub fn new(config: Config, service: Arc<ScreenshotService>) -> Self {
         let worker_count = config.browser_pool_size;
+        let metrics = service.metrics();
         let (request_sender, request_receiver) = mpsc::channel(1000);
         let (result_sender, result_receiver) = mpsc::channel(1000);
 
@@ -222,6 +234,8 @@ impl WorkerPool {
             workers,
             request_sender,
             result_receiver,
+            metrics,
+            pending: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
         }
     }
 
@@ -229,11 +243,43 @@ impl WorkerPool {
         self.request_sender
             .send(request)
             .await
-            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))
+            .map_err(|e| ScreenshotError::CaptureFailed(e.to_string()))?;
+
+        let pending = self
+            .pending
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
+        self.metrics.set_queue_size(pending);
+
+        Ok(())
+    }
+
+    /// Results of the next completed request
+    ///
+    /// A request yields several results when it captures multiple viewports
+    /// or elements, and none when its elements were not found and skipped.
+    pub async fn get_results(&mut self) -> Option<Vec<ScreenshotResult>> {
+        let results = self.result_receiver.recv().await;
+
+        if results.is_some() {
+            // Never wrap below zero, even for a result that was not counted
+            let pending = self
+                .pending
+                .fetch_update(
+                    std::sync::atomic::Ordering::Relaxed,
+                    std::sync::atomic::Ordering::Relaxed,
+                    |pending| pending.checked_sub(1),
+                )
+                .map_or(0, |previous| previous - 1);
+            self.metrics.set_queue_size(pending);
+        }
+
+        results
     }
 
-    pub async fn get_result(&mut self) -> Option<ScreenshotResult> {
-        self.result_receiver.recv().await
+    /// Number of submitted requests whose results have not been collected yet
+    pub fn pending_requests(&self) -> usize {
+        self.pending.load(std::sync::atomic::Ordering::Relaxed)
     }
 
     pub fn get_worker_stats(&self) -> Vec<WorkerStats> {
@@ -295,8 +341,8 @@ impl BatchProcessor {
 
         // Collect results
         for _ in 0..total_requests {
-            if let Some(result) = self.worker_pool.get_result().await {
-                results.push(result);
+            if let Some(request_results) = self.worker_pool.get_results().await {
+                results.extend(request_results);
             }
         }
 